@@ -94,8 +94,17 @@ impl Client {
         self.make_request(Method::WalletList, None::<Box<RawValue>>)
     }
 
-    pub fn wallet_load(&self, descriptor: String, name: String) -> Result<response::Wallet, Error> {
-        let req = request::WalletLoad { descriptor, name };
+    pub fn wallet_load(
+        &self,
+        descriptor: String,
+        name: String,
+        gap_limit: Option<u32>,
+    ) -> Result<response::Wallet, Error> {
+        let req = request::WalletLoad {
+            descriptor,
+            name,
+            gap_limit,
+        };
         self.make_request(Method::WalletLoad, Some(req))
     }
 
@@ -140,15 +149,32 @@ impl Client {
         self.make_request(Method::WalletAddress, Some(req))
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn wallet_send_many(
         &self,
         name: String,
         addressees: Vec<UnvalidatedRecipient>,
         fee_rate: Option<f32>,
+        fee_target: Option<u32>,
+        coin_selection: Option<String>,
+        add_utxos: Vec<String>,
+        exclude_utxos: Vec<String>,
+        utxos_only: bool,
+        min_confirmations: Option<u32>,
+        spend_unconfirmed: Option<String>,
+        lock_blocks: Option<u32>,
     ) -> Result<response::Pset, Error> {
         let req = request::WalletSendMany {
             addressees: addressees.into_iter().map(unvalidate_addressee).collect(),
             fee_rate,
+            fee_target,
+            coin_selection,
+            add_utxos: Some(add_utxos),
+            exclude_utxos: Some(exclude_utxos),
+            utxos_only: Some(utxos_only),
+            min_confirmations,
+            spend_unconfirmed,
+            lock_blocks,
             name,
         };
         self.make_request(Method::WalletSendMany, Some(req))
@@ -159,10 +185,16 @@ impl Client {
         name: String,
         address: String,
         fee_rate: Option<f32>,
+        fee_target: Option<u32>,
+        min_confirmations: Option<u32>,
+        spend_unconfirmed: Option<String>,
     ) -> Result<response::Pset, Error> {
         let req = request::WalletDrain {
             address,
             fee_rate,
+            fee_target,
+            min_confirmations,
+            spend_unconfirmed,
             name,
         };
         self.make_request(Method::WalletDrain, Some(req))
@@ -320,6 +352,71 @@ impl Client {
         self.make_request(Method::WalletSetAddrMemo, Some(req))
     }
 
+    pub fn wallet_lock_utxos(
+        &self,
+        name: String,
+        utxos: Vec<String>,
+        blocks: Option<u32>,
+    ) -> Result<response::Empty, Error> {
+        let req = request::WalletLockUtxos {
+            name,
+            utxos,
+            blocks,
+        };
+        self.make_request(Method::WalletLockUtxos, Some(req))
+    }
+
+    pub fn wallet_unlock_utxos(
+        &self,
+        name: String,
+        utxos: Vec<String>,
+    ) -> Result<response::Empty, Error> {
+        let req = request::WalletUnlockUtxos { name, utxos };
+        self.make_request(Method::WalletUnlockUtxos, Some(req))
+    }
+
+    pub fn wallet_freeze_utxos(
+        &self,
+        name: String,
+        utxos: Vec<String>,
+    ) -> Result<response::Empty, Error> {
+        let req = request::WalletFreezeUtxos { name, utxos };
+        self.make_request(Method::WalletFreezeUtxos, Some(req))
+    }
+
+    pub fn wallet_bump_fee(
+        &self,
+        name: String,
+        txid: String,
+        fee_rate: f32,
+        pset: Option<String>,
+        addressees: Vec<UnvalidatedRecipient>,
+    ) -> Result<response::Pset, Error> {
+        let req = request::WalletBumpFee {
+            name,
+            txid,
+            fee_rate,
+            pset,
+            addressees: addressees.into_iter().map(unvalidate_addressee).collect(),
+        };
+        self.make_request(Method::WalletBumpFee, Some(req))
+    }
+
+    pub fn wallet_rescan(&self, name: String, gap_limit: u32) -> Result<response::Empty, Error> {
+        let req = request::WalletRescan { name, gap_limit };
+        self.make_request(Method::WalletRescan, Some(req))
+    }
+
+    pub fn wallet_export(&self, name: String) -> Result<response::WalletExport, Error> {
+        let req = request::WalletExport { name };
+        self.make_request(Method::WalletExport, Some(req))
+    }
+
+    pub fn wallet_import(&self, name: String, backup: String) -> Result<response::Empty, Error> {
+        let req = request::WalletImport { name, backup };
+        self.make_request(Method::WalletImport, Some(req))
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn wallet_issue(
         &self,
@@ -448,6 +545,11 @@ impl Client {
         self.make_request(Method::SignerJadeId, Some(req))
     }
 
+    pub fn fee_estimates(&self, targets: Vec<u32>) -> Result<response::FeeEstimates, Error> {
+        let req = request::FeeEstimates { targets };
+        self.make_request(Method::FeeEstimates, Some(req))
+    }
+
     pub fn scan(&self) -> Result<Value, Error> {
         self.make_request(Method::Scan, None::<Box<RawValue>>)
     }
@@ -1,7 +1,9 @@
 use lwk_jade::Network as JadeNetwork;
 use lwk_jade::TIMEOUT;
 use lwk_wollet::elements::AssetId;
-use lwk_wollet::ElementsNetwork;
+use lwk_wollet::{
+    ElectrumClient, ElectrumOptions, ElectrumUrl, ElementsNetwork, EsploraClient, MultiBackend,
+};
 use std::fs;
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -16,6 +18,17 @@ pub struct Config {
     pub addr: SocketAddr,
     pub datadir: PathBuf,
     pub electrum_url: String,
+
+    /// Servers used when `electrum_url` fails, urls starting with `http` are Esplora servers,
+    /// the others are Electrum servers using `tls` and `validate_domain`
+    pub backup_servers: Vec<String>,
+
+    /// Compare tips and scan results between the servers, to detect lagging or lying ones
+    pub cross_check: bool,
+
+    /// SOCKS5 proxy used to connect to the servers, for instance `127.0.0.1:9050` for Tor
+    pub socks5: Option<String>,
+
     pub network: ElementsNetwork,
     pub tls: bool,
     pub validate_domain: bool,
@@ -36,6 +49,9 @@ impl Config {
             addr: consts::DEFAULT_ADDR.into(),
             datadir,
             electrum_url: "blockstream.info:465".into(),
+            backup_servers: vec![],
+            cross_check: false,
+            socks5: None,
             network: ElementsNetwork::LiquidTestnet,
             tls: true,
             validate_domain: true,
@@ -52,6 +68,9 @@ impl Config {
             addr: consts::DEFAULT_ADDR.into(),
             datadir,
             electrum_url: "blockstream.info:995".into(),
+            backup_servers: vec![],
+            cross_check: false,
+            socks5: None,
             network: ElementsNetwork::Liquid,
             tls: true,
             validate_domain: true,
@@ -72,6 +91,9 @@ impl Config {
             addr: consts::DEFAULT_ADDR.into(),
             datadir,
             electrum_url: "".into(),
+            backup_servers: vec![],
+            cross_check: false,
+            socks5: None,
             network: ElementsNetwork::ElementsRegtest { policy_asset },
             tls: false,
             validate_domain: false,
@@ -119,12 +141,41 @@ impl Config {
         matches!(self.network, ElementsNetwork::Liquid)
     }
 
-    fn electrum_url(&self) -> lwk_wollet::ElectrumUrl {
-        lwk_wollet::ElectrumUrl::new(&self.electrum_url, self.tls, self.validate_domain)
-    }
-
-    pub fn electrum_client(&self) -> Result<lwk_wollet::ElectrumClient, Error> {
-        // TODO cache it instead of recreating every time
-        Ok(lwk_wollet::ElectrumClient::new(&self.electrum_url())?)
+    /// The backend using `electrum_url` and failing over to `backup_servers`
+    ///
+    /// Electrum servers that can't be reached are skipped, it fails only if no server is usable.
+    pub fn blockchain_backend(&self) -> Result<MultiBackend, Error> {
+        let mut backend = MultiBackend::new();
+        let mut added = 0;
+        for url in std::iter::once(&self.electrum_url).chain(self.backup_servers.iter()) {
+            if url.starts_with("http://") || url.starts_with("https://") {
+                let mut builder = EsploraClient::builder(url);
+                if let Some(socks5) = self.socks5.as_ref() {
+                    builder = builder.socks5(socks5);
+                }
+                backend = backend.add(url, builder.build()?);
+            } else {
+                let electrum_url = ElectrumUrl::new(url, self.tls, self.validate_domain);
+                let options = ElectrumOptions {
+                    socks5: self.socks5.clone(),
+                    timeout: None,
+                };
+                match ElectrumClient::new_with_options(&electrum_url, &options) {
+                    Ok(client) => backend = backend.add(url, client.with_incremental_sync()),
+                    Err(e) => {
+                        tracing::warn!("Cannot connect to {url}: {e}");
+                        continue;
+                    }
+                }
+            }
+            added += 1;
+        }
+        if added == 0 {
+            return Err(lwk_wollet::Error::NoBackend.into());
+        }
+        if self.cross_check {
+            backend = backend.with_cross_check();
+        }
+        Ok(backend)
     }
 }
@@ -11,7 +11,7 @@
 //!
 //! All the requests and responses data model are in the [`lwk_rpc_model`] crate.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::net::SocketAddr;
 use std::num::NonZeroU8;
 use std::str::FromStr;
@@ -36,12 +36,14 @@ use lwk_wollet::bitcoin::XKeyIdentifier;
 use lwk_wollet::elements::encode::serialize;
 use lwk_wollet::elements::hex::{FromHex, ToHex};
 use lwk_wollet::elements::pset::PartiallySignedTransaction;
-use lwk_wollet::elements::{Address, AssetId, Txid};
+use lwk_wollet::elements::{Address, AssetId, OutPoint, Txid};
 use lwk_wollet::elements_miniscript::descriptor::{Descriptor, DescriptorType, WshInner};
 use lwk_wollet::elements_miniscript::miniscript::decode::Terminal;
 use lwk_wollet::elements_miniscript::{DescriptorPublicKey, ForEachKey};
-use lwk_wollet::{full_scan_with_electrum_client, Wollet};
-use lwk_wollet::{BlockchainBackend, WolletDescriptor};
+use lwk_wollet::{
+    BlockchainBackend, CoinSelection, UtxoLock, WolletDescriptor, DEFAULT_LOCK_BLOCKS,
+};
+use lwk_wollet::{MultiBackend, Wollet, WolletEvent};
 use serde_json::Value;
 
 use crate::explorer::{get_registry_data, get_tx};
@@ -151,33 +153,89 @@ impl App {
         let state_scanning = state.clone();
         let scanning_interval = self.config.scanning_interval;
         let stop_interval = Duration::from_millis(100);
+        let notification_interval = Duration::from_secs(1);
         let mut interval = Duration::ZERO; // Do not wait in the first scan loop
-        let scanning_handle = std::thread::spawn(move || 'scan: loop {
-            // Sleep for scanning_interval, but check stop signal every stop_interval
-            'stop: loop {
-                if !is_scanning.load(Ordering::Relaxed) {
-                    break 'scan;
-                }
-                if interval == Duration::ZERO
-                    || state_scanning
-                        .lock()
-                        .map(|s| s.interrupt_wait)
-                        .unwrap_or(false)
-                {
-                    interval = scanning_interval; // Reset wait interval
-                    break 'stop;
+        let scanning_handle = std::thread::spawn(move || {
+            // Kept across scans so that the server pushes the changes of the subscribed scripts
+            let mut backend: Option<MultiBackend> = None;
+            let mut since_notification_check = Duration::ZERO;
+            'scan: loop {
+                // Sleep for scanning_interval, but check stop signal every stop_interval and
+                // server notifications every notification_interval
+                'stop: loop {
+                    if !is_scanning.load(Ordering::Relaxed) {
+                        break 'scan;
+                    }
+                    if interval == Duration::ZERO
+                        || state_scanning
+                            .lock()
+                            .map(|s| s.interrupt_wait)
+                            .unwrap_or(false)
+                    {
+                        interval = scanning_interval; // Reset wait interval
+                        break 'stop;
+                    }
+                    std::thread::sleep(stop_interval);
+                    interval = interval.saturating_sub(stop_interval);
+
+                    since_notification_check += stop_interval;
+                    if since_notification_check >= notification_interval {
+                        since_notification_check = Duration::ZERO;
+                        if let Some(client) = backend.as_mut() {
+                            match client.has_notifications() {
+                                Ok(true) => {
+                                    interval = scanning_interval;
+                                    break 'stop;
+                                }
+                                Ok(false) => {}
+                                Err(_) => backend = None, // Reconnect at the next scan
+                            }
+                        }
+                    }
                 }
-                std::thread::sleep(stop_interval);
-                interval = interval.saturating_sub(stop_interval);
-            }
 
-            if let Ok(mut s) = state_scanning.lock() {
+                let mut s = match state_scanning.lock() {
+                    Ok(s) => s,
+                    Err(_) => continue,
+                };
                 s.interrupt_wait = false;
                 s.scan_loops_started += 1;
-                if let Ok(mut electrum_client) = s.config.electrum_client() {
-                    for (_name, wollet) in s.wollets.iter_mut() {
+                if backend.is_none() {
+                    backend = match s.config.blockchain_backend() {
+                        Ok(backend) => Some(backend),
+                        Err(e) => {
+                            tracing::warn!("No server available for scanning: {e}");
+                            None
+                        }
+                    };
+                }
+                if let Some(client) = backend.as_mut() {
+                    let mut failed = false;
+                    for (name, wollet) in s.wollets.iter_mut() {
                         // TODO: release lock when doing network calls
-                        let _ = full_scan_with_electrum_client(wollet, &mut electrum_client);
+                        let result = client.full_scan(wollet).and_then(|update| match update {
+                            Some(update) => wollet.apply_update(update),
+                            None => Ok(()),
+                        });
+                        if let Err(e) = result {
+                            tracing::warn!("wallet {name}: scan failed: {e}");
+                            failed = true;
+                        }
+                        for event in wollet.take_events() {
+                            match event {
+                                WolletEvent::Reorg(reorg) => tracing::warn!(
+                                    "wallet {name}: reorg of {} blocks from height {}",
+                                    reorg.depth,
+                                    reorg.height
+                                ),
+                            }
+                        }
+                    }
+                    if failed {
+                        for health in client.health() {
+                            tracing::warn!("{health:?}");
+                        }
+                        backend = None; // Reconnect at the next scan
                     }
                 }
                 s.scan_loops_completed += 1;
@@ -280,7 +338,10 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
             // TODO recognize different name same descriptor?
 
             let desc: WolletDescriptor = r.descriptor.parse()?;
-            let wollet = Wollet::with_fs_persist(s.config.network, desc, &s.config.datadir)?;
+            let mut wollet = Wollet::with_fs_persist(s.config.network, desc, &s.config.datadir)?;
+            if let Some(gap_limit) = r.gap_limit {
+                wollet.set_gap_limit(gap_limit)?;
+            }
             s.wollets.insert(&r.name, wollet)?;
 
             s.persist(&request)?;
@@ -485,22 +546,31 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
             let r: request::WalletBalance = serde_json::from_value(params)?;
             let mut s = state.lock()?;
             let wollet = s.wollets.get_mut(&r.name)?;
-            let mut balance = wollet
-                .balance()?
-                .into_iter()
-                .map(|(k, v)| (k.to_string(), v as i64))
-                .collect();
-            if r.with_tickers {
-                balance = s.replace_id_with_ticker(balance);
-            }
+            let details = wollet.balance_details()?;
+            let to_response = |balance: BTreeMap<AssetId, u64>| {
+                let balance = balance.into_iter().map(|(k, v)| (k.to_string(), v as i64));
+                if r.with_tickers {
+                    s.replace_id_with_ticker(balance)
+                } else {
+                    balance.collect()
+                }
+            };
             Response::result(
                 request.id,
-                serde_json::to_value(response::WalletBalance { balance })?,
+                serde_json::to_value(response::WalletBalance {
+                    balance: to_response(details.total()),
+                    confirmed: to_response(details.confirmed),
+                    trusted_pending: to_response(details.trusted_pending),
+                    untrusted_pending: to_response(details.untrusted_pending),
+                    immature: to_response(details.immature),
+                    locked: to_response(details.locked),
+                })?,
             )
         }
         Method::WalletSendMany => {
             let r: request::WalletSendMany = serde_json::from_value(params)?;
             let mut s = state.lock()?;
+            let fee_rate = fee_rate(&s.config, r.fee_rate, r.fee_target)?;
             let wollet: &mut Wollet = s.wollets.get_mut(&r.name)?;
 
             let recipients: Vec<_> = r
@@ -508,11 +578,34 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
                 .into_iter()
                 .map(unvalidated_addressee)
                 .collect();
-            let mut tx = wollet
+            let coin_selection = match r.coin_selection {
+                Some(coin_selection) => coin_selection.parse()?,
+                None => CoinSelection::default(),
+            };
+            let mut builder = wollet
                 .tx_builder()
                 .set_unvalidated_recipients(&recipients)?
-                .fee_rate(r.fee_rate)
-                .finish()?;
+                .fee_rate(fee_rate)
+                .coin_selection(coin_selection);
+            for outpoint in parse_outpoints(&r.add_utxos.unwrap_or_default())? {
+                builder = builder.add_utxo(outpoint);
+            }
+            for outpoint in parse_outpoints(&r.exclude_utxos.unwrap_or_default())? {
+                builder = builder.exclude_utxo(outpoint);
+            }
+            if r.utxos_only.unwrap_or(false) {
+                builder = builder.manually_selected_only();
+            }
+            if let Some(min_confirmations) = r.min_confirmations {
+                builder = builder.min_confirmations(min_confirmations);
+            }
+            if let Some(spend_unconfirmed) = r.spend_unconfirmed {
+                builder = builder.spend_unconfirmed(spend_unconfirmed.parse()?);
+            }
+            if let Some(lock_blocks) = r.lock_blocks {
+                builder = builder.lock_inputs_for(lock_blocks);
+            }
+            let mut tx = builder.finish()?;
 
             add_contracts(&mut tx, s.assets.iter());
             Response::result(
@@ -525,15 +618,22 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
         Method::WalletDrain => {
             let r: request::WalletDrain = serde_json::from_value(params)?;
             let mut s = state.lock()?;
+            let fee_rate = fee_rate(&s.config, r.fee_rate, r.fee_target)?;
             let wollet: &mut Wollet = s.wollets.get_mut(&r.name)?;
 
             let address = Address::from_str(&r.address)?;
-            let mut tx = wollet
+            let mut builder = wollet
                 .tx_builder()
                 .drain_lbtc_wallet()
                 .drain_lbtc_to(address)
-                .fee_rate(r.fee_rate)
-                .finish()?;
+                .fee_rate(fee_rate);
+            if let Some(min_confirmations) = r.min_confirmations {
+                builder = builder.min_confirmations(min_confirmations);
+            }
+            if let Some(spend_unconfirmed) = r.spend_unconfirmed {
+                builder = builder.spend_unconfirmed(spend_unconfirmed.parse()?);
+            }
+            let mut tx = builder.finish()?;
 
             add_contracts(&mut tx, s.assets.iter());
             Response::result(
@@ -662,19 +762,54 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
             let mut pset =
                 PartiallySignedTransaction::from_str(&r.pset).map_err(|e| e.to_string())?;
             let tx = wollet.finalize(&mut pset)?;
-            let electrum_client = s.config.electrum_client()?;
-
+            let txid = tx.txid();
             if !r.dry_run {
-                electrum_client.broadcast(&tx)?;
+                s.config.blockchain_backend()?.broadcast(&tx)?;
+                // Spent utxos must not be used before the next scan, failing to apply the
+                // transaction doesn't make the broadcast fail, the next scan will find it
+                if let Err(e) = s.wollets.get_mut(&r.name)?.apply_transaction(tx) {
+                    tracing::warn!("wallet {}: failed to apply tx {txid}: {e}", r.name);
+                }
             }
 
             Response::result(
                 request.id,
                 serde_json::to_value(response::WalletBroadcast {
-                    txid: tx.txid().to_string(),
+                    txid: txid.to_string(),
                 })?,
             )
         }
+        Method::WalletRescan => {
+            let r: request::WalletRescan = serde_json::from_value(params)?;
+            let mut s = state.lock()?;
+            let mut backend = s.config.blockchain_backend()?;
+            let wollet = s.wollets.get_mut(&r.name)?;
+            // TODO: release lock when doing network calls
+            if let Some(update) = backend.full_scan_with_gap_limit(wollet, r.gap_limit)? {
+                wollet.apply_update(update)?;
+            }
+            Response::result(request.id, serde_json::to_value(response::Empty {})?)
+        }
+        Method::WalletExport => {
+            let r: request::WalletExport = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let wollet = s.wollets.get(&r.name)?;
+            let backup = wollet.export_backup()?.to_hex();
+            Response::result(
+                request.id,
+                serde_json::to_value(response::WalletExport { backup })?,
+            )
+        }
+        Method::WalletImport => {
+            let r: request::WalletImport = serde_json::from_value(params)?;
+            let mut s = state.lock()?;
+            let wollet = s.wollets.get_mut(&r.name)?;
+            let backup =
+                Vec::<u8>::from_hex(&r.backup).map_err(|e| Error::Generic(e.to_string()))?;
+            // The imported data is persisted by the wallet persister
+            wollet.import_backup(&backup)?;
+            Response::result(request.id, serde_json::to_value(response::Empty {})?)
+        }
         Method::WalletDetails => {
             let r: request::WalletDetails = serde_json::from_value(params)?;
             let mut s = state.lock()?;
@@ -827,7 +962,11 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
             let r: request::WalletUtxos = serde_json::from_value(params)?;
             let mut s = state.lock()?;
             let wollet = s.wollets.get_mut(&r.name)?;
-            let utxos: Vec<response::Utxo> = wollet.utxos()?.iter().map(convert_utxo).collect();
+            let mut utxos = vec![];
+            for utxo in wollet.utxos()? {
+                let lock = wollet.utxo_lock(&utxo.outpoint)?;
+                utxos.push(convert_utxo(&utxo, lock));
+            }
             Response::result(
                 request.id,
                 serde_json::to_value(response::WalletUtxos { utxos })?,
@@ -891,6 +1030,55 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
             s.persist(&request)?;
             Response::result(request.id, serde_json::to_value(response::Empty {})?)
         }
+        Method::WalletLockUtxos => {
+            let r: request::WalletLockUtxos = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let wollet = s.wollets.get(&r.name)?;
+            let outpoints = parse_outpoints(&r.utxos)?;
+            wollet.lock_utxos(&outpoints, r.blocks.unwrap_or(DEFAULT_LOCK_BLOCKS))?;
+            Response::result(request.id, serde_json::to_value(response::Empty {})?)
+        }
+        Method::WalletUnlockUtxos => {
+            let r: request::WalletUnlockUtxos = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let wollet = s.wollets.get(&r.name)?;
+            wollet.unlock_utxos(&parse_outpoints(&r.utxos)?)?;
+            Response::result(request.id, serde_json::to_value(response::Empty {})?)
+        }
+        Method::WalletFreezeUtxos => {
+            let r: request::WalletFreezeUtxos = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let wollet = s.wollets.get(&r.name)?;
+            wollet.freeze_utxos(&parse_outpoints(&r.utxos)?)?;
+            Response::result(request.id, serde_json::to_value(response::Empty {})?)
+        }
+        Method::WalletBumpFee => {
+            let r: request::WalletBumpFee = serde_json::from_value(params)?;
+            let s = state.lock()?;
+            let wollet = s.wollets.get(&r.name)?;
+
+            let txid = Txid::from_str(&r.txid)?;
+            let original = match r.pset {
+                Some(pset) => {
+                    Some(PartiallySignedTransaction::from_str(&pset).map_err(|e| e.to_string())?)
+                }
+                None => None,
+            };
+            let recipients = r
+                .addressees
+                .into_iter()
+                .map(|a| unvalidated_addressee(a).validate(wollet.network()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut tx = wollet.bump_fee_with(&txid, r.fee_rate, original.as_ref(), &recipients)?;
+
+            add_contracts(&mut tx, s.assets.iter());
+            Response::result(
+                request.id,
+                serde_json::to_value(response::Pset {
+                    pset: tx.to_string(),
+                })?,
+            )
+        }
         Method::WalletIssue => {
             let r: request::WalletIssue = serde_json::from_value(params)?;
             let mut s = state.lock()?;
@@ -1084,6 +1272,24 @@ fn inner_method_handler(request: Request, state: Arc<Mutex<State>>) -> Result<Re
         Method::Stop => {
             return Err(Error::Stop);
         }
+        Method::FeeEstimates => {
+            let r: request::FeeEstimates = serde_json::from_value(params)?;
+            let targets = if r.targets.is_empty() {
+                vec![1, 2, 3, 6, 12, 24, 144]
+            } else {
+                r.targets
+            };
+            let backend = state.lock()?.config.blockchain_backend()?;
+            let mut fee_rates = HashMap::new();
+            for target in targets {
+                let fee_rate = backend.estimate_fee(target as usize)?;
+                fee_rates.insert(target.to_string(), fee_rate.to_sat_kvb());
+            }
+            Response::result(
+                request.id,
+                serde_json::to_value(response::FeeEstimates { fee_rates })?,
+            )
+        }
         Method::AssetPublish => {
             let r: request::AssetPublish = serde_json::from_value(params)?;
             let asset_id =
@@ -1139,6 +1345,26 @@ fn scan(state: &Arc<Mutex<State>>) -> Result<(), Error> {
     Ok(())
 }
 
+/// The fee rate to use, estimated by the blockchain backend if `fee_target` is set
+fn fee_rate(
+    config: &Config,
+    fee_rate: Option<f32>,
+    fee_target: Option<u32>,
+) -> Result<Option<f32>, Error> {
+    match (fee_rate, fee_target) {
+        (Some(_), Some(_)) => Err(Error::Generic(
+            "fee_rate and fee_target can't be used together".to_string(),
+        )),
+        (None, Some(fee_target)) => {
+            let fee_rate = config
+                .blockchain_backend()?
+                .estimate_fee(fee_target as usize)?;
+            Ok(Some(fee_rate.to_sat_kvb()))
+        }
+        (fee_rate, None) => Ok(fee_rate),
+    }
+}
+
 fn unvalidated_addressee(a: request::UnvalidatedAddressee) -> lwk_wollet::UnvalidatedRecipient {
     lwk_wollet::UnvalidatedRecipient {
         satoshi: a.satoshi,
@@ -1183,7 +1409,7 @@ fn add_contracts<'a>(
     }
 }
 
-fn convert_utxo(u: &lwk_wollet::WalletTxOut) -> response::Utxo {
+fn convert_utxo(u: &lwk_wollet::WalletTxOut, lock: Option<UtxoLock>) -> response::Utxo {
     response::Utxo {
         txid: u.outpoint.txid.to_string(),
         vout: u.outpoint.vout,
@@ -1191,9 +1417,21 @@ fn convert_utxo(u: &lwk_wollet::WalletTxOut) -> response::Utxo {
         script_pubkey: u.script_pubkey.to_hex(),
         asset: u.unblinded.asset.to_string(),
         value: u.unblinded.value,
+        locked_until: match lock {
+            Some(UtxoLock::UntilHeight(height)) => Some(height),
+            _ => None,
+        },
+        frozen: lock == Some(UtxoLock::Frozen),
     }
 }
 
+fn parse_outpoints(outpoints: &[String]) -> Result<Vec<OutPoint>, Error> {
+    outpoints
+        .iter()
+        .map(|o| OutPoint::from_str(o).map_err(|e| Error::Generic(e.to_string())))
+        .collect()
+}
+
 fn convert_tx(
     tx: &lwk_wollet::WalletTx,
     explorer_url: &str,
@@ -1204,6 +1442,8 @@ fn convert_tx(
     response::Tx {
         txid: tx.txid.to_string(),
         height: tx.height,
+        status: tx.status.to_string(),
+        conflicting_txid: tx.status.conflicting_txid().map(|t| t.to_string()),
         balance: tx
             .balance
             .iter()
@@ -20,6 +20,7 @@ pub enum Method {
     Version,
     Scan,
     Stop,
+    FeeEstimates,
     WalletLoad,
     WalletUnload,
     WalletList,
@@ -40,6 +41,13 @@ pub enum Method {
     WalletMultisigDescriptor,
     WalletSetTxMemo,
     WalletSetAddrMemo,
+    WalletLockUtxos,
+    WalletUnlockUtxos,
+    WalletFreezeUtxos,
+    WalletBumpFee,
+    WalletRescan,
+    WalletExport,
+    WalletImport,
     SignerGenerate,
     SignerJadeId,
     SignerLoadSoftware,
@@ -68,6 +76,7 @@ impl Method {
                 Method::Version => schema_for!(request::Empty),
                 Method::Scan => schema_for!(request::Empty),
                 Method::Stop => schema_for!(request::Empty),
+                Method::FeeEstimates => schema_for!(request::FeeEstimates),
                 Method::WalletLoad => schema_for!(request::WalletLoad),
                 Method::WalletUnload => schema_for!(request::WalletUnload),
                 Method::WalletList => schema_for!(request::Empty),
@@ -88,6 +97,13 @@ impl Method {
                 Method::WalletMultisigDescriptor => schema_for!(request::WalletMultisigDescriptor),
                 Method::WalletSetTxMemo => schema_for!(request::WalletSetTxMemo),
                 Method::WalletSetAddrMemo => schema_for!(request::WalletSetAddrMemo),
+                Method::WalletLockUtxos => schema_for!(request::WalletLockUtxos),
+                Method::WalletUnlockUtxos => schema_for!(request::WalletUnlockUtxos),
+                Method::WalletFreezeUtxos => schema_for!(request::WalletFreezeUtxos),
+                Method::WalletBumpFee => schema_for!(request::WalletBumpFee),
+                Method::WalletRescan => schema_for!(request::WalletRescan),
+                Method::WalletExport => schema_for!(request::WalletExport),
+                Method::WalletImport => schema_for!(request::WalletImport),
                 Method::SignerGenerate => schema_for!(request::Empty),
                 Method::SignerJadeId => schema_for!(request::Empty),
                 Method::SignerLoadSoftware => schema_for!(request::SignerLoadSoftware),
@@ -115,6 +131,7 @@ impl Method {
                 Method::Version => schema_for!(response::Version),
                 Method::Scan => schema_for!(response::Empty),
                 Method::Stop => schema_for!(request::Empty),
+                Method::FeeEstimates => schema_for!(response::FeeEstimates),
                 Method::WalletLoad => schema_for!(response::Wallet),
                 Method::WalletUnload => schema_for!(response::WalletUnload),
                 Method::WalletList => schema_for!(response::WalletList),
@@ -135,6 +152,13 @@ impl Method {
                 Method::WalletMultisigDescriptor => schema_for!(response::WalletMultisigDescriptor),
                 Method::WalletSetTxMemo => schema_for!(response::Empty),
                 Method::WalletSetAddrMemo => schema_for!(response::Empty),
+                Method::WalletLockUtxos => schema_for!(response::Empty),
+                Method::WalletUnlockUtxos => schema_for!(response::Empty),
+                Method::WalletFreezeUtxos => schema_for!(response::Empty),
+                Method::WalletBumpFee => schema_for!(response::Pset),
+                Method::WalletRescan => schema_for!(response::Empty),
+                Method::WalletExport => schema_for!(response::WalletExport),
+                Method::WalletImport => schema_for!(response::Empty),
                 Method::SignerGenerate => schema_for!(response::SignerGenerate),
                 Method::SignerJadeId => schema_for!(response::JadeId),
                 Method::SignerLoadSoftware => schema_for!(response::Signer),
@@ -170,6 +194,7 @@ impl FromStr for Method {
             "version" => Method::Version,
             "scan" => Method::Scan,
             "stop" => Method::Stop,
+            "fee_estimates" => Method::FeeEstimates,
             "wallet_load" => Method::WalletLoad,
             "wallet_unload" => Method::WalletUnload,
             "wallet_list" => Method::WalletList,
@@ -190,6 +215,13 @@ impl FromStr for Method {
             "wallet_multisig_descriptor" => Method::WalletMultisigDescriptor,
             "wallet_set_tx_memo" => Method::WalletSetTxMemo,
             "wallet_set_addr_memo" => Method::WalletSetAddrMemo,
+            "wallet_lock_utxos" => Method::WalletLockUtxos,
+            "wallet_unlock_utxos" => Method::WalletUnlockUtxos,
+            "wallet_freeze_utxos" => Method::WalletFreezeUtxos,
+            "wallet_bump_fee" => Method::WalletBumpFee,
+            "wallet_rescan" => Method::WalletRescan,
+            "wallet_export" => Method::WalletExport,
+            "wallet_import" => Method::WalletImport,
             "signer_generate" => Method::SignerGenerate,
             "signer_jade_id" => Method::SignerJadeId,
             "signer_load_software" => Method::SignerLoadSoftware,
@@ -225,6 +257,7 @@ impl std::fmt::Display for Method {
             Method::Version => "version",
             Method::Scan => "scan",
             Method::Stop => "stop",
+            Method::FeeEstimates => "fee_estimates",
             Method::WalletLoad => "wallet_load",
             Method::WalletUnload => "wallet_unload",
             Method::WalletList => "wallet_list",
@@ -245,6 +278,13 @@ impl std::fmt::Display for Method {
             Method::WalletMultisigDescriptor => "wallet_multisig_descriptor",
             Method::WalletSetTxMemo => "wallet_set_tx_memo",
             Method::WalletSetAddrMemo => "wallet_set_addr_memo",
+            Method::WalletLockUtxos => "wallet_lock_utxos",
+            Method::WalletUnlockUtxos => "wallet_unlock_utxos",
+            Method::WalletFreezeUtxos => "wallet_freeze_utxos",
+            Method::WalletBumpFee => "wallet_bump_fee",
+            Method::WalletRescan => "wallet_rescan",
+            Method::WalletExport => "wallet_export",
+            Method::WalletImport => "wallet_import",
             Method::SignerGenerate => "signer_generate",
             Method::SignerJadeId => "signer_jade_id",
             Method::SignerLoadSoftware => "signer_load_software",
@@ -628,6 +628,7 @@ impl State {
             let params = request::WalletLoad {
                 descriptor: w.descriptor().to_string(),
                 name: n.to_string(),
+                gap_limit: Some(w.gap_limit()),
             };
             let r = Request {
                 jsonrpc: "2.0".into(),
@@ -0,0 +1,50 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::types::AssetId;
+
+/// Wrapper over [`lwk_wollet::Balance`]
+#[derive(uniffi::Object, Debug)]
+pub struct Balance {
+    inner: lwk_wollet::Balance,
+}
+
+impl From<lwk_wollet::Balance> for Balance {
+    fn from(inner: lwk_wollet::Balance) -> Self {
+        Self { inner }
+    }
+}
+
+fn convert(map: &BTreeMap<elements::AssetId, u64>) -> HashMap<AssetId, u64> {
+    map.iter().map(|(k, v)| (AssetId::from(*k), *v)).collect()
+}
+
+#[uniffi::export]
+impl Balance {
+    pub fn confirmed(&self) -> HashMap<AssetId, u64> {
+        convert(&self.inner.confirmed)
+    }
+
+    pub fn trusted_pending(&self) -> HashMap<AssetId, u64> {
+        convert(&self.inner.trusted_pending)
+    }
+
+    pub fn untrusted_pending(&self) -> HashMap<AssetId, u64> {
+        convert(&self.inner.untrusted_pending)
+    }
+
+    pub fn immature(&self) -> HashMap<AssetId, u64> {
+        convert(&self.inner.immature)
+    }
+
+    pub fn locked(&self) -> HashMap<AssetId, u64> {
+        convert(&self.inner.locked)
+    }
+
+    pub fn total(&self) -> HashMap<AssetId, u64> {
+        convert(&self.inner.total())
+    }
+
+    pub fn spendable(&self) -> HashMap<AssetId, u64> {
+        convert(&self.inner.spendable())
+    }
+}
@@ -23,6 +23,20 @@ impl WalletTx {
         self.inner.height
     }
 
+    /// One of "confirmed", "pending", "replaced" or "conflicted"
+    pub fn status(&self) -> String {
+        self.inner.status.to_string()
+    }
+
+    /// The transaction replacing or conflicting with this one, if the status is "replaced" or
+    /// "conflicted"
+    pub fn conflicting_txid(&self) -> Option<Arc<Txid>> {
+        self.inner
+            .status
+            .conflicting_txid()
+            .map(|txid| Arc::new(txid.into()))
+    }
+
     pub fn balance(&self) -> HashMap<AssetId, i64> {
         self.inner
             .balance
@@ -99,6 +113,7 @@ mod tests {
             tx: tx.clone(),
             txid: tx.txid(),
             height: Some(4),
+            status: lwk_wollet::WalletTxStatus::Confirmed,
             balance: BTreeMap::new(),
             fee: 23,
             type_: "type".to_string(),
@@ -113,6 +128,10 @@ mod tests {
 
         assert_eq!(wallet_tx.height(), Some(4));
 
+        assert_eq!(wallet_tx.status(), "confirmed");
+
+        assert!(wallet_tx.conflicting_txid().is_none());
+
         assert_eq!(wallet_tx.balance(), HashMap::new());
 
         assert_eq!(wallet_tx.fee(), 23);
@@ -26,6 +26,29 @@ impl ElectrumClient {
         }))
     }
 
+    /// Construct an Electrum client connecting through the SOCKS5 proxy at `socks5`,
+    /// for instance `127.0.0.1:9050` for Tor
+    ///
+    /// If `timeout` (seconds) is not set, a timeout suitable for Tor is used.
+    #[uniffi::constructor]
+    pub fn with_socks5(
+        electrum_url: &str,
+        tls: bool,
+        validate_domain: bool,
+        socks5: &str,
+        timeout: Option<u8>,
+    ) -> Result<Arc<Self>, LwkError> {
+        let url = lwk_wollet::ElectrumUrl::new(electrum_url, tls, validate_domain);
+        let options = lwk_wollet::ElectrumOptions {
+            socks5: Some(socks5.to_string()),
+            timeout,
+        };
+        let client = lwk_wollet::ElectrumClient::new_with_options(&url, &options)?;
+        Ok(Arc::new(Self {
+            inner: Mutex::new(client),
+        }))
+    }
+
     pub fn broadcast(&self, tx: &Transaction) -> Result<Arc<Txid>, LwkError> {
         Ok(Arc::new(self.inner.lock()?.broadcast(tx.as_ref())?.into()))
     }
@@ -35,4 +58,11 @@ impl ElectrumClient {
         let update: Option<lwk_wollet::Update> = self.inner.lock()?.full_scan(&wollet)?;
         Ok(update.map(Into::into).map(Arc::new))
     }
+
+    /// Estimate the fee rate in sat/kvb needed to confirm within `target_blocks` blocks,
+    /// to be passed to [`crate::TxBuilder::fee_rate()`]
+    pub fn estimate_fee(&self, target_blocks: u32) -> Result<f32, LwkError> {
+        let fee_rate = self.inner.lock()?.estimate_fee(target_blocks as usize)?;
+        Ok(fee_rate.to_sat_kvb())
+    }
 }
@@ -1,9 +1,23 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use lwk_wollet::BlockchainBackend;
 
 use crate::{LwkError, Transaction, Txid, Update, Wollet};
 
+/// Authentication of the requests to the esplora server
+#[derive(uniffi::Enum, Debug)]
+pub enum EsploraAuth {
+    /// HTTP basic authentication
+    Basic { user: String, password: String },
+
+    /// Bearer token authentication
+    Bearer { token: String },
+}
+
 /// Wrapper over [`lwk_wollet::EsploraClient`]
 #[derive(uniffi::Object, Debug)]
 pub struct EsploraClient {
@@ -21,6 +35,48 @@ impl EsploraClient {
         })
     }
 
+    /// Construct an Esplora Client connecting through the SOCKS5 proxy at `socks5`,
+    /// for instance `127.0.0.1:9050` for Tor
+    #[uniffi::constructor]
+    pub fn with_socks5(url: &str, socks5: &str) -> Result<Arc<Self>, LwkError> {
+        let client = lwk_wollet::EsploraClient::builder(url)
+            .socks5(socks5)
+            .build()?;
+        Ok(Arc::new(Self {
+            inner: Mutex::new(client),
+        }))
+    }
+
+    /// Construct an Esplora Client sending `headers` and the optional `auth` with every request
+    ///
+    /// `danger_accept_invalid_certs` disables the validation of the server TLS certificate,
+    /// use only for testing.
+    #[uniffi::constructor]
+    pub fn with_options(
+        url: &str,
+        headers: HashMap<String, String>,
+        auth: Option<EsploraAuth>,
+        timeout_secs: Option<u32>,
+        danger_accept_invalid_certs: bool,
+    ) -> Result<Arc<Self>, LwkError> {
+        let mut builder = lwk_wollet::EsploraClient::builder(url)
+            .danger_accept_invalid_certs(danger_accept_invalid_certs);
+        for (name, value) in headers.iter() {
+            builder = builder.header(name, value);
+        }
+        builder = match auth {
+            Some(EsploraAuth::Basic { user, password }) => builder.basic_auth(&user, &password),
+            Some(EsploraAuth::Bearer { token }) => builder.bearer_auth(&token),
+            None => builder,
+        };
+        if let Some(secs) = timeout_secs {
+            builder = builder.timeout(Duration::from_secs(secs as u64));
+        }
+        Ok(Arc::new(Self {
+            inner: Mutex::new(builder.build()?),
+        }))
+    }
+
     pub fn broadcast(&self, tx: &Transaction) -> Result<Arc<Txid>, LwkError> {
         Ok(Arc::new(self.inner.lock()?.broadcast(tx.as_ref())?.into()))
     }
@@ -30,4 +86,11 @@ impl EsploraClient {
         let update: Option<lwk_wollet::Update> = self.inner.lock()?.full_scan(&wollet)?;
         Ok(update.map(Into::into).map(Arc::new))
     }
+
+    /// Estimate the fee rate in sat/kvb needed to confirm within `target_blocks` blocks,
+    /// to be passed to [`crate::TxBuilder::fee_rate()`]
+    pub fn estimate_fee(&self, target_blocks: u32) -> Result<f32, LwkError> {
+        let fee_rate = self.inner.lock()?.estimate_fee(target_blocks as usize)?;
+        Ok(fee_rate.to_sat_kvb())
+    }
 }
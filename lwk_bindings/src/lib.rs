@@ -2,6 +2,7 @@
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 #![doc = include_str!("../README.md")]
 
+mod balance;
 pub mod blockdata;
 mod chain;
 mod contract;
@@ -30,6 +31,7 @@ pub use blockdata::txid::Txid;
 pub use blockdata::wallet_tx::WalletTx;
 pub use blockdata::wallet_tx_out::WalletTxOut;
 
+pub use crate::balance::Balance;
 pub use crate::contract::Contract;
 pub use crate::signer::Signer;
 pub use crate::wollet::Wollet;
@@ -37,7 +39,7 @@ pub use chain::Chain;
 pub use desc::WolletDescriptor;
 pub use electrum_client::ElectrumClient;
 pub use error::LwkError;
-pub use esplora_client::EsploraClient;
+pub use esplora_client::{EsploraAuth, EsploraClient};
 pub use mnemonic::Mnemonic;
 pub use network::Network;
 pub use persister::{ForeignPersister, ForeignPersisterLink};
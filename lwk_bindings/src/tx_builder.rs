@@ -5,7 +5,9 @@ use std::{
 
 use lwk_wollet::UnvalidatedRecipient;
 
-use crate::{types::AssetId, Address, Contract, LwkError, Network, Pset, Transaction, Wollet};
+use crate::{
+    types::AssetId, Address, Contract, LwkError, Network, OutPoint, Pset, Transaction, Wollet,
+};
 
 /// Wrapper over [`lwk_wollet::TxBuilder`]
 #[derive(uniffi::Object, Debug)]
@@ -76,6 +78,47 @@ impl TxBuilder {
         Ok(())
     }
 
+    /// Spend the given wallet UTXO, wrapper of [`lwk_wollet::TxBuilder::add_utxo()`]
+    pub fn add_utxo(&self, outpoint: &OutPoint) -> Result<(), LwkError> {
+        let mut lock = self.inner.lock()?;
+        let inner = lock.take().ok_or_else(builder_finished)?;
+        *lock = Some(inner.add_utxo(outpoint.into()));
+        Ok(())
+    }
+
+    /// Spend exactly the given wallet UTXOs, wrapper of [`lwk_wollet::TxBuilder::add_utxos_only()`]
+    pub fn add_utxos_only(&self, outpoints: Vec<Arc<OutPoint>>) -> Result<(), LwkError> {
+        let outpoints: Vec<_> = outpoints.iter().map(|o| o.as_ref().into()).collect();
+        let mut lock = self.inner.lock()?;
+        let inner = lock.take().ok_or_else(builder_finished)?;
+        *lock = Some(inner.add_utxos_only(&outpoints));
+        Ok(())
+    }
+
+    /// Never spend the given wallet UTXO, wrapper of [`lwk_wollet::TxBuilder::exclude_utxo()`]
+    pub fn exclude_utxo(&self, outpoint: &OutPoint) -> Result<(), LwkError> {
+        let mut lock = self.inner.lock()?;
+        let inner = lock.take().ok_or_else(builder_finished)?;
+        *lock = Some(inner.exclude_utxo(outpoint.into()));
+        Ok(())
+    }
+
+    /// Spend only the added UTXOs, wrapper of [`lwk_wollet::TxBuilder::manually_selected_only()`]
+    pub fn manually_selected_only(&self) -> Result<(), LwkError> {
+        let mut lock = self.inner.lock()?;
+        let inner = lock.take().ok_or_else(builder_finished)?;
+        *lock = Some(inner.manually_selected_only());
+        Ok(())
+    }
+
+    /// Set for how many blocks the inputs are locked, wrapper of [`lwk_wollet::TxBuilder::lock_inputs_for()`]
+    pub fn lock_inputs_for(&self, blocks: u32) -> Result<(), LwkError> {
+        let mut lock = self.inner.lock()?;
+        let inner = lock.take().ok_or_else(builder_finished)?;
+        *lock = Some(inner.lock_inputs_for(blocks));
+        Ok(())
+    }
+
     /// Add a recipient receiving L-BTC
     pub fn add_lbtc_recipient(&self, address: &Address, satoshi: u64) -> Result<(), LwkError> {
         let unvalidated_recipient = UnvalidatedRecipient::lbtc(address.to_string(), satoshi);
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::LwkError;
 
 /// Wrapper over [`lwk_wollet::Update`]
@@ -34,12 +36,21 @@ impl AsRef<lwk_wollet::Update> for Update {
 impl Update {
     #[uniffi::constructor]
     pub fn new(bytes: &[u8]) -> Result<Update, LwkError> {
-        Ok(lwk_wollet::Update::deserialize(bytes)?.into())
+        Ok(lwk_wollet::Update::deserialize_versioned(bytes)?.into())
     }
 
     pub fn serialize(&self) -> Result<Vec<u8>, LwkError> {
         Ok(self.inner.serialize()?)
     }
+
+    /// Merge `other`, an update following this one, see [`lwk_wollet::Update::merge()`]
+    ///
+    /// Useful to compact the updates persisted by a [`crate::ForeignPersister`].
+    pub fn merge(&self, other: &Update) -> Arc<Update> {
+        let mut inner = self.inner.clone();
+        inner.merge(other.inner.clone());
+        Arc::new(inner.into())
+    }
 }
 
 #[cfg(test)]
@@ -50,5 +61,8 @@ mod tests {
         let bytes = lwk_test_util::update_test_vector_bytes();
         let update = crate::Update::new(&bytes).unwrap();
         assert_eq!(update.serialize().unwrap(), bytes);
+
+        let merged = update.merge(&update);
+        assert!(*merged == update);
     }
 }
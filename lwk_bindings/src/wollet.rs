@@ -3,7 +3,10 @@ use lwk_wollet::NoPersist;
 use crate::desc::WolletDescriptor;
 use crate::network::Network;
 use crate::types::AssetId;
-use crate::{AddressResult, ForeignPersisterLink, LwkError, Pset, Txid, Update, WalletTx};
+use crate::{
+    AddressResult, Balance, ForeignPersisterLink, LwkError, Pset, Transaction, Txid, Update,
+    WalletTx,
+};
 use std::sync::{MutexGuard, PoisonError};
 use std::{
     collections::HashMap,
@@ -78,6 +81,28 @@ impl Wollet {
         Ok(())
     }
 
+    /// Export the wallet state in a single encrypted blob, see
+    /// [`lwk_wollet::Wollet::export_backup()`]
+    pub fn export_backup(&self) -> Result<Vec<u8>, LwkError> {
+        Ok(self.inner.lock()?.export_backup()?)
+    }
+
+    /// Import a blob created by [`Wollet::export_backup()`] of the same wallet, see
+    /// [`lwk_wollet::Wollet::import_backup()`]
+    pub fn import_backup(&self, backup: &[u8]) -> Result<(), LwkError> {
+        let mut wollet = self.inner.lock()?;
+        wollet.import_backup(backup)?;
+        Ok(())
+    }
+
+    /// Apply a transaction as unconfirmed, for example just after broadcasting it, see
+    /// [`lwk_wollet::Wollet::apply_transaction()`]
+    pub fn apply_transaction(&self, tx: &Transaction) -> Result<(), LwkError> {
+        let mut wollet = self.inner.lock()?;
+        wollet.apply_transaction(tx.into())?;
+        Ok(())
+    }
+
     pub fn balance(&self) -> Result<HashMap<AssetId, u64>, LwkError> {
         let m: HashMap<_, _> = self
             .inner
@@ -89,6 +114,10 @@ impl Wollet {
         Ok(m)
     }
 
+    pub fn balance_details(&self) -> Result<Arc<Balance>, LwkError> {
+        Ok(Arc::new(self.inner.lock()?.balance_details()?.into()))
+    }
+
     pub fn transactions(&self) -> Result<Vec<Arc<WalletTx>>, LwkError> {
         Ok(self
             .inner
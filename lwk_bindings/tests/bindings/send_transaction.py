@@ -23,6 +23,7 @@ txid = node.send_to_address(wollet_address.address(), funded_satoshi, asset=None
 wollet.wait_for_tx(txid, client)
 
 assert(wollet.balance()[policy_asset] == funded_satoshi)
+assert(wollet.balance_details().untrusted_pending()[policy_asset] == funded_satoshi)
 
 node_address = node.get_new_address()
 sent_satoshi = 1000
@@ -39,3 +40,4 @@ txid = client.broadcast(tx)
 wollet.wait_for_tx(txid, client)
 expected_balance = funded_satoshi- sent_satoshi - tx.fee(policy_asset)
 assert(wollet.balance()[policy_asset] == expected_balance)
+assert(wollet.balance_details().trusted_pending()[policy_asset] == expected_balance)
@@ -139,6 +139,7 @@ pub enum ServerSubCommandsEnum {
     // Start is a special command
     Scan,
     Stop,
+    FeeEstimates,
 }
 
 #[derive(Debug, Args)]
@@ -166,6 +167,13 @@ pub enum WalletSubCommandsEnum {
     Txs,
     SetTxMemo,
     SetAddrMemo,
+    LockUtxos,
+    UnlockUtxos,
+    FreezeUtxos,
+    BumpFee,
+    Rescan,
+    Export,
+    Import,
 }
 
 #[derive(Debug, Args)]
@@ -397,6 +405,42 @@ impl Display for MultisigKind {
     }
 }
 
+#[derive(ValueEnum, Clone, Debug)]
+pub enum CoinSelectionKind {
+    BranchAndBound,
+    LargestFirst,
+    OldestFirst,
+    Privacy,
+}
+
+impl Display for CoinSelectionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoinSelectionKind::BranchAndBound => write!(f, "branch-and-bound"),
+            CoinSelectionKind::LargestFirst => write!(f, "largest-first"),
+            CoinSelectionKind::OldestFirst => write!(f, "oldest-first"),
+            CoinSelectionKind::Privacy => write!(f, "privacy"),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum SpendUnconfirmedKind {
+    All,
+    OwnOnly,
+    None,
+}
+
+impl Display for SpendUnconfirmedKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpendUnconfirmedKind::All => write!(f, "all"),
+            SpendUnconfirmedKind::OwnOnly => write!(f, "own-only"),
+            SpendUnconfirmedKind::None => write!(f, "none"),
+        }
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct WalletArgs {
     #[command(subcommand)]
@@ -413,6 +457,12 @@ pub enum WalletCommand {
 
         #[arg(short, long)]
         descriptor: String,
+
+        /// Number of consecutive unused addresses after which the scan stops
+        ///
+        /// If missing, the one previously set for the wallet or 20.
+        #[arg(long)]
+        gap_limit: Option<u32>,
     },
 
     /// Unload a wallet
@@ -483,6 +533,43 @@ pub enum WalletCommand {
         /// Fee rate to use
         #[arg(long)]
         fee_rate: Option<f32>,
+
+        /// Use the fee rate estimated to confirm within this number of blocks
+        #[arg(long, conflicts_with = "fee_rate")]
+        fee_target: Option<u32>,
+
+        /// Strategy used to select the coins to spend
+        #[arg(long)]
+        coin_selection: Option<CoinSelectionKind>,
+
+        /// A UTXO that must be spent, in the form "txid:vout"
+        ///
+        /// Can be specified multiple times.
+        #[arg(long)]
+        add_utxo: Vec<String>,
+
+        /// A UTXO that must not be spent, in the form "txid:vout"
+        ///
+        /// Can be specified multiple times.
+        #[arg(long)]
+        exclude_utxo: Vec<String>,
+
+        /// Spend only the UTXOs specified with `--add-utxo`
+        #[arg(long, action)]
+        utxos_only: bool,
+
+        /// Select only UTXOs with at least this number of confirmations
+        #[arg(long)]
+        min_confirmations: Option<u32>,
+
+        /// Which unconfirmed UTXOs can be selected
+        #[arg(long)]
+        spend_unconfirmed: Option<SpendUnconfirmedKind>,
+
+        /// Lock the inputs of the created PSET for this number of blocks, so that following
+        /// transactions don't spend them
+        #[arg(long)]
+        lock_blocks: Option<u32>,
     },
 
     /// Drain the wallet of the policy asset
@@ -498,6 +585,18 @@ pub enum WalletCommand {
         /// Fee rate to use
         #[arg(long)]
         fee_rate: Option<f32>,
+
+        /// Use the fee rate estimated to confirm within this number of blocks
+        #[arg(long, conflicts_with = "fee_rate")]
+        fee_target: Option<u32>,
+
+        /// Drain only UTXOs with at least this number of confirmations
+        #[arg(long)]
+        min_confirmations: Option<u32>,
+
+        /// Which unconfirmed UTXOs are drained
+        #[arg(long)]
+        spend_unconfirmed: Option<SpendUnconfirmedKind>,
     },
 
     /// Issue an asset
@@ -705,6 +804,112 @@ pub enum WalletCommand {
         #[arg(long)]
         memo: String,
     },
+
+    /// Lock wallet UTXOs, so that they are not spent by new transactions
+    LockUtxos {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// A UTXO to lock, in the form "txid:vout"
+        ///
+        /// Can be specified multiple times.
+        #[arg(long, required = true)]
+        utxo: Vec<String>,
+
+        /// For how many blocks the UTXOs are locked, if missing about a week
+        #[arg(long)]
+        blocks: Option<u32>,
+    },
+
+    /// Release the locks on wallet UTXOs, including frozen ones
+    UnlockUtxos {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// A UTXO to unlock, in the form "txid:vout"
+        ///
+        /// Can be specified multiple times.
+        #[arg(long, required = true)]
+        utxo: Vec<String>,
+    },
+
+    /// Freeze wallet UTXOs, so that they are not spent until unlocked
+    FreezeUtxos {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// A UTXO to freeze, in the form "txid:vout"
+        ///
+        /// Can be specified multiple times.
+        #[arg(long, required = true)]
+        utxo: Vec<String>,
+    },
+
+    /// Create an unsigned transaction (PSET) replacing an unconfirmed wallet transaction with one
+    /// paying an higher fee
+    BumpFee {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// Transaction id of the transaction to replace
+        #[arg(long)]
+        txid: String,
+
+        /// Fee rate of the replacing transaction
+        #[arg(long)]
+        fee_rate: f32,
+
+        /// The PSET which created the transaction to replace
+        ///
+        /// Used to recover the confidential outputs not belonging to the wallet.
+        #[arg(long)]
+        pset: Option<String>,
+
+        /// Specify a recipient of the transaction to replace in the form
+        /// "address:satoshi:asset_id"
+        ///
+        /// Can be specified multiple times.
+        ///
+        /// Needed for the confidential outputs not belonging to the wallet if `--pset` is not
+        /// given.
+        #[arg(long)]
+        recipient: Vec<String>,
+    },
+
+    /// Scan the wallet once with a higher gap limit, to find funds sent to addresses far beyond
+    /// the last used one
+    Rescan {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// Number of consecutive unused addresses after which the rescan stops
+        #[arg(long)]
+        gap_limit: u32,
+    },
+
+    /// Export the wallet state, including the cache, gap limit and UTXO locks, in an encrypted
+    /// backup which can be imported only in the same wallet
+    Export {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+    },
+
+    /// Import in the wallet a backup exported from the same wallet
+    Import {
+        /// Wallet name
+        #[arg(short, long, env)]
+        wallet: String,
+
+        /// The backup in hex format
+        #[arg(long)]
+        backup: String,
+    },
 }
 
 #[derive(Debug, Args)]
@@ -818,6 +1023,20 @@ pub enum ServerCommand {
         #[arg(short, long)]
         electrum_url: Option<String>,
 
+        /// Server used when the Electrum server fails, can be specified multiple times
+        ///
+        /// Urls starting with `http` are Esplora servers, the others are Electrum servers.
+        #[arg(long)]
+        backup_server: Vec<String>,
+
+        /// Compare tips and scan results between the servers to detect lagging or lying ones
+        #[arg(long)]
+        cross_check: bool,
+
+        /// SOCKS5 proxy used to connect to the servers, for instance `127.0.0.1:9050` for Tor
+        #[arg(long)]
+        socks5: Option<String>,
+
         #[arg(long)]
         #[cfg(feature = "registry")]
         /// Needed only in regtest because public network have their official defaults
@@ -845,6 +1064,15 @@ pub enum ServerCommand {
     /// Wait until an entire blockchain scan has been completed
     Scan,
 
+    /// Get the fee rates (sat/kvb) estimated by the blockchain backend
+    FeeEstimates {
+        /// A confirmation target in number of blocks
+        ///
+        /// Can be specified multiple times, if missing 1, 2, 3, 6, 12, 24 and 144 blocks.
+        #[arg(long)]
+        target: Vec<u32>,
+    },
+
     /// Stop the server
     ///
     /// Alternatively the server can be stopped also with SIGINT (ctrl-c)
@@ -79,6 +79,9 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
             match a.command {
                 ServerCommand::Start {
                     electrum_url,
+                    backup_server,
+                    cross_check,
+                    socks5,
                     #[cfg(feature = "registry")]
                     registry_url,
                     esplora_api_url,
@@ -114,6 +117,9 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                     if let Some(url) = esplora_api_url {
                         config.esplora_api_url = url;
                     };
+                    config.backup_servers = backup_server;
+                    config.cross_check = cross_check;
+                    config.socks5 = socks5;
 
                     #[cfg(feature = "registry")]
                     if let Some(url) = registry_url {
@@ -152,6 +158,10 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                 ServerCommand::Scan => {
                     client.scan()?;
                 }
+                ServerCommand::FeeEstimates { target } => {
+                    let r = client.fee_estimates(target)?;
+                    return Ok(serde_json::to_value(r)?);
+                }
                 ServerCommand::Stop => {
                     client.stop()?;
                 }
@@ -227,8 +237,24 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
             }
         },
         CliCommand::Wallet(a) => match a.command {
-            WalletCommand::Load { descriptor, wallet } => {
-                let r = client.wallet_load(descriptor, wallet)?;
+            WalletCommand::Load {
+                descriptor,
+                wallet,
+                gap_limit,
+            } => {
+                let r = client.wallet_load(descriptor, wallet, gap_limit)?;
+                serde_json::to_value(r)?
+            }
+            WalletCommand::Rescan { wallet, gap_limit } => {
+                let r = client.wallet_rescan(wallet, gap_limit)?;
+                serde_json::to_value(r)?
+            }
+            WalletCommand::Export { wallet } => {
+                let r = client.wallet_export(wallet)?;
+                serde_json::to_value(r)?
+            }
+            WalletCommand::Import { wallet, backup } => {
+                let r = client.wallet_import(wallet, backup)?;
                 serde_json::to_value(r)?
             }
             WalletCommand::Unload { wallet } => {
@@ -246,6 +272,14 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                 wallet,
                 recipient,
                 fee_rate,
+                fee_target,
+                coin_selection,
+                add_utxo,
+                exclude_utxo,
+                utxos_only,
+                min_confirmations,
+                spend_unconfirmed,
+                lock_blocks,
             } => {
                 let mut addressees = vec![];
                 for rec in recipient {
@@ -255,15 +289,37 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                     );
                 }
 
-                let r = client.wallet_send_many(wallet, addressees, fee_rate)?;
+                let r = client.wallet_send_many(
+                    wallet,
+                    addressees,
+                    fee_rate,
+                    fee_target,
+                    coin_selection.map(|c| c.to_string()),
+                    add_utxo,
+                    exclude_utxo,
+                    utxos_only,
+                    min_confirmations,
+                    spend_unconfirmed.map(|s| s.to_string()),
+                    lock_blocks,
+                )?;
                 serde_json::to_value(r)?
             }
             WalletCommand::Drain {
                 wallet,
                 address,
                 fee_rate,
+                fee_target,
+                min_confirmations,
+                spend_unconfirmed,
             } => {
-                let r = client.wallet_drain(wallet, address, fee_rate)?;
+                let r = client.wallet_drain(
+                    wallet,
+                    address,
+                    fee_rate,
+                    fee_target,
+                    min_confirmations,
+                    spend_unconfirmed.map(|s| s.to_string()),
+                )?;
                 serde_json::to_value(r)?
             }
             WalletCommand::Address {
@@ -386,6 +442,40 @@ pub fn inner_main(args: args::Cli) -> anyhow::Result<Value> {
                 let r = client.wallet_set_addr_memo(wallet, address, memo)?;
                 serde_json::to_value(r)?
             }
+            WalletCommand::LockUtxos {
+                wallet,
+                utxo,
+                blocks,
+            } => {
+                let r = client.wallet_lock_utxos(wallet, utxo, blocks)?;
+                serde_json::to_value(r)?
+            }
+            WalletCommand::UnlockUtxos { wallet, utxo } => {
+                let r = client.wallet_unlock_utxos(wallet, utxo)?;
+                serde_json::to_value(r)?
+            }
+            WalletCommand::FreezeUtxos { wallet, utxo } => {
+                let r = client.wallet_freeze_utxos(wallet, utxo)?;
+                serde_json::to_value(r)?
+            }
+            WalletCommand::BumpFee {
+                wallet,
+                txid,
+                fee_rate,
+                pset,
+                recipient,
+            } => {
+                let mut addressees = vec![];
+                for rec in recipient {
+                    addressees.push(
+                        rec.try_into()
+                            .with_context(|| "error parsing recipient argument")?,
+                    );
+                }
+
+                let r = client.wallet_bump_fee(wallet, txid, fee_rate, pset, addressees)?;
+                serde_json::to_value(r)?
+            }
         },
         CliCommand::Asset(a) => match a.command {
             AssetCommand::Contract {
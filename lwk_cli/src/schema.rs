@@ -31,6 +31,7 @@ impl From<ServerSubCommandsEnum> for Method {
         match value {
             ServerSubCommandsEnum::Scan => Method::Scan,
             ServerSubCommandsEnum::Stop => Method::Stop,
+            ServerSubCommandsEnum::FeeEstimates => Method::FeeEstimates,
         }
     }
 }
@@ -55,6 +56,13 @@ impl From<WalletSubCommandsEnum> for Method {
             WalletSubCommandsEnum::Txs => Method::WalletTxs,
             WalletSubCommandsEnum::SetTxMemo => Method::WalletSetTxMemo,
             WalletSubCommandsEnum::SetAddrMemo => Method::WalletSetAddrMemo,
+            WalletSubCommandsEnum::LockUtxos => Method::WalletLockUtxos,
+            WalletSubCommandsEnum::UnlockUtxos => Method::WalletUnlockUtxos,
+            WalletSubCommandsEnum::FreezeUtxos => Method::WalletFreezeUtxos,
+            WalletSubCommandsEnum::BumpFee => Method::WalletBumpFee,
+            WalletSubCommandsEnum::Rescan => Method::WalletRescan,
+            WalletSubCommandsEnum::Export => Method::WalletExport,
+            WalletSubCommandsEnum::Import => Method::WalletImport,
         }
     }
 }
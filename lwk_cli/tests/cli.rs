@@ -659,6 +659,208 @@ fn test_wallet_memos() {
     t.join().unwrap();
 }
 
+#[test]
+fn test_wallet_utxo_locks() {
+    let (t, _tmp, cli, _params, server, _) = setup_cli(false);
+
+    sw_signer(&cli, "s1");
+    singlesig_wallet(&cli, "w1", "s1", "slip77", "wpkh");
+    fund(&server, &cli, "w1", 1_000_000);
+
+    let r = sh(&format!("{cli} wallet utxos --wallet w1"));
+    let utxo = &r.get("utxos").unwrap().as_array().unwrap()[0];
+    let outpoint = format!("{}:{}", get_str(utxo, "txid"), utxo.get("vout").unwrap());
+    assert!(utxo.get("locked_until").unwrap().is_null());
+    assert!(!utxo.get("frozen").unwrap().as_bool().unwrap());
+
+    // Creating a PSET locks its inputs only if requested
+    let node_address = server.node_getnewaddress();
+    let policy_asset = "5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225";
+    let recipient = format!("--recipient {node_address}:1000:{policy_asset}");
+    sh(&format!("{cli} wallet send --wallet w1 {recipient}"));
+    let r = sh(&format!("{cli} wallet utxos --wallet w1"));
+    let utxo = &r.get("utxos").unwrap().as_array().unwrap()[0];
+    assert!(utxo.get("locked_until").unwrap().is_null());
+    sh(&format!(
+        "{cli} wallet send --wallet w1 {recipient} --lock-blocks 10"
+    ));
+    let r = sh(&format!("{cli} wallet utxos --wallet w1"));
+    let utxo = &r.get("utxos").unwrap().as_array().unwrap()[0];
+    assert!(utxo.get("locked_until").unwrap().as_u64().is_some());
+    let err = sh_err(&format!("{cli} wallet send --wallet w1 {recipient}"));
+    assert!(err.contains("Insufficient funds"));
+
+    sh(&format!(
+        "{cli} wallet unlock-utxos -w w1 --utxo {outpoint}"
+    ));
+    sh(&format!(
+        "{cli} wallet freeze-utxos -w w1 --utxo {outpoint}"
+    ));
+    let r = sh(&format!("{cli} wallet utxos --wallet w1"));
+    let utxo = &r.get("utxos").unwrap().as_array().unwrap()[0];
+    assert!(utxo.get("locked_until").unwrap().is_null());
+    assert!(utxo.get("frozen").unwrap().as_bool().unwrap());
+    let r = sh(&format!("{cli} wallet balance --wallet w1"));
+    let locked = r.get("locked").unwrap().get(policy_asset).unwrap();
+    assert_eq!(locked.as_u64().unwrap(), 1_000_000);
+    let confirmed = r.get("confirmed").unwrap().get(policy_asset).unwrap();
+    assert_eq!(confirmed.as_u64().unwrap(), 0);
+
+    sh(&format!(
+        "{cli} wallet unlock-utxos -w w1 --utxo {outpoint}"
+    ));
+    sh(&format!(
+        "{cli} wallet lock-utxos -w w1 --utxo {outpoint} --blocks 10"
+    ));
+    let r = sh(&format!("{cli} wallet utxos --wallet w1"));
+    let utxo = &r.get("utxos").unwrap().as_array().unwrap()[0];
+    assert!(utxo.get("locked_until").unwrap().as_u64().is_some());
+
+    sh(&format!(
+        "{cli} wallet unlock-utxos -w w1 --utxo {outpoint}"
+    ));
+    sh(&format!("{cli} wallet send --wallet w1 {recipient}"));
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
+#[test]
+fn test_wallet_bump_fee() {
+    let (t, _tmp, cli, _params, server, _) = setup_cli(false);
+
+    sw_signer(&cli, "s1");
+    singlesig_wallet(&cli, "w1", "s1", "slip77", "wpkh");
+    fund(&server, &cli, "w1", 1_000_000);
+
+    let node_address = server.node_getnewaddress();
+    let policy_asset = "5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225";
+    let recipient = format!("--recipient {node_address}:1000:{policy_asset}");
+    let r = sh(&format!("{cli} wallet send --wallet w1 {recipient}"));
+    let pset = get_str(&r, "pset").to_string();
+    let txid = complete(&cli, "w1", &pset, &["s1"]);
+
+    let cmd = format!("{cli} wallet bump-fee -w w1 --txid {txid}");
+    let err = sh_err(&format!("{cmd} --pset {pset} --fee-rate 100"));
+    assert!(err.contains("must be higher"));
+    let cmd = format!("{cmd} --fee-rate 1000");
+    let err = sh_err(&cmd);
+    assert!(err.contains("Missing recipient"));
+
+    let r = sh(&format!("{cmd} {recipient}"));
+    let _: PartiallySignedTransaction = get_str(&r, "pset").parse().unwrap();
+    let r = sh(&format!("{cmd} --pset {pset}"));
+    let new_txid = complete(&cli, "w1", get_str(&r, "pset"), &["s1"]);
+    assert_ne!(txid, new_txid);
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
+#[test]
+fn test_wallet_rescan() {
+    let (t, _tmp, cli, _params, server, _) = setup_cli(false);
+
+    sw_signer(&cli, "s1");
+    singlesig_wallet(&cli, "w1", "s1", "slip77", "wpkh");
+
+    // Fund an address beyond the default gap limit
+    let r = sh(&format!("{cli} wallet address --wallet w1 --index 30"));
+    let addr = Address::from_str(get_str(&r, "address")).unwrap();
+    server.node_sendtoaddress(&addr, 1_000_000, None);
+    server.generate(1);
+
+    let policy_asset = "5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225";
+    let balance = || {
+        sh(&format!("{cli} server scan"));
+        let r = sh(&format!("{cli} wallet balance --wallet w1"));
+        let b = r.get("balance").unwrap().as_object().unwrap();
+        b.get(policy_asset).and_then(|v| v.as_u64()).unwrap_or(0)
+    };
+    assert_eq!(balance(), 0);
+
+    sh(&format!("{cli} wallet rescan --wallet w1 --gap-limit 50"));
+    assert_eq!(balance(), 1_000_000);
+
+    let r = sh(&format!("{cli} wallet address --wallet w1"));
+    assert_eq!(r.get("index").unwrap().as_u64().unwrap(), 31);
+
+    // The gap limit is set when loading the wallet
+    let desc = "ct(c25deb86fa11e49d651d7eae27c220ef930fbd86ea023eebfa73e54875647963,elwpkh(tpubD6NzVbkrYhZ4Was8nwnZi7eiWUNJq2LFpPSCMQLioUfUtT1e72GkRbmVeRAZc26j5MRUz2hRLsaVHJfs6L7ppNfLUrm9btQTuaEsLrT7D87/*))#q9cypnmc";
+    let err = sh_err(&format!(
+        "{cli} wallet load --wallet w2 -d {desc} --gap-limit 0"
+    ));
+    assert!(err.contains("Gap limit must be greater than 0"));
+    sh(&format!(
+        "{cli} wallet load --wallet w2 -d {desc} --gap-limit 100"
+    ));
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
+#[test]
+fn test_wallet_export_import() {
+    let (t, _tmp, cli, _params, server, _) = setup_cli(false);
+
+    sw_signer(&cli, "s1");
+    singlesig_wallet(&cli, "w1", "s1", "slip77", "wpkh");
+    let r = sh(&format!("{cli} wallet address --wallet w1"));
+    let addr = Address::from_str(get_str(&r, "address")).unwrap();
+    server.node_sendtoaddress(&addr, 1_000_000, None);
+    server.generate(1);
+    sh(&format!("{cli} server scan"));
+
+    let r = sh(&format!("{cli} wallet export --wallet w1"));
+    let backup = get_str(&r, "backup").to_string();
+    let balance = sh(&format!("{cli} wallet balance --wallet w1"));
+
+    sh(&format!(
+        "{cli} wallet import --wallet w1 --backup {backup}"
+    ));
+    let r = sh(&format!("{cli} wallet balance --wallet w1"));
+    assert_eq!(r, balance);
+
+    // The backup can't be imported in another wallet
+    sw_signer(&cli, "s2");
+    singlesig_wallet(&cli, "w2", "s2", "slip77", "wpkh");
+    let err = sh_err(&format!(
+        "{cli} wallet import --wallet w2 --backup {backup}"
+    ));
+    assert!(err.contains("The backup belongs to another wallet or network"));
+    let err = sh_err(&format!("{cli} wallet import --wallet w2 --backup 00"));
+    assert!(err.contains("Invalid backup"));
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
+#[test]
+fn test_fee_estimates() {
+    let (t, _tmp, cli, _params, server, _) = setup_cli(false);
+
+    let r = sh(&format!("{cli} server fee-estimates"));
+    let fee_rates = r.get("fee_rates").unwrap().as_object().unwrap();
+    assert_eq!(fee_rates.len(), 7);
+    let r = sh(&format!("{cli} server fee-estimates --target 2"));
+    let fee_rate = r.get("fee_rates").unwrap().get("2").unwrap();
+    assert!(fee_rate.as_f64().unwrap() >= 100.0);
+
+    sw_signer(&cli, "s1");
+    singlesig_wallet(&cli, "w1", "s1", "slip77", "wpkh");
+    fund(&server, &cli, "w1", 1_000_000);
+
+    let node_address = server.node_getnewaddress();
+    let policy_asset = "5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225";
+    let recipient = format!("--recipient {node_address}:1000:{policy_asset}");
+    let cmd = format!("{cli} wallet send --wallet w1 {recipient} --fee-target 2");
+    let r = sh(&cmd);
+    let _: PartiallySignedTransaction = get_str(&r, "pset").parse().unwrap();
+
+    sh(&format!("{cli} server stop"));
+    t.join().unwrap();
+}
+
 #[test]
 fn test_wallet_details() {
     let (t, _tmp, cli, _params, _server, _) = setup_cli(false);
@@ -922,6 +1124,7 @@ fn test_issue() {
     for tx in txs {
         assert!(tx.get("height").is_some());
         assert!(tx.get("timestamp").is_some());
+        assert_eq!(tx.get("status").unwrap().as_str().unwrap(), "confirmed");
     }
 
     let balance = txs[0].get("balance").unwrap().as_object().unwrap();
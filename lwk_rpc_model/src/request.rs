@@ -12,6 +12,13 @@ use serde::{Deserialize, Serialize};
 #[derive(JsonSchema)]
 pub struct Empty {}
 
+/// Request the fee rates estimated by the blockchain backend
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct FeeEstimates {
+    /// The confirmation targets in number of blocks, if empty 1, 2, 3, 6, 12, 24 and 144
+    pub targets: Vec<u32>,
+}
+
 /// Request a JSON schema of a method of the RPC
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct Schema {
@@ -41,6 +48,10 @@ pub struct WalletLoad {
 
     /// The name given to the wallet, will be needed for calls related to the wallet
     pub name: String,
+
+    /// The number of consecutive unused addresses after which the scan stops, if missing the
+    /// one persisted with the wallet or 20
+    pub gap_limit: Option<u32>,
 }
 
 /// Unload the wallet identified by the given name
@@ -140,6 +151,34 @@ pub struct WalletSendMany {
 
     /// Optional fee rate in sat/vb
     pub fee_rate: Option<f32>,
+
+    /// Optional confirmation target in number of blocks, the fee rate is estimated by the
+    /// blockchain backend, can't be used with `fee_rate`
+    pub fee_target: Option<u32>,
+
+    /// Optional coin selection strategy, one of "branch-and-bound" (default), "largest-first",
+    /// "oldest-first" or "privacy" // TODO enum
+    pub coin_selection: Option<String>,
+
+    /// Optional UTXOs that must be spent, in the form "txid:vout"
+    pub add_utxos: Option<Vec<String>>,
+
+    /// Optional UTXOs that must not be spent, in the form "txid:vout"
+    pub exclude_utxos: Option<Vec<String>>,
+
+    /// Optional, if true spend only the UTXOs in `add_utxos`
+    pub utxos_only: Option<bool>,
+
+    /// Optional minimum number of confirmations of the selected UTXOs
+    pub min_confirmations: Option<u32>,
+
+    /// Optional policy for selecting unconfirmed UTXOs, one of "all" (default), "own-only" or
+    /// "none"
+    pub spend_unconfirmed: Option<String>,
+
+    /// Optional number of blocks the inputs are locked for once the PSET is created, so that
+    /// following transactions don't spend them, if missing they are not locked
+    pub lock_blocks: Option<u32>,
 }
 
 ///  An addressee which has yet to be validated
@@ -170,6 +209,17 @@ pub struct WalletDrain {
 
     /// Optional fee rate in sat/vb
     pub fee_rate: Option<f32>,
+
+    /// Optional confirmation target in number of blocks, the fee rate is estimated by the
+    /// blockchain backend, can't be used with `fee_rate`
+    pub fee_target: Option<u32>,
+
+    /// Optional minimum number of confirmations of the drained UTXOs
+    pub min_confirmations: Option<u32>,
+
+    /// Optional policy for draining unconfirmed UTXOs, one of "all" (default), "own-only" or
+    /// "none"
+    pub spend_unconfirmed: Option<String>,
 }
 
 /// A request containing information to create a single signature descriptor wallet
@@ -460,6 +510,88 @@ pub struct WalletSetAddrMemo {
     pub memo: String,
 }
 
+/// Request to lock wallet UTXOs, so that they are not spent by new transactions
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WalletLockUtxos {
+    /// The wallet name
+    pub name: String,
+
+    /// The UTXOs to lock, in the form "txid:vout"
+    pub utxos: Vec<String>,
+
+    /// For how many blocks the UTXOs are locked, if missing about a week
+    pub blocks: Option<u32>,
+}
+
+/// Request to release the locks on wallet UTXOs, including frozen ones
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WalletUnlockUtxos {
+    /// The wallet name
+    pub name: String,
+
+    /// The UTXOs to unlock, in the form "txid:vout"
+    pub utxos: Vec<String>,
+}
+
+/// Request to freeze wallet UTXOs, so that they are not spent until unlocked
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WalletFreezeUtxos {
+    /// The wallet name
+    pub name: String,
+
+    /// The UTXOs to freeze, in the form "txid:vout"
+    pub utxos: Vec<String>,
+}
+
+/// Request to replace an unconfirmed wallet transaction with one paying an higher fee
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WalletBumpFee {
+    /// The wallet name creating the replacing transaction
+    pub name: String,
+
+    /// The transaction id of the unconfirmed transaction to replace
+    pub txid: String,
+
+    /// The fee rate of the replacing transaction in sat/kvb
+    pub fee_rate: f32,
+
+    /// The PSET which created the replaced transaction, used to recover the confidential
+    /// outputs not belonging to the wallet
+    pub pset: Option<String>,
+
+    /// The recipients of the confidential outputs not belonging to the wallet, if the PSET
+    /// which created the replaced transaction is not available
+    pub addressees: Vec<UnvalidatedAddressee>,
+}
+
+/// Request to scan the wallet once with a gap limit higher than usual, to find funds sent to
+/// addresses far beyond the last used one
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WalletRescan {
+    /// The wallet name
+    pub name: String,
+
+    /// The number of consecutive unused addresses after which the rescan stops
+    pub gap_limit: u32,
+}
+
+/// Request to export the state of a wallet in an encrypted backup
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WalletExport {
+    /// The wallet name
+    pub name: String,
+}
+
+/// Request to import in a wallet a backup exported from the same wallet
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WalletImport {
+    /// The wallet name
+    pub name: String,
+
+    /// The backup in hex format, as returned by `wallet_export`
+    pub backup: String,
+}
+
 #[cfg(test)]
 mod test {
     use schemars::schema_for;
@@ -470,8 +602,18 @@ mod test {
     fn test_json_schema() {
         let schema = schema_for!(WalletLoad);
         assert_eq!(
-            r#"{"$schema":"http://json-schema.org/draft-07/schema#","title":"WalletLoad","description":"Request to load a wallet in the server, returning [`response::Wallet`]","type":"object","required":["descriptor","name"],"properties":{"descriptor":{"description":"The read-only descriptor describing the wallet outputs","type":"string"},"name":{"description":"The name given to the wallet, will be needed for calls related to the wallet","type":"string"}}}"#,
+            r#"{"$schema":"http://json-schema.org/draft-07/schema#","title":"WalletLoad","description":"Request to load a wallet in the server, returning [`response::Wallet`]","type":"object","required":["descriptor","name"],"properties":{"descriptor":{"description":"The read-only descriptor describing the wallet outputs","type":"string"},"gap_limit":{"description":"The number of consecutive unused addresses after which the scan stops, if missing the one persisted with the wallet or 20","type":["integer","null"],"format":"uint32","minimum":0.0},"name":{"description":"The name given to the wallet, will be needed for calls related to the wallet","type":"string"}}}"#,
             serde_json::to_string(&schema).unwrap()
         );
     }
+
+    #[test]
+    fn test_wallet_send_many_optional_fields() {
+        // Requests made before the coin control options were added are still valid
+        let json = r#"{"name":"w","addressees":[],"fee_rate":null}"#;
+        let req: WalletSendMany = serde_json::from_str(json).unwrap();
+        assert!(req.add_utxos.is_none());
+        assert!(req.exclude_utxos.is_none());
+        assert!(req.utxos_only.is_none());
+    }
 }
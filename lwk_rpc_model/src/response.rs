@@ -21,6 +21,13 @@ pub struct Version {
     pub network: String,
 }
 
+/// Fee rates estimated by the blockchain backend
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct FeeEstimates {
+    /// The fee rate in sat/kvb needed to confirm within the number of blocks used as key
+    pub fee_rates: HashMap<String, f32>,
+}
+
 /// Response for generate signer
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SignerGenerate {
@@ -102,6 +109,22 @@ pub struct WalletAddress {
 pub struct WalletBalance {
     /// A map of the balance of every asset in the wallet
     pub balance: HashMap<String, i64>,
+
+    /// The balance of the UTXOs confirmed in a block
+    pub confirmed: HashMap<String, i64>,
+
+    /// The balance of the unconfirmed UTXOs created by transactions spending only wallet UTXOs,
+    /// like the change
+    pub trusted_pending: HashMap<String, i64>,
+
+    /// The balance of the unconfirmed UTXOs received from others
+    pub untrusted_pending: HashMap<String, i64>,
+
+    /// The balance of the coinbase UTXOs which can't be spent yet
+    pub immature: HashMap<String, i64>,
+
+    /// The balance of the locked or frozen UTXOs
+    pub locked: HashMap<String, i64>,
 }
 
 /// PSET response
@@ -111,6 +134,13 @@ pub struct Pset {
     pub pset: String,
 }
 
+/// Wallet backup response
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct WalletExport {
+    /// The encrypted backup in hex format, it can be imported only in the same wallet
+    pub backup: String,
+}
+
 /// Response containing a single signature descriptor
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SignerSinglesigDescriptor {
@@ -324,6 +354,12 @@ pub struct Utxo {
 
     /// Output value in satoshi
     pub value: u64,
+
+    /// If the output is locked by a transaction not yet broadcast, the height until it's locked
+    pub locked_until: Option<u32>,
+
+    /// Whether the output is frozen
+    pub frozen: bool,
 }
 
 /// Wallet unspent transaction outputs
@@ -345,6 +381,13 @@ pub struct Tx {
     /// Timestamp of the block containing the transaction, present only if the tx is confirmed.
     pub timestamp: Option<u32>,
 
+    /// Status of the transaction, one of "confirmed", "pending", "replaced" or "conflicted"
+    pub status: String,
+
+    /// The transaction replacing or conflicting with this one, present only if the status is
+    /// "replaced" or "conflicted"
+    pub conflicting_txid: Option<String>,
+
     /// Net balance for the transaction
     pub balance: HashMap<String, i64>,
 
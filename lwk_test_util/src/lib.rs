@@ -66,8 +66,8 @@ fn add_checksum(desc: &str) -> String {
     }
 }
 
-fn compute_fee_rate(pset: &PartiallySignedTransaction) -> f32 {
-    let vsize = pset.extract_tx().unwrap().vsize();
+fn compute_fee_rate(pset: &PartiallySignedTransaction, fee_policy: FeePolicy) -> f32 {
+    let vsize = fee_policy.vsize(&pset.extract_tx().unwrap());
     let fee_satoshi = pset.outputs().last().unwrap().amount.unwrap();
     1000.0 * (fee_satoshi as f32 / vsize as f32)
 }
@@ -264,10 +264,34 @@ impl TestElectrumServer {
         node_getnewaddress(&self.node.client, None)
     }
 
+    /// The url of the node JSON-RPC interface
+    pub fn node_rpc_url(&self) -> String {
+        self.node.rpc_url()
+    }
+
+    /// The cookie file to authenticate to the node JSON-RPC interface
+    pub fn node_cookie_file(&self) -> std::path::PathBuf {
+        self.node.params.cookie_file.clone()
+    }
+
     pub fn node_height(&self) -> u64 {
         let raw: serde_json::Value = self.node.client.call("getblockchaininfo", &[]).unwrap();
         raw.get("blocks").unwrap().as_u64().unwrap()
     }
+
+    /// Invalidate the block at the given height and its descendants, their transactions go back
+    /// to the node mempool
+    pub fn node_invalidateblock(&self, height: u64) {
+        let hash: Value = self
+            .node
+            .client
+            .call("getblockhash", &[height.into()])
+            .unwrap();
+        self.node
+            .client
+            .call::<Value>("invalidateblock", &[hash])
+            .unwrap();
+    }
 }
 
 pub struct TestWollet {
@@ -477,7 +501,10 @@ impl TestWollet {
         for signer in signers {
             self.sign(signer, &mut pset);
         }
-        assert_fee_rate(compute_fee_rate(&pset), fee_rate);
+        assert_fee_rate(
+            compute_fee_rate(&pset, self.network().fee_policy()),
+            fee_rate,
+        );
         let txid = self.send(&mut pset);
         let balance_after = self.balance_btc();
         assert!(balance_before > balance_after);
@@ -568,7 +595,10 @@ impl TestWollet {
         for signer in signers {
             self.sign(signer, &mut pset);
         }
-        assert_fee_rate(compute_fee_rate(&pset), fee_rate);
+        assert_fee_rate(
+            compute_fee_rate(&pset, self.network().fee_policy()),
+            fee_rate,
+        );
         self.send(&mut pset);
         let balance_after = self.balance(asset);
         assert!(balance_before > balance_after);
@@ -624,7 +654,10 @@ impl TestWollet {
         for signer in signers {
             self.sign(signer, &mut pset);
         }
-        assert_fee_rate(compute_fee_rate(&pset), fee_rate);
+        assert_fee_rate(
+            compute_fee_rate(&pset, self.network().fee_policy()),
+            fee_rate,
+        );
         self.send(&mut pset);
         let balance1_after = self.balance(asset1);
         let balance2_after = self.balance(asset2);
@@ -684,7 +717,10 @@ impl TestWollet {
         for signer in signers {
             self.sign(signer, &mut pset);
         }
-        assert_fee_rate(compute_fee_rate(&pset), fee_rate);
+        assert_fee_rate(
+            compute_fee_rate(&pset, self.network().fee_policy()),
+            fee_rate,
+        );
         let txid = self.send(&mut pset);
         let tx = self.get_tx(&txid);
         assert_eq!(&tx.type_, "issuance");
@@ -759,7 +795,10 @@ impl TestWollet {
         for signer in signers {
             self.sign(signer, &mut pset);
         }
-        assert_fee_rate(compute_fee_rate(&pset), fee_rate);
+        assert_fee_rate(
+            compute_fee_rate(&pset, self.network().fee_policy()),
+            fee_rate,
+        );
         let txid = self.send(&mut pset);
         let tx = self.get_tx(&txid);
         assert_eq!(&tx.type_, "reissuance");
@@ -814,7 +853,10 @@ impl TestWollet {
         for signer in signers {
             self.sign(signer, &mut pset);
         }
-        assert_fee_rate(compute_fee_rate(&pset), fee_rate);
+        assert_fee_rate(
+            compute_fee_rate(&pset, self.network().fee_policy()),
+            fee_rate,
+        );
         let txid = self.send(&mut pset);
         let tx = self.get_tx(&txid);
         assert_eq!(&tx.type_, "burn");
@@ -1035,6 +1077,14 @@ pub fn update_test_vector_bytes() -> Vec<u8> {
     Vec::<u8>::from_hex(include_str!("../test_data/update_test_vector.hex")).unwrap()
 }
 
+pub fn update_test_vector_v1_bytes() -> Vec<u8> {
+    Vec::<u8>::from_hex(include_str!("../test_data/update_test_vector_v1.hex")).unwrap()
+}
+
+pub fn update_test_vector_v2_bytes() -> Vec<u8> {
+    Vec::<u8>::from_hex(include_str!("../test_data/update_test_vector_v2.hex")).unwrap()
+}
+
 pub fn update_test_vector_encrypted_bytes() -> Vec<u8> {
     Vec::<u8>::from_hex(include_str!(
         "../test_data/update_test_vector_encrypted.hex"
@@ -26,6 +26,18 @@ impl WalletTx {
         self.inner.height
     }
 
+    /// One of "confirmed", "pending", "replaced" or "conflicted"
+    pub fn status(&self) -> String {
+        self.inner.status.to_string()
+    }
+
+    /// The transaction replacing or conflicting with this one, if the status is "replaced" or
+    /// "conflicted"
+    #[wasm_bindgen(js_name = conflictingTxid)]
+    pub fn conflicting_txid(&self) -> Option<Txid> {
+        self.inner.status.conflicting_txid().map(Into::into)
+    }
+
     pub fn balance(&self) -> JsValue {
         serde_wasm_bindgen::to_value(
             &self
@@ -111,6 +123,7 @@ mod tests {
             txid: tx.txid(),
             tx: tx.clone(),
             height: Some(4),
+            status: lwk_wollet::WalletTxStatus::Confirmed,
             balance: vec![(a, 10)].into_iter().collect(),
             fee: 23,
             type_: "type".to_string(),
@@ -125,6 +138,8 @@ mod tests {
 
         assert_eq!(wallet_tx.height(), Some(4));
 
+        assert_eq!(wallet_tx.status(), "confirmed");
+
         let balance: HashMap<elements::AssetId, i64> =
             serde_wasm_bindgen::from_value(wallet_tx.balance()).unwrap();
         assert_eq!(balance.get(&a), Some(&10));
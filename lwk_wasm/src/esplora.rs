@@ -1,4 +1,5 @@
 use crate::{Error, Pset, Txid, Update, Wollet};
+use lwk_wollet::AsyncBlockchainBackend;
 use wasm_bindgen::prelude::*;
 
 /// Wrapper of [`lwk_wollet::EsploraWasmClient`]
@@ -27,6 +28,65 @@ impl EsploraClient {
         let txid = self.inner.broadcast(&(tx.into())).await?;
         Ok(txid.into())
     }
+
+    /// Estimate the fee rate in sat/kvb needed to confirm within `target_blocks` blocks,
+    /// to be passed to `TxBuilder.feeRate()`
+    #[wasm_bindgen(js_name = estimateFee)]
+    pub async fn estimate_fee(&self, target_blocks: u32) -> Result<f32, Error> {
+        let fee_rate = self.inner.estimate_fee(target_blocks as usize).await?;
+        Ok(fee_rate.to_sat_kvb())
+    }
+}
+
+/// Wrapper of [`lwk_wollet::EsploraClientBuilder`]
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct EsploraClientBuilder {
+    inner: lwk_wollet::EsploraClientBuilder,
+}
+
+impl From<lwk_wollet::EsploraClientBuilder> for EsploraClientBuilder {
+    fn from(value: lwk_wollet::EsploraClientBuilder) -> Self {
+        Self { inner: value }
+    }
+}
+
+#[wasm_bindgen]
+impl EsploraClientBuilder {
+    /// Creates a builder for an `EsploraClient` connecting to `url`
+    #[wasm_bindgen(constructor)]
+    pub fn new(url: &str) -> EsploraClientBuilder {
+        lwk_wollet::EsploraClientBuilder::new(url).into()
+    }
+
+    /// Add a header sent with every request
+    pub fn header(self, name: &str, value: &str) -> EsploraClientBuilder {
+        self.inner.header(name, value).into()
+    }
+
+    /// Authenticate every request with HTTP basic authentication
+    #[wasm_bindgen(js_name = basicAuth)]
+    pub fn basic_auth(self, user: &str, password: &str) -> EsploraClientBuilder {
+        self.inner.basic_auth(user, password).into()
+    }
+
+    /// Authenticate every request with the bearer `token`
+    #[wasm_bindgen(js_name = bearerAuth)]
+    pub fn bearer_auth(self, token: &str) -> EsploraClientBuilder {
+        self.inner.bearer_auth(token).into()
+    }
+
+    /// Set the maximum number of retries of a request rate limited by the server
+    #[wasm_bindgen(js_name = maxRetries)]
+    pub fn max_retries(self, max_retries: u32) -> EsploraClientBuilder {
+        self.inner.max_retries(max_retries).into()
+    }
+
+    /// Build the `EsploraClient`
+    pub fn build(self) -> Result<EsploraClient, Error> {
+        let inner = self.inner.build_wasm()?;
+        Ok(EsploraClient { inner })
+    }
 }
 
 #[cfg(test)]
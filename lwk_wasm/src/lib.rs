@@ -31,7 +31,7 @@ pub use blockdata::wallet_tx_out::{OptionWalletTxOut, WalletTxOut};
 pub use contract::Contract;
 pub use descriptor::WolletDescriptor;
 pub(crate) use error::Error;
-pub use esplora::EsploraClient;
+pub use esplora::{EsploraClient, EsploraClientBuilder};
 #[cfg(all(feature = "serial", target_arch = "wasm32"))]
 pub use jade::{Jade, Singlesig};
 pub use mnemonic::Mnemonic;
@@ -3,7 +3,7 @@ use std::fmt::Display;
 use lwk_wollet::UnvalidatedRecipient;
 use wasm_bindgen::prelude::*;
 
-use crate::{Address, AssetId, Contract, Error, Network, Pset, Transaction, Wollet};
+use crate::{Address, AssetId, Contract, Error, Network, OutPoint, Pset, Transaction, Wollet};
 
 /// Wrapper of [`lwk_wollet::TxBuilder`]
 #[wasm_bindgen]
@@ -57,6 +57,37 @@ impl TxBuilder {
         self.inner.drain_lbtc_to(address.into()).into()
     }
 
+    /// Spend the given wallet UTXO, wrapper of [`lwk_wollet::TxBuilder::add_utxo()`]
+    #[wasm_bindgen(js_name = addUtxo)]
+    pub fn add_utxo(self, outpoint: &OutPoint) -> TxBuilder {
+        self.inner.add_utxo(outpoint.into()).into()
+    }
+
+    /// Spend exactly the given wallet UTXOs, wrapper of [`lwk_wollet::TxBuilder::add_utxos_only()`]
+    #[wasm_bindgen(js_name = addUtxosOnly)]
+    pub fn add_utxos_only(self, outpoints: Vec<OutPoint>) -> TxBuilder {
+        let outpoints: Vec<_> = outpoints.iter().map(Into::into).collect();
+        self.inner.add_utxos_only(&outpoints).into()
+    }
+
+    /// Never spend the given wallet UTXO, wrapper of [`lwk_wollet::TxBuilder::exclude_utxo()`]
+    #[wasm_bindgen(js_name = excludeUtxo)]
+    pub fn exclude_utxo(self, outpoint: &OutPoint) -> TxBuilder {
+        self.inner.exclude_utxo(outpoint.into()).into()
+    }
+
+    /// Spend only the added UTXOs, wrapper of [`lwk_wollet::TxBuilder::manually_selected_only()`]
+    #[wasm_bindgen(js_name = manuallySelectedOnly)]
+    pub fn manually_selected_only(self) -> TxBuilder {
+        self.inner.manually_selected_only().into()
+    }
+
+    /// Set for how many blocks the inputs are locked, wrapper of [`lwk_wollet::TxBuilder::lock_inputs_for()`]
+    #[wasm_bindgen(js_name = lockInputsFor)]
+    pub fn lock_inputs_for(self, blocks: u32) -> TxBuilder {
+        self.inner.lock_inputs_for(blocks).into()
+    }
+
     /// Add a recipient receiving L-BTC
     ///
     /// Errors if address's network is incompatible
@@ -171,12 +202,12 @@ mod tests {
         let policy = network.policy_asset();
 
         let mut builder = TxBuilder::new(&network);
-        assert_eq!(builder.to_string(), "TxBuilder { network: Liquid, recipients: [], fee_rate: 100.0, issuance_request: None, drain_lbtc: false, drain_to: None }");
+        assert_eq!(builder.to_string(), "TxBuilder { network: Liquid, recipients: [], fee_rate: 100.0, fee_policy: Vsize, issuance_request: None, drain_lbtc: false, drain_to: None, coin_selection: BranchAndBound, utxos_must_spend: [], utxos_excluded: {}, manually_selected_only: false, min_confirmations: 0, spend_unconfirmed: All, lock_blocks: 0, replaced_txid: None, cpfp_parent: None }");
 
         builder = builder.fee_rate(Some(200.0));
-        assert_eq!(builder.to_string(), "TxBuilder { network: Liquid, recipients: [], fee_rate: 200.0, issuance_request: None, drain_lbtc: false, drain_to: None }");
+        assert_eq!(builder.to_string(), "TxBuilder { network: Liquid, recipients: [], fee_rate: 200.0, fee_policy: Vsize, issuance_request: None, drain_lbtc: false, drain_to: None, coin_selection: BranchAndBound, utxos_must_spend: [], utxos_excluded: {}, manually_selected_only: false, min_confirmations: 0, spend_unconfirmed: All, lock_blocks: 0, replaced_txid: None, cpfp_parent: None }");
 
         builder = builder.add_burn(1000, &policy);
-        assert_eq!(builder.to_string(), "TxBuilder { network: Liquid, recipients: [Recipient { satoshi: 1000, script_pubkey: Script(OP_RETURN), blinding_pubkey: None, asset: 6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d }], fee_rate: 200.0, issuance_request: None, drain_lbtc: false, drain_to: None }");
+        assert_eq!(builder.to_string(), "TxBuilder { network: Liquid, recipients: [Recipient { satoshi: 1000, script_pubkey: Script(OP_RETURN), blinding_pubkey: None, asset: 6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d }], fee_rate: 200.0, fee_policy: Vsize, issuance_request: None, drain_lbtc: false, drain_to: None, coin_selection: BranchAndBound, utxos_must_spend: [], utxos_excluded: {}, manually_selected_only: false, min_confirmations: 0, spend_unconfirmed: All, lock_blocks: 0, replaced_txid: None, cpfp_parent: None }");
     }
 }
@@ -38,7 +38,7 @@ impl Update {
     /// Creates an `Update`
     #[wasm_bindgen(constructor)]
     pub fn new(bytes: &[u8]) -> Result<Update, Error> {
-        Ok(lwk_wollet::Update::deserialize(bytes)?.into())
+        Ok(lwk_wollet::Update::deserialize_versioned(bytes)?.into())
     }
 
     pub fn serialize(&self) -> Result<Vec<u8>, Error> {
@@ -62,6 +62,14 @@ impl Update {
     pub fn only_tip(&self) -> bool {
         self.inner.only_tip()
     }
+
+    /// Merge `other`, an update following this one, so that applying the result is equivalent
+    /// to applying this update and then `other`
+    pub fn merge(&self, other: &Update) -> Update {
+        let mut inner = self.inner.clone();
+        inner.merge(other.inner.clone());
+        inner.into()
+    }
 }
 
 #[cfg(test)]
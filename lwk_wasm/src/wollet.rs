@@ -1,4 +1,7 @@
-use crate::{AddressResult, Error, Network, Pset, PsetDetails, Update, WalletTx, WolletDescriptor};
+use crate::{
+    AddressResult, Error, Network, Pset, PsetDetails, Transaction, Update, WalletTx,
+    WolletDescriptor,
+};
 use lwk_jade::derivation_path_to_vec;
 use lwk_wollet::elements::pset::PartiallySignedTransaction;
 use lwk_wollet::elements_miniscript::ForEachKey;
@@ -65,11 +68,25 @@ impl Wollet {
         Ok(self.inner.apply_update(update.into())?)
     }
 
+    /// Apply a transaction as unconfirmed, for example just after broadcasting it
+    #[wasm_bindgen(js_name = applyTransaction)]
+    pub fn apply_transaction(&mut self, tx: &Transaction) -> Result<(), Error> {
+        Ok(self.inner.apply_transaction(tx.clone().into())?)
+    }
+
     pub fn balance(&self) -> Result<JsValue, Error> {
         let balance = self.inner.balance()?;
         Ok(serde_wasm_bindgen::to_value(&balance)?)
     }
 
+    /// The balance split in `confirmed`, `trusted_pending`, `untrusted_pending`, `immature` and
+    /// `locked` funds
+    #[wasm_bindgen(js_name = balanceDetails)]
+    pub fn balance_details(&self) -> Result<JsValue, Error> {
+        let balance = self.inner.balance_details()?;
+        Ok(serde_wasm_bindgen::to_value(&balance)?)
+    }
+
     pub fn transactions(&self) -> Result<Vec<WalletTx>, Error> {
         Ok(self
             .inner
@@ -110,6 +127,20 @@ impl Wollet {
     pub fn never_scanned(&self) -> bool {
         self.inner.never_scanned()
     }
+
+    /// Export the wallet state in a single encrypted blob, see
+    /// [lwk_wollet::Wollet::export_backup()]
+    #[wasm_bindgen(js_name = exportBackup)]
+    pub fn export_backup(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.inner.export_backup()?)
+    }
+
+    /// Import a blob created by `exportBackup` of the same wallet, see
+    /// [lwk_wollet::Wollet::import_backup()]
+    #[wasm_bindgen(js_name = importBackup)]
+    pub fn import_backup(&mut self, backup: &[u8]) -> Result<(), Error> {
+        Ok(self.inner.import_backup(backup)?)
+    }
 }
 
 /// Wrapper of [`lwk_wollet::Tip`]
@@ -190,11 +221,20 @@ mod tests {
         };
 
         wollet.apply_update(&update).unwrap();
+
+        let backup = wollet.export_backup().unwrap();
+        let mut restored = Wollet::new(&network, &descriptor).unwrap();
+        restored.import_backup(&backup).unwrap();
+        assert_eq!(restored.status(), wollet.status());
+
         let balance = wollet.balance().unwrap();
         let balance: HashMap<lwk_wollet::elements::AssetId, u64> =
             serde_wasm_bindgen::from_value(balance).unwrap();
         let lbtc = lwk_wollet::ElementsNetwork::Liquid.policy_asset();
         assert!(*balance.get(&lbtc).unwrap() >= 5000);
+        let details: lwk_wollet::Balance =
+            serde_wasm_bindgen::from_value(wollet.balance_details().unwrap()).unwrap();
+        assert_eq!(details.total().get(&lbtc), balance.get(&lbtc));
 
         let txs = wollet.transactions().unwrap();
         assert!(!txs.is_empty());
@@ -0,0 +1,196 @@
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::elements::OutPoint;
+use crate::update::{decrypt, encrypt, DownloadTxResult};
+use crate::{Error, Update, UtxoLock, UtxoLocks, Wollet};
+
+const BACKUP_MAGIC_BYTES: [u8; 4] = [0x6c, 0x77, 0x6b, 0x62];
+
+/// The latest version of the wallet backups created by [`Wollet::export_backup()`]
+///
+/// A backup is serialized as the magic bytes, the version byte and the encrypted content, so that
+/// the version is known before decrypting it.
+pub const BACKUP_VERSION: u8 = 0;
+
+/// The content of a wallet backup, encrypted with a key derived from the wallet descriptor
+#[derive(Serialize, Deserialize)]
+struct Backup {
+    /// The network of the wallet
+    network: String,
+
+    /// The gap limit of the wallet
+    gap_limit: u32,
+
+    /// The active UTXO locks of the wallet
+    locks: Vec<(OutPoint, UtxoLock)>,
+
+    /// The wallet cache as a single update, base64 encoded, none if the wallet was never scanned
+    update: Option<String>,
+}
+
+impl Wollet {
+    /// Export the state of this wallet in a single encrypted blob, to be imported in the same
+    /// wallet on another device with [`Wollet::import_backup()`]
+    ///
+    /// The backup contains the wallet cache, the gap limit and the UTXO locks. It is encrypted
+    /// with a key derived from the descriptor, which is needed to import it.
+    pub fn export_backup(&self) -> Result<Vec<u8>, Error> {
+        let desc = self.wollet_descriptor();
+        let update = match self.cache_update() {
+            Some(update) => Some(BASE64_STANDARD.encode(update.serialize()?)),
+            None => None,
+        };
+        let backup = Backup {
+            network: self.network().as_str().to_string(),
+            gap_limit: self.gap_limit(),
+            locks: self.locked_utxos()?,
+            update,
+        };
+        let mut result = BACKUP_MAGIC_BYTES.to_vec();
+        result.push(BACKUP_VERSION);
+        result.extend(encrypt(serde_json::to_vec(&backup)?, &desc)?);
+        Ok(result)
+    }
+
+    /// Import a backup created by [`Wollet::export_backup()`] of this same wallet
+    ///
+    /// The wallet cache is applied as an update and persisted, the gap limit and the UTXO locks
+    /// replace the ones of this wallet. Fails if the backup belongs to another wallet or network,
+    /// or if this wallet has been scanned up to a tip more recent than the backup.
+    pub fn import_backup(&mut self, backup: &[u8]) -> Result<(), Error> {
+        if backup.len() < BACKUP_MAGIC_BYTES.len() + 1 + 12 || backup[..4] != BACKUP_MAGIC_BYTES {
+            return Err(Error::InvalidBackup);
+        }
+        let version = backup[4];
+        if version > BACKUP_VERSION {
+            return Err(Error::UnsupportedBackupVersion {
+                version,
+                latest: BACKUP_VERSION,
+            });
+        }
+        let desc = self.wollet_descriptor();
+        let plaintext = decrypt(&backup[5..], &desc).map_err(|_| Error::BackupWalletMismatch)?;
+        let backup: Backup = serde_json::from_slice(&plaintext)?;
+        if backup.network != self.network().as_str() {
+            return Err(Error::BackupWalletMismatch);
+        }
+
+        if let Some(update) = backup.update {
+            let bytes = BASE64_STANDARD
+                .decode(update)
+                .map_err(|_| Error::InvalidBackup)?;
+            self.apply_update(Update::deserialize_versioned(&bytes)?)?;
+        }
+        self.set_gap_limit(backup.gap_limit)?;
+        self.replace_locks(UtxoLocks(backup.locks.into_iter().collect()))?;
+        Ok(())
+    }
+
+    /// The content of the wallet cache as a single update, none if never scanned
+    ///
+    /// Evicted transactions are not included.
+    fn cache_update(&self) -> Option<Update> {
+        let cache = &self.store.cache;
+        let tip = cache.tip_header.clone()?;
+        Some(Update {
+            new_txs: DownloadTxResult {
+                txs: cache.all_txs.clone().into_iter().collect(),
+                unblinds: cache.unblinded.clone().into_iter().collect(),
+            },
+            txid_height_new: cache.heights.clone().into_iter().collect(),
+            txid_height_delete: vec![],
+            timestamps: cache.timestamps.clone().into_iter().collect(),
+            scripts: cache.paths.clone(),
+            tip,
+            block_hashes: cache.block_hashes.clone().into_iter().collect(),
+            reorg: None,
+            scripts_status: cache
+                .script_status
+                .iter()
+                .map(|(script, status)| (script.clone(), Some(*status)))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::elements::OutPoint;
+    use crate::{ElementsNetwork, Error, Update, UtxoLock, UtxoLocks, Wollet, WolletDescriptor};
+
+    use super::BACKUP_VERSION;
+
+    fn test_wollet() -> Wollet {
+        let desc: WolletDescriptor = lwk_test_util::wollet_descriptor_string().parse().unwrap();
+        Wollet::without_persist(ElementsNetwork::LiquidTestnet, desc).unwrap()
+    }
+
+    #[test]
+    fn test_backup_roundtrip() {
+        let mut wollet = test_wollet();
+        let update = Update::deserialize(&lwk_test_util::update_test_vector_bytes()).unwrap();
+        wollet.apply_update(update).unwrap();
+        wollet.set_gap_limit(50).unwrap();
+        let outpoint = OutPoint::from_str(
+            "0000000000000000000000000000000000000000000000000000000000000003:0",
+        )
+        .unwrap();
+        let locks = UtxoLocks([(outpoint, UtxoLock::Frozen)].into_iter().collect());
+        wollet.replace_locks(locks).unwrap();
+        let backup = wollet.export_backup().unwrap();
+        assert_eq!(backup[4], BACKUP_VERSION);
+
+        let mut other = test_wollet();
+        other.import_backup(&backup).unwrap();
+        assert_eq!(other.status(), wollet.status());
+        assert_eq!(other.gap_limit(), 50);
+        assert_eq!(
+            other.locked_utxos().unwrap(),
+            vec![(outpoint, UtxoLock::Frozen)]
+        );
+
+        // Never scanned wallets can be backed up as well
+        let mut other = test_wollet();
+        let mut empty = test_wollet();
+        empty.set_gap_limit(30).unwrap();
+        other
+            .import_backup(&empty.export_backup().unwrap())
+            .unwrap();
+        assert!(other.never_scanned());
+        assert_eq!(other.gap_limit(), 30);
+        assert_eq!(
+            other.locked_utxos().unwrap(),
+            Vec::<(OutPoint, UtxoLock)>::new()
+        );
+    }
+
+    #[test]
+    fn test_backup_errors() {
+        let backup = test_wollet().export_backup().unwrap();
+
+        let desc: WolletDescriptor = lwk_test_util::wollet_descriptor_string2().parse().unwrap();
+        let mut other = Wollet::without_persist(ElementsNetwork::LiquidTestnet, desc).unwrap();
+        let err = other.import_backup(&backup).unwrap_err();
+        assert!(matches!(err, Error::BackupWalletMismatch));
+
+        let desc: WolletDescriptor = lwk_test_util::wollet_descriptor_string().parse().unwrap();
+        let mut other = Wollet::without_persist(ElementsNetwork::Liquid, desc).unwrap();
+        let err = other.import_backup(&backup).unwrap_err();
+        assert!(matches!(err, Error::BackupWalletMismatch));
+
+        let mut backup_next = backup.clone();
+        backup_next[4] = BACKUP_VERSION + 1;
+        let err = test_wollet().import_backup(&backup_next).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedBackupVersion { .. }));
+
+        let err = test_wollet().import_backup(&backup[..10]).unwrap_err();
+        assert!(matches!(err, Error::InvalidBackup));
+        let mut invalid = backup.clone();
+        invalid[0] = 0;
+        let err = test_wollet().import_backup(&invalid).unwrap_err();
+        assert!(matches!(err, Error::InvalidBackup));
+    }
+}
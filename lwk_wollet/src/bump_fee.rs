@@ -0,0 +1,147 @@
+use std::collections::HashSet;
+
+use crate::elements::pset::PartiallySignedTransaction;
+use crate::elements::{Script, TxOut, Txid};
+use crate::{Chain, Error, Recipient, SpendUnconfirmed, WalletTx, WalletTxStatus, Wollet};
+
+impl Wollet {
+    /// Create a PSET replacing the unconfirmed wallet transaction `txid` with one paying
+    /// `fee_rate` (sat/kvb)
+    ///
+    /// Confidential outputs not belonging to the wallet can't be recovered, to replace a
+    /// transaction having them use [`Wollet::bump_fee_with()`].
+    pub fn bump_fee(
+        &self,
+        txid: &Txid,
+        fee_rate: f32,
+    ) -> Result<PartiallySignedTransaction, Error> {
+        self.bump_fee_with(txid, fee_rate, None, &[])
+    }
+
+    /// Create a PSET replacing the unconfirmed wallet transaction `txid` with one paying
+    /// `fee_rate` (sat/kvb)
+    ///
+    /// All the inputs of the replaced transaction are spent again, other confirmed wallet UTXOs
+    /// are added if needed. The outputs sent to others and to the wallet external addresses are
+    /// kept, while the change is recomputed. The transaction is blinded again.
+    ///
+    /// The pending wallet transactions spending the outputs of the replaced one are replaced as
+    /// well, the new fee must cover theirs.
+    ///
+    /// The wallet can't unblind the confidential outputs it doesn't own, their amount, asset and
+    /// blinding public key are taken from `original`, the PSET which created the replaced
+    /// transaction, or from the `recipients` with the same script pubkey.
+    pub fn bump_fee_with(
+        &self,
+        txid: &Txid,
+        fee_rate: f32,
+        original: Option<&PartiallySignedTransaction>,
+        recipients: &[Recipient],
+    ) -> Result<PartiallySignedTransaction, Error> {
+        let tx = self
+            .transaction(txid)?
+            .ok_or_else(|| Error::MissingTransaction)?;
+        if tx.height.is_some() {
+            return Err(Error::TxAlreadyConfirmed(*txid));
+        }
+        if tx.tx.input.iter().any(|i| i.has_issuance()) {
+            return Err(Error::BumpFeeIssuance);
+        }
+
+        // New unconfirmed inputs are not allowed in a replacement (BIP125 rule 2)
+        let mut builder = self
+            .tx_builder()
+            .fee_rate(Some(fee_rate))
+            .spend_unconfirmed(SpendUnconfirmed::None)
+            .replace_tx(*txid);
+        for input in tx.inputs.iter() {
+            let input = input.as_ref().ok_or_else(|| Error::BumpFeeForeignInput)?;
+            builder = builder.add_utxo(input.outpoint);
+        }
+
+        let mut recipients = recipients.to_vec();
+        for (vout, (txout, wallet_txout)) in tx.tx.output.iter().zip(tx.outputs.iter()).enumerate()
+        {
+            if txout.is_fee() {
+                continue;
+            }
+            let recipient = match wallet_txout {
+                // The change is recomputed by the builder
+                Some(txo) if txo.ext_int == Chain::Internal => continue,
+                Some(txo) => {
+                    let address = self.address(Some(txo.wildcard_index))?;
+                    Recipient::from_address(
+                        txo.unblinded.value,
+                        address.address(),
+                        txo.unblinded.asset,
+                    )
+                }
+                None => match (txout.value.explicit(), txout.asset.explicit()) {
+                    (Some(satoshi), Some(asset)) => Recipient {
+                        satoshi,
+                        script_pubkey: txout.script_pubkey.clone(),
+                        blinding_pubkey: None,
+                        asset,
+                    },
+                    _ => take_recipient(&mut recipients, &txout.script_pubkey)
+                        .or_else(|| original_recipient(original, vout, txout))
+                        .ok_or_else(|| Error::BumpFeeMissingRecipient(vout as u32))?,
+                },
+            };
+            builder = builder.add_validated_recipient(recipient);
+        }
+
+        builder.finish()
+    }
+
+    /// The pending wallet transactions spending, directly or not, the outputs of `txid`
+    pub(crate) fn pending_descendants(&self, txid: &Txid) -> Result<Vec<WalletTx>, Error> {
+        let mut pending: Vec<_> = self
+            .transactions()?
+            .into_iter()
+            .filter(|tx| tx.status == WalletTxStatus::Pending)
+            .collect();
+        let mut ancestors = HashSet::from([*txid]);
+        let mut descendants = vec![];
+        loop {
+            let (children, others): (Vec<_>, Vec<_>) = pending.into_iter().partition(|tx| {
+                tx.tx
+                    .input
+                    .iter()
+                    .any(|i| ancestors.contains(&i.previous_output.txid))
+            });
+            if children.is_empty() {
+                return Ok(descendants);
+            }
+            ancestors.extend(children.iter().map(|tx| tx.txid));
+            descendants.extend(children);
+            pending = others;
+        }
+    }
+}
+
+/// Remove and return the first recipient with the given script pubkey
+fn take_recipient(recipients: &mut Vec<Recipient>, script_pubkey: &Script) -> Option<Recipient> {
+    let idx = recipients
+        .iter()
+        .position(|r| &r.script_pubkey == script_pubkey)?;
+    Some(recipients.remove(idx))
+}
+
+/// Recover the recipient of the output `vout` from the PSET which created the transaction
+fn original_recipient(
+    original: Option<&PartiallySignedTransaction>,
+    vout: usize,
+    txout: &TxOut,
+) -> Option<Recipient> {
+    let output = original?.outputs().get(vout)?;
+    if output.script_pubkey != txout.script_pubkey {
+        return None;
+    }
+    Some(Recipient {
+        satoshi: output.amount?,
+        script_pubkey: output.script_pubkey.clone(),
+        blinding_pubkey: Some(output.blinding_key?.inner),
+        asset: output.asset?,
+    })
+}
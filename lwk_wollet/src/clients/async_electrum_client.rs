@@ -0,0 +1,92 @@
+use crate::store::Height;
+use crate::{ElectrumClient, ElectrumUrl, Error, FeeRate};
+use elements::{BlockHash, BlockHeader, Script, Transaction, Txid};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::{AsyncBlockchainBackend, BlockchainBackend, History, ScriptStatusHash};
+
+/// An [`ElectrumClient`] usable from async code.
+///
+/// The requests are blocking, so they are executed with [`tokio::task::spawn_blocking`] and the
+/// client must be used inside a tokio runtime.
+#[derive(Debug, Clone)]
+pub struct AsyncElectrumClient {
+    inner: Arc<Mutex<ElectrumClient>>,
+}
+
+impl AsyncElectrumClient {
+    /// Connect to the electrum server at `url`
+    pub async fn new(url: &ElectrumUrl) -> Result<Self, Error> {
+        let url = url.clone();
+        let client = tokio::task::spawn_blocking(move || ElectrumClient::new(&url)).await??;
+        Ok(client.into())
+    }
+
+    async fn run<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut ElectrumClient) -> Result<T, Error> + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut client = inner.lock().map_err(|e| Error::Generic(e.to_string()))?;
+            f(&mut client)
+        })
+        .await?
+    }
+}
+
+impl From<ElectrumClient> for AsyncElectrumClient {
+    fn from(client: ElectrumClient) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(client)),
+        }
+    }
+}
+
+impl AsyncBlockchainBackend for AsyncElectrumClient {
+    async fn tip(&mut self) -> Result<BlockHeader, Error> {
+        self.run(|c| c.tip()).await
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
+        let tx = tx.clone();
+        self.run(move |c| c.broadcast(&tx)).await
+    }
+
+    async fn get_transactions(&self, txids: &[Txid]) -> Result<Vec<Transaction>, Error> {
+        let txids = txids.to_vec();
+        self.run(move |c| c.get_transactions(&txids)).await
+    }
+
+    async fn get_headers(
+        &self,
+        heights: &[Height],
+        height_blockhash: &HashMap<Height, BlockHash>,
+    ) -> Result<Vec<BlockHeader>, Error> {
+        let heights = heights.to_vec();
+        let height_blockhash = height_blockhash.clone();
+        self.run(move |c| c.get_headers(&heights, &height_blockhash))
+            .await
+    }
+
+    async fn get_scripts_history(&self, scripts: &[&Script]) -> Result<Vec<Vec<History>>, Error> {
+        let scripts: Vec<Script> = scripts.iter().map(|s| (*s).clone()).collect();
+        self.run(move |c| c.get_scripts_history(&scripts.iter().collect::<Vec<_>>()))
+            .await
+    }
+
+    async fn estimate_fee(&self, target_blocks: usize) -> Result<FeeRate, Error> {
+        self.run(move |c| c.estimate_fee(target_blocks)).await
+    }
+
+    async fn get_scripts_status(
+        &mut self,
+        scripts: &[&Script],
+    ) -> Result<Option<Vec<Option<ScriptStatusHash>>>, Error> {
+        let scripts: Vec<Script> = scripts.iter().map(|s| (*s).clone()).collect();
+        self.run(move |c| c.get_scripts_status(&scripts.iter().collect::<Vec<_>>()))
+            .await
+    }
+}
@@ -1,15 +1,15 @@
 use crate::store::Height;
-use crate::Error;
+use crate::{Error, FeeRate};
 use electrum_client::ScriptStatus;
-use electrum_client::{Client, ConfigBuilder, ElectrumApi, GetHistoryRes};
+use electrum_client::{Client, ConfigBuilder, ElectrumApi, GetHistoryRes, Socks5Config};
 use elements::encode::deserialize as elements_deserialize;
 use elements::encode::serialize as elements_serialize;
 use elements::Address;
 use elements::{bitcoin, BlockHash, BlockHeader, Script, Transaction, Txid};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 
-use super::History;
+use super::{History, ScriptStatusHash};
 
 /// A client to issue TCP requests to an electrum server.
 pub struct ElectrumClient {
@@ -18,6 +18,16 @@ pub struct ElectrumClient {
     tip: BlockHeader,
 
     script_status: HashMap<Script, ScriptStatus>,
+
+    /// Scripts subscribed with `blockchain.scripthash.subscribe`
+    subscribed: HashSet<Script>,
+
+    /// If true scripts are subscribed and only the history of the ones with a changed status is
+    /// downloaded, see [`ElectrumClient::with_incremental_sync()`]
+    incremental_sync: bool,
+
+    /// If true the notifications sent by the server since the last scan have been read
+    notifications_read: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +45,23 @@ impl std::fmt::Display for ElectrumUrl {
     }
 }
 
+/// Default timeout in seconds of the connections through a proxy, Tor circuits can take a while
+pub const PROXY_TIMEOUT_SECS: u8 = 60;
+
+/// Options of the connection to the electrum server
+#[derive(Debug, Clone, Default)]
+pub struct ElectrumOptions {
+    /// Address of a SOCKS5 proxy, for instance `127.0.0.1:9050` for Tor
+    ///
+    /// The server host is resolved by the proxy, so `.onion` hosts can be used.
+    pub socks5: Option<String>,
+
+    /// Timeout in seconds of the connection and of the requests
+    ///
+    /// If not set there is no timeout, or [`PROXY_TIMEOUT_SECS`] when using a proxy.
+    pub timeout: Option<u8>,
+}
+
 impl ElectrumUrl {
     pub fn new(electrum_url: &str, tls: bool, validate_domain: bool) -> Self {
         match tls {
@@ -43,7 +70,18 @@ impl ElectrumUrl {
         }
     }
     pub fn build_client(&self) -> Result<Client, Error> {
-        let builder = ConfigBuilder::new();
+        self.build_client_with_options(&ElectrumOptions::default())
+    }
+
+    pub fn build_client_with_options(&self, options: &ElectrumOptions) -> Result<Client, Error> {
+        let timeout = match (&options.socks5, options.timeout) {
+            (_, Some(timeout)) => Some(timeout),
+            (Some(_), None) => Some(PROXY_TIMEOUT_SECS),
+            (None, None) => None,
+        };
+        let builder = ConfigBuilder::new()
+            .socks5(options.socks5.as_ref().map(Socks5Config::new))
+            .timeout(timeout);
         let (url, builder) = match self {
             ElectrumUrl::Tls(url, validate) => {
                 (format!("ssl://{}", url), builder.validate_domain(*validate))
@@ -64,7 +102,12 @@ impl Debug for ElectrumClient {
 
 impl ElectrumClient {
     pub fn new(url: &ElectrumUrl) -> Result<Self, Error> {
-        let client = url.build_client()?;
+        Self::new_with_options(url, &ElectrumOptions::default())
+    }
+
+    /// Connect to the electrum server with the given options, for instance through a proxy
+    pub fn new_with_options(url: &ElectrumUrl, options: &ElectrumOptions) -> Result<Self, Error> {
+        let client = url.build_client_with_options(options)?;
         let header = client.block_headers_subscribe_raw()?;
         let tip: BlockHeader = elements_deserialize(&header.header)?;
 
@@ -72,9 +115,54 @@ impl ElectrumClient {
             client,
             tip,
             script_status: HashMap::new(),
+            subscribed: HashSet::new(),
+            incremental_sync: false,
+            notifications_read: false,
         })
     }
 
+    /// Enable the incremental sync mode
+    ///
+    /// The wallet scripts are subscribed with `blockchain.scripthash.subscribe` and the history is
+    /// downloaded only for scripts whose status differs from the one stored in the wallet. The
+    /// client should be kept across scans, notifications can be checked with
+    /// [`ElectrumClient::has_notifications()`] to know when a scan is needed.
+    pub fn with_incremental_sync(mut self) -> Self {
+        self.incremental_sync = true;
+        self
+    }
+
+    /// Return true if the server notified a new block or a change in the status of the
+    /// subscribed scripts since the last call
+    pub fn has_notifications(&mut self) -> Result<bool, Error> {
+        // Any request processes the incoming notifications
+        self.client.ping()?;
+        self.notifications_read = true;
+
+        let mut notified = false;
+        while let Some(header) = self.client.block_headers_pop_raw()? {
+            self.tip = elements_deserialize(&header.header)?;
+            notified = true;
+        }
+        let subscribed: Vec<Script> = self.subscribed.iter().cloned().collect();
+        for script in subscribed {
+            notified |= self.pop_script_status(&script)?;
+        }
+        Ok(notified)
+    }
+
+    /// Update the status of a subscribed script with the queued notifications, return true if
+    /// there was any
+    fn pop_script_status(&mut self, script: &Script) -> Result<bool, Error> {
+        let bitcoin_script = bitcoin::Script::from_bytes(script.as_bytes());
+        let mut popped = false;
+        while let Some(status) = self.client.script_pop(bitcoin_script)? {
+            self.script_status.insert(script.clone(), status);
+            popped = true;
+        }
+        Ok(popped)
+    }
+
     /// Return the status of an address as defined by the electrum protocol
     ///
     /// The status is function of the transaction ids where this address appears and the height of
@@ -93,6 +181,7 @@ impl ElectrumClient {
             Err(e) => return Err(e.into()),
         };
 
+        self.subscribed.insert(elements_script.clone());
         if let Some(val) = val {
             self.script_status.insert(elements_script.clone(), val);
         }
@@ -110,6 +199,8 @@ impl super::BlockchainBackend for ElectrumClient {
             let tip: BlockHeader = elements_deserialize(&popped_header.header)?;
             self.tip = tip;
         }
+        // The tip is requested once per scan, notifications are read again in the next one
+        self.notifications_read = false;
 
         Ok(self.tip.clone())
     }
@@ -161,6 +252,59 @@ impl super::BlockchainBackend for ElectrumClient {
             .map(|e| e.into_iter().map(Into::into).collect())
             .collect())
     }
+
+    fn estimate_fee(&self, target_blocks: usize) -> Result<FeeRate, Error> {
+        // Returns -1 if the server doesn't have enough data
+        let btc_kvb = self.client.estimate_fee(target_blocks)?;
+        Ok(FeeRate::from_btc_kvb(btc_kvb).at_least_min_relay())
+    }
+
+    fn has_notifications(&mut self) -> Result<bool, Error> {
+        ElectrumClient::has_notifications(self)
+    }
+
+    fn get_scripts_status(
+        &mut self,
+        scripts: &[&Script],
+    ) -> Result<Option<Vec<Option<ScriptStatusHash>>>, Error> {
+        if !self.incremental_sync {
+            return Ok(None);
+        }
+        if !self.notifications_read {
+            self.client.ping()?;
+            self.notifications_read = true;
+        }
+
+        let to_subscribe: Vec<&Script> = scripts
+            .iter()
+            .filter(|s| !self.subscribed.contains(**s))
+            .cloned()
+            .collect();
+        if !to_subscribe.is_empty() {
+            let bitcoin_scripts: Vec<&bitcoin::Script> = to_subscribe
+                .iter()
+                .map(|s| bitcoin::Script::from_bytes(s.as_bytes()))
+                .collect();
+            let statuses = self.client.batch_script_subscribe(&bitcoin_scripts)?;
+            for (script, status) in to_subscribe.into_iter().zip(statuses) {
+                self.subscribed.insert(script.clone());
+                if let Some(status) = status {
+                    self.script_status.insert(script.clone(), status);
+                }
+            }
+        }
+
+        let mut result = vec![];
+        for script in scripts {
+            self.pop_script_status(script)?;
+            result.push(self.script_status.get(*script).map(|status| {
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(status.as_ref());
+                bytes
+            }));
+        }
+        Ok(Some(result))
+    }
 }
 
 impl From<GetHistoryRes> for History {
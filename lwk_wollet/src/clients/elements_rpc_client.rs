@@ -0,0 +1,450 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic;
+
+use elements::encode::Decodable;
+use elements::hashes::{hex::FromHex, sha256, Hash};
+use elements::hex::ToHex;
+use elements::pset::serialize::Serialize;
+use elements::{BlockHash, BlockHeader, Script, Transaction, Txid};
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::store::Height;
+use crate::{BlockchainBackend, Error, FeeRate, Update, Wollet};
+
+use super::{block_on_ready, scan, History, SyncBackend};
+
+/// Number of addresses per chain imported in the node wallet at the first scan
+const DEFAULT_RANGE: u32 = 1000;
+
+/// The credentials to authenticate to the elementsd JSON-RPC interface
+#[derive(Debug, Clone)]
+pub enum ElementsRpcAuth {
+    /// The `rpcuser` and `rpcpassword` of the node
+    UserPass { user: String, password: String },
+
+    /// The `.cookie` file created by the node in its data directory
+    CookieFile(PathBuf),
+}
+
+/// A blockchain backend implementation using the JSON-RPC interface of an elementsd node.
+///
+/// At every scan the (non-confidential) descriptors of the wallet are imported in a dedicated
+/// watch-only wallet of the node, whose transactions are used to build the history of the
+/// wallet scripts. The first import rescans the whole chain.
+///
+/// The node doesn't need `-txindex`.
+pub struct ElementsRpcClient {
+    client: Client,
+    url: String,
+    auth: ElementsRpcAuth,
+
+    /// The node wallet watching the last scanned descriptor
+    wallet: Option<String>,
+
+    /// The descriptors imported in the node wallets, with the end of the imported range
+    imported: HashMap<String, u32>,
+
+    /// The history of the scripts of the node wallet, refreshed at every scan
+    scripts_history: HashMap<Script, Vec<History>>,
+
+    /// The transactions of the node wallet
+    txs: HashMap<Txid, Transaction>,
+}
+
+impl std::fmt::Debug for ElementsRpcClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ElementsRpcClient")
+            .field("url", &self.url)
+            .field("wallet", &self.wallet)
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ListSinceBlock {
+    transactions: Vec<WalletTransaction>,
+}
+
+#[derive(Deserialize)]
+struct WalletTransaction {
+    txid: Txid,
+    confirmations: i64,
+    blockhash: Option<BlockHash>,
+    blockheight: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct ListDescriptors {
+    descriptors: Vec<ImportedDescriptor>,
+}
+
+#[derive(Deserialize)]
+struct ImportedDescriptor {
+    desc: String,
+    range: Option<(u32, u32)>,
+}
+
+#[derive(Deserialize)]
+struct GetTransaction {
+    hex: String,
+}
+
+impl ElementsRpcClient {
+    /// Create a client connecting to the elementsd JSON-RPC interface at `url`,
+    /// for instance `http://127.0.0.1:7041`
+    pub fn new(url: &str, auth: ElementsRpcAuth) -> Result<Self, Error> {
+        let client = Self {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            auth,
+            wallet: None,
+            imported: HashMap::new(),
+            scripts_history: HashMap::new(),
+            txs: HashMap::new(),
+        };
+        // Fail early if the node is not reachable or the credentials are wrong
+        client.call::<Value>(None, "getblockcount", json!([]))?;
+        Ok(client)
+    }
+
+    fn credentials(&self) -> Result<(String, String), Error> {
+        match &self.auth {
+            ElementsRpcAuth::UserPass { user, password } => Ok((user.clone(), password.clone())),
+            ElementsRpcAuth::CookieFile(path) => {
+                let content = std::fs::read_to_string(path)?;
+                let (user, password) = content
+                    .trim()
+                    .split_once(':')
+                    .ok_or_else(|| Error::Generic("Invalid cookie file".to_string()))?;
+                Ok((user.to_string(), password.to_string()))
+            }
+        }
+    }
+
+    fn call<T: DeserializeOwned>(
+        &self,
+        wallet: Option<&str>,
+        method: &str,
+        params: Value,
+    ) -> Result<T, Error> {
+        let url = match wallet {
+            Some(wallet) => format!("{}/wallet/{}", self.url, wallet),
+            None => self.url.clone(),
+        };
+        let (user, password) = self.credentials()?;
+        let body = json!({"jsonrpc": "1.0", "id": "lwk", "method": method, "params": params});
+        let response = self
+            .client
+            .post(url)
+            .basic_auth(user, Some(password))
+            .json(&body)
+            .send()?;
+        tracing::debug!("{} status_code:{}", method, response.status());
+
+        // The node replies with an error status code and the error in the body
+        let response: RpcResponse<T> = response.json()?;
+        match (response.result, response.error) {
+            (_, Some(e)) => Err(Error::ElementsRpc {
+                code: e.code,
+                message: e.message,
+            }),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(Error::Generic(format!("{method} returned no result"))),
+        }
+    }
+
+    fn wallet_call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Error> {
+        let wallet = self.wallet.as_deref().ok_or(Error::NeverScanned)?;
+        self.call(Some(wallet), method, params)
+    }
+
+    fn get_header(&self, block_hash: &BlockHash) -> Result<BlockHeader, Error> {
+        let hex: String = self.call(None, "getblockheader", json!([block_hash, false]))?;
+        let bytes = Vec::<u8>::from_hex(&hex)?;
+        Ok(BlockHeader::consensus_decode(&bytes[..])?)
+    }
+
+    /// Load or create the node wallet watching the wallet descriptor and import the descriptors
+    /// with a range covering the wallet lookahead
+    fn import_descriptors(&mut self, wollet: &Wollet, gap_limit: u32) -> Result<(), Error> {
+        let descriptor = wollet.wollet_descriptor();
+        let descriptor_hash = sha256::Hash::hash(descriptor.to_string().as_bytes());
+        let wallet = format!("lwk_{}", &descriptor_hash.to_byte_array()[..8].to_hex());
+
+        let loaded: Vec<String> = self.call(None, "listwallets", json!([]))?;
+        if !loaded.contains(&wallet) {
+            let loaded = self.call::<Value>(None, "loadwallet", json!([wallet]));
+            if loaded.is_err() {
+                // disable_private_keys, blank, passphrase, avoid_reuse, descriptors
+                self.call::<Value>(
+                    None,
+                    "createwallet",
+                    json!([wallet, true, true, "", false, true]),
+                )?;
+            }
+        }
+        if self.wallet.as_ref() != Some(&wallet) {
+            self.wallet = Some(wallet.clone());
+            // Avoid rescanning the descriptors already imported, for instance by a previous run
+            let list: ListDescriptors = self.wallet_call("listdescriptors", json!([]))?;
+            for d in list.descriptors {
+                if let Some((0, end)) = d.range {
+                    let desc = normalize_hardened(d.desc.split('#').next().unwrap_or(&d.desc));
+                    self.imported.insert(format!("{wallet}/{desc}"), end);
+                }
+            }
+        }
+
+        let store = &wollet.store;
+        let last_unused = store
+            .cache
+            .last_unused_external
+            .load(atomic::Ordering::Relaxed)
+            .max(
+                store
+                    .cache
+                    .last_unused_internal
+                    .load(atomic::Ordering::Relaxed),
+            );
+        let needed = last_unused + 2 * gap_limit;
+
+        let mut requests = vec![];
+        let mut imported = vec![];
+        for single in descriptor.descriptor().clone().into_single_descriptors()? {
+            let desc = node_descriptor(&single.to_string());
+            let key = format!("{wallet}/{}", normalize_hardened(&desc));
+            if self.imported.get(&key).map(|end| *end >= needed) == Some(true) {
+                continue;
+            }
+            let end = DEFAULT_RANGE.max(2 * needed);
+            let info: Value = self.call(None, "getdescriptorinfo", json!([desc]))?;
+            let checksum = info
+                .get("checksum")
+                .and_then(|c| c.as_str())
+                .ok_or_else(|| Error::Generic("Missing descriptor checksum".to_string()))?;
+            requests.push(json!({
+                "desc": format!("{desc}#{checksum}"),
+                "timestamp": 0,
+                "range": [0, end],
+            }));
+            imported.push((key, end));
+        }
+        if requests.is_empty() {
+            return Ok(());
+        }
+
+        tracing::info!("importing {} descriptors in {}", requests.len(), wallet);
+        let results: Vec<Value> = self.wallet_call("importdescriptors", json!([requests]))?;
+        for result in results {
+            if result.get("success").and_then(|s| s.as_bool()) != Some(true) {
+                return Err(Error::Generic(format!(
+                    "Cannot import descriptor: {result}"
+                )));
+            }
+        }
+        self.imported.extend(imported);
+        Ok(())
+    }
+
+    /// Download the transactions of the node wallet and compute the history of its scripts
+    fn refresh_scripts_history(&mut self) -> Result<(), Error> {
+        let list: ListSinceBlock = self.wallet_call("listsinceblock", json!([]))?;
+
+        let mut heights = HashMap::new();
+        for tx in list.transactions {
+            // Negative confirmations mean the transaction conflicts with a confirmed one
+            if tx.confirmations < 0 {
+                continue;
+            }
+            let height = match (tx.confirmations, tx.blockheight) {
+                (0, _) | (_, None) => 0,
+                (_, Some(height)) => height,
+            };
+            heights.insert(tx.txid, (height, tx.blockhash));
+        }
+
+        for txid in heights.keys() {
+            if !self.txs.contains_key(txid) {
+                let tx: GetTransaction = self.wallet_call("gettransaction", json!([txid, true]))?;
+                let bytes = Vec::<u8>::from_hex(&tx.hex)?;
+                self.txs
+                    .insert(*txid, Transaction::consensus_decode(&bytes[..])?);
+            }
+        }
+
+        let mut scripts_txids: HashMap<Script, HashSet<Txid>> = HashMap::new();
+        for txid in heights.keys() {
+            let tx = &self.txs[txid];
+            for output in tx.output.iter() {
+                scripts_txids
+                    .entry(output.script_pubkey.clone())
+                    .or_default()
+                    .insert(*txid);
+            }
+            for input in tx.input.iter() {
+                let prevout = self
+                    .txs
+                    .get(&input.previous_output.txid)
+                    .and_then(|prev| prev.output.get(input.previous_output.vout as usize));
+                if let Some(prevout) = prevout {
+                    scripts_txids
+                        .entry(prevout.script_pubkey.clone())
+                        .or_default()
+                        .insert(*txid);
+                }
+            }
+        }
+
+        self.scripts_history = scripts_txids
+            .into_iter()
+            .map(|(script, txids)| {
+                let history = txids
+                    .into_iter()
+                    .map(|txid| {
+                        let (height, block_hash) = heights[&txid];
+                        History {
+                            txid,
+                            height,
+                            block_hash,
+                        }
+                    })
+                    .collect();
+                (script, history)
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+/// Convert an elements miniscript descriptor to the syntax used by elementsd, without checksum
+///
+/// Elements miniscript prefixes the fragments with `el`, while elementsd uses the bitcoin names.
+fn node_descriptor(desc: &str) -> String {
+    let desc = desc.split('#').next().unwrap_or(desc);
+    ["wpkh(", "wsh(", "sh(", "pkh(", "tr("]
+        .iter()
+        .fold(desc.to_string(), |desc, fragment| {
+            desc.replace(&format!("el{fragment}"), fragment)
+        })
+}
+
+/// Use the same notation for hardened derivation steps, since the node could use a different
+/// one from the imported descriptor
+fn normalize_hardened(desc: &str) -> String {
+    desc.replace('\'', "h")
+}
+
+impl BlockchainBackend for ElementsRpcClient {
+    fn tip(&mut self) -> Result<BlockHeader, Error> {
+        let block_hash: BlockHash = self.call(None, "getbestblockhash", json!([]))?;
+        self.get_header(&block_hash)
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
+        let hex = tx.serialize().to_hex();
+        let txid: String = self.call(None, "sendrawtransaction", json!([hex]))?;
+        Ok(Txid::from_str(&txid)?)
+    }
+
+    fn get_transactions(&self, txids: &[Txid]) -> Result<Vec<Transaction>, Error> {
+        let mut result = vec![];
+        for txid in txids {
+            match self.txs.get(txid) {
+                Some(tx) => result.push(tx.clone()),
+                None => {
+                    // Without -txindex works only for mempool transactions
+                    let hex: String = self.call(None, "getrawtransaction", json!([txid]))?;
+                    let bytes = Vec::<u8>::from_hex(&hex)?;
+                    result.push(Transaction::consensus_decode(&bytes[..])?);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn get_headers(
+        &self,
+        heights: &[Height],
+        height_blockhash: &HashMap<Height, BlockHash>,
+    ) -> Result<Vec<BlockHeader>, Error> {
+        let mut result = vec![];
+        for height in heights {
+            let block_hash = match height_blockhash.get(height) {
+                Some(block_hash) => *block_hash,
+                None => self.call(None, "getblockhash", json!([height]))?,
+            };
+            result.push(self.get_header(&block_hash)?);
+        }
+        Ok(result)
+    }
+
+    fn get_scripts_history(&self, scripts: &[&Script]) -> Result<Vec<Vec<History>>, Error> {
+        Ok(scripts
+            .iter()
+            .map(|script| {
+                self.scripts_history
+                    .get(*script)
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+
+    fn estimate_fee(&self, target_blocks: usize) -> Result<FeeRate, Error> {
+        let estimate: Value = self.call(None, "estimatesmartfee", json!([target_blocks.max(1)]))?;
+        // Without enough data the node doesn't return the fee rate
+        Ok(match estimate.get("feerate").and_then(|f| f.as_f64()) {
+            Some(btc_kvb) => FeeRate::from_btc_kvb(btc_kvb).at_least_min_relay(),
+            None => FeeRate::MIN_RELAY,
+        })
+    }
+
+    fn full_scan_with_gap_limit(
+        &mut self,
+        wollet: &Wollet,
+        gap_limit: u32,
+    ) -> Result<Option<Update>, Error> {
+        self.import_descriptors(wollet, gap_limit)?;
+        self.refresh_scripts_history()?;
+        block_on_ready(scan(&mut SyncBackend(self), wollet, gap_limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::node_descriptor;
+
+    #[test]
+    fn test_node_descriptor() {
+        let xpub = "tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M";
+        assert_eq!(
+            node_descriptor(&format!("elwpkh({xpub}/0/*)#abcdefgh")),
+            format!("wpkh({xpub}/0/*)")
+        );
+        assert_eq!(
+            node_descriptor(&format!("elsh(elwpkh({xpub}/1/*))")),
+            format!("sh(wpkh({xpub}/1/*))")
+        );
+        assert_eq!(
+            node_descriptor(&format!("elwsh(multi(1,{xpub}/0/*))")),
+            format!("wsh(multi(1,{xpub}/0/*))")
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use std::time::Duration;
+
+use base64::prelude::*;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, USER_AGENT};
+
+use crate::Error;
+
+/// Default maximum number of requests in flight of the [`crate::EsploraClient`]
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Default maximum number of retries of a request rate limited by the server
+pub const DEFAULT_MAX_RETRIES: u32 = 6;
+
+/// Default delay before retrying a request rate limited by the server
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Default timeout of the requests through a proxy, Tor circuits can take a while
+pub const PROXY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A builder for the esplora clients
+///
+/// The same options are used by the [`crate::EsploraClient`], built with `build()`, and by the
+/// [`crate::EsploraWasmClient`], built with `build_wasm()`. In the browser the connection is
+/// handled by `fetch`, so proxy, TLS, timeout and concurrency options are ignored there.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "esplora"), allow(dead_code))]
+pub struct EsploraClientBuilder {
+    pub(super) url: String,
+    pub(super) concurrency: usize,
+    pub(super) max_retries: u32,
+    pub(super) retry_delay: Duration,
+    pub(super) timeout: Option<Duration>,
+    pub(super) socks5: Option<String>,
+    pub(super) headers: Vec<(String, String)>,
+    pub(super) accept_invalid_certs: bool,
+    pub(super) root_certificates: Vec<Vec<u8>>,
+}
+
+impl std::fmt::Debug for EsploraClientBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Header values may contain credentials, like API keys, don't leak them in the logs
+        let headers: Vec<_> = self
+            .headers
+            .iter()
+            .map(|(name, _)| (name.as_str(), "<redacted>"))
+            .collect();
+        f.debug_struct("EsploraClientBuilder")
+            .field("url", &self.url)
+            .field("concurrency", &self.concurrency)
+            .field("max_retries", &self.max_retries)
+            .field("retry_delay", &self.retry_delay)
+            .field("timeout", &self.timeout)
+            .field("socks5", &self.socks5)
+            .field("headers", &headers)
+            .field("accept_invalid_certs", &self.accept_invalid_certs)
+            .field("root_certificates", &self.root_certificates.len())
+            .finish()
+    }
+}
+
+impl EsploraClientBuilder {
+    /// Create a builder for an esplora client connecting to `url`
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            concurrency: DEFAULT_CONCURRENCY,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            timeout: None,
+            socks5: None,
+            headers: vec![],
+            accept_invalid_certs: false,
+            root_certificates: vec![],
+        }
+    }
+
+    /// Set the maximum number of requests in flight, 1 means requests are made serially
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set the maximum number of retries of a request rate limited by the server (429 and 503)
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before the first retry, doubled at every subsequent attempt
+    ///
+    /// If the server specifies the delay with the `Retry-After` header, the latter is used.
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Set the timeout of every request
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Connect through the SOCKS5 proxy at `addr`, for instance `127.0.0.1:9050` for Tor
+    ///
+    /// The server host is resolved by the proxy, so `.onion` hosts can be used. If not set
+    /// otherwise, the timeout is [`PROXY_TIMEOUT`].
+    pub fn socks5(mut self, addr: &str) -> Self {
+        self.socks5 = Some(addr.to_string());
+        self
+    }
+
+    /// Add a header sent with every request, setting the same header twice replaces it
+    ///
+    /// The value is considered sensitive and it's not shown in the `Debug` output of the builder
+    /// and the client. Invalid names or values are reported when the client is built.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Authenticate every request with HTTP basic authentication
+    pub fn basic_auth(self, user: &str, password: &str) -> Self {
+        let credentials = BASE64_STANDARD.encode(format!("{user}:{password}"));
+        self.header(AUTHORIZATION.as_str(), &format!("Basic {credentials}"))
+    }
+
+    /// Authenticate every request with the bearer `token`
+    pub fn bearer_auth(self, token: &str) -> Self {
+        self.header(AUTHORIZATION.as_str(), &format!("Bearer {token}"))
+    }
+
+    /// Set the `User-Agent` header
+    pub fn user_agent(self, user_agent: &str) -> Self {
+        self.header(USER_AGENT.as_str(), user_agent)
+    }
+
+    /// Accept invalid TLS certificates, for instance self signed ones
+    ///
+    /// Use only for testing, any certificate is trusted.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Trust the PEM encoded certificate `pem` in addition to the default root certificates
+    pub fn add_root_certificate(mut self, pem: &[u8]) -> Self {
+        self.root_certificates.push(pem.to_vec());
+        self
+    }
+
+    /// The headers sent with every request
+    pub(super) fn default_headers(&self) -> Result<HeaderMap, Error> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in self.headers.iter() {
+            let name = HeaderName::from_bytes(name.as_bytes())?;
+            let mut value = HeaderValue::from_str(value)?;
+            value.set_sensitive(true);
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{AUTHORIZATION, USER_AGENT};
+
+    use super::EsploraClientBuilder;
+
+    #[test]
+    fn test_default_headers() {
+        let builder = EsploraClientBuilder::new("http://127.0.0.1:3000")
+            .header("X-Api-Key", "key")
+            .header("x-api-key", "other")
+            .user_agent("lwk")
+            .bearer_auth("token");
+        let headers = builder.default_headers().unwrap();
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers["x-api-key"], "other");
+        assert_eq!(headers[USER_AGENT], "lwk");
+        assert_eq!(headers[AUTHORIZATION], "Bearer token");
+        assert!(headers.values().all(|v| v.is_sensitive()));
+
+        let headers = builder
+            .basic_auth("user", "pass")
+            .default_headers()
+            .unwrap();
+        assert_eq!(headers[AUTHORIZATION], "Basic dXNlcjpwYXNz");
+
+        let builder = EsploraClientBuilder::new("http://127.0.0.1:3000").header("X Bad", "v");
+        assert!(builder.default_headers().is_err());
+        let builder = EsploraClientBuilder::new("http://127.0.0.1:3000").header("X-Bad", "a\nb");
+        assert!(builder.default_headers().is_err());
+    }
+
+    #[test]
+    fn test_debug_redacts_credentials() {
+        let builder = EsploraClientBuilder::new("http://127.0.0.1:3000")
+            .header("X-Api-Key", "key")
+            .basic_auth("user", "pass");
+        let debug = format!("{builder:?}");
+        assert!(debug.contains("X-Api-Key"));
+        assert!(!debug.contains("key\""));
+        assert!(!debug.contains("dXNlcjpwYXNz"));
+        assert!(debug.contains("<redacted>"));
+
+        #[cfg(feature = "esplora")]
+        {
+            let debug = format!("{:?}", builder.clone().build().unwrap());
+            assert!(!debug.contains("key\""));
+            assert!(!debug.contains("dXNlcjpwYXNz"));
+        }
+
+        let debug = format!("{:?}", builder.bearer_auth("token"));
+        assert!(!debug.contains("token"));
+    }
+}
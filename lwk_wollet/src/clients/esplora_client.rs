@@ -1,4 +1,4 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, str::FromStr, time::Duration};
 
 use elements::{
     encode::Decodable,
@@ -7,35 +7,121 @@ use elements::{
     pset::serialize::Serialize,
     BlockHash, Script, Txid,
 };
-use reqwest::blocking::Response;
+use reqwest::blocking::{Client, Response};
 use serde::Deserialize;
 
-use crate::{store::Height, BlockchainBackend, Error};
+use crate::{store::Height, BlockchainBackend, Error, FeeRate};
 
-use super::History;
+use super::esplora_builder::{
+    EsploraClientBuilder, DEFAULT_CONCURRENCY, DEFAULT_MAX_RETRIES, DEFAULT_RETRY_DELAY,
+    PROXY_TIMEOUT,
+};
+use super::{fee_rate_from_estimates, History};
 
 #[derive(Debug)]
 /// A blockchain backend implementation based on the
 /// [esplora HTTP API](https://github.com/blockstream/esplora/blob/master/API.md)
 pub struct EsploraClient {
+    client: Client,
     base_url: String,
     tip_hash_url: String,
     broadcast_url: String,
+
+    /// Maximum number of requests in flight
+    concurrency: usize,
+
+    /// Maximum number of retries of a request rate limited by the server
+    max_retries: u32,
+
+    /// Delay before the first retry, doubled at every attempt
+    retry_delay: Duration,
+}
+
+impl EsploraClientBuilder {
+    /// Build the [`EsploraClient`]
+    pub fn build(self) -> Result<EsploraClient, Error> {
+        let mut builder = Client::builder();
+        if let Some(addr) = self.socks5.as_ref() {
+            // socks5h to resolve the host through the proxy
+            builder = builder.proxy(reqwest::Proxy::all(format!("socks5h://{addr}"))?);
+        }
+        let timeout = match (self.timeout, self.socks5.as_ref()) {
+            (Some(timeout), _) => Some(timeout),
+            (None, Some(_)) => Some(PROXY_TIMEOUT),
+            (None, None) => None,
+        };
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        for pem in self.root_certificates.iter() {
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(pem)?);
+        }
+        let builder = builder
+            .default_headers(self.default_headers()?)
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+        let url = self.url;
+        Ok(EsploraClient {
+            client: builder.build()?,
+            tip_hash_url: format!("{url}/blocks/tip/hash"),
+            broadcast_url: format!("{url}/tx"),
+            base_url: url,
+            concurrency: self.concurrency,
+            max_retries: self.max_retries,
+            retry_delay: self.retry_delay,
+        })
+    }
 }
 
 impl EsploraClient {
     pub fn new(url: &str) -> Self {
         Self {
+            client: Client::new(),
             base_url: url.to_string(),
             tip_hash_url: format!("{url}/blocks/tip/hash"),
             broadcast_url: format!("{url}/tx"),
+            concurrency: DEFAULT_CONCURRENCY,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_delay: DEFAULT_RETRY_DELAY,
         }
     }
 
+    /// Create a builder to customize the client
+    pub fn builder(url: &str) -> EsploraClientBuilder {
+        EsploraClientBuilder::new(url)
+    }
+
     fn last_block_hash(&mut self) -> Result<elements::BlockHash, crate::Error> {
-        let response = get_with_retry(&self.tip_hash_url, 0)?;
+        let response = self.get_with_retry(&self.tip_hash_url)?;
         Ok(BlockHash::from_str(&response.text()?)?)
     }
+
+    fn get_with_retry(&self, url: &str) -> Result<Response, Error> {
+        let mut attempt = 0;
+        loop {
+            let response = self.client.get(url).send()?;
+            tracing::debug!(
+                "{} status_code:{} body bytes:{:?}",
+                &url,
+                response.status(),
+                response.content_length(),
+            );
+
+            // 429 Too many requests
+            // 503 Service Temporarily Unavailable
+            if response.status() != 429 && response.status() != 503 {
+                return Ok(response);
+            }
+            if attempt >= self.max_retries {
+                return Err(Error::Generic("Too many retry".to_string()));
+            }
+            let wait =
+                retry_after(&response).unwrap_or_else(|| self.retry_delay * (1 << attempt.min(16)));
+
+            tracing::debug!("waiting {wait:?}");
+            std::thread::sleep(wait);
+            attempt += 1;
+        }
+    }
 }
 
 impl BlockchainBackend for EsploraClient {
@@ -43,7 +129,7 @@ impl BlockchainBackend for EsploraClient {
         let last_block_hash = self.last_block_hash()?;
 
         let header_url = format!("{}/block/{}/header", self.base_url, last_block_hash);
-        let response = get_with_retry(&header_url, 0)?;
+        let response = self.get_with_retry(&header_url)?;
         let header_bytes = Vec::<u8>::from_hex(&response.text()?)?;
 
         let header = elements::BlockHeader::consensus_decode(&header_bytes[..])?;
@@ -52,21 +138,22 @@ impl BlockchainBackend for EsploraClient {
 
     fn broadcast(&self, tx: &elements::Transaction) -> Result<elements::Txid, crate::Error> {
         let tx_bytes = tx.serialize();
-        let client = reqwest::blocking::Client::new();
-        let response = client.post(&self.broadcast_url).body(tx_bytes).send()?;
+        let response = self
+            .client
+            .post(&self.broadcast_url)
+            .body(tx_bytes)
+            .send()?;
         let txid = elements::Txid::from_str(&response.text()?)?;
         Ok(txid)
     }
 
     fn get_transactions(&self, txids: &[Txid]) -> Result<Vec<elements::Transaction>, Error> {
-        let mut result = vec![];
-        for txid in txids.iter() {
+        parallel_map(self.concurrency, txids, |txid| {
             let tx_url = format!("{}/tx/{}/raw", self.base_url, txid);
-            let response = get_with_retry(&tx_url, 0)?;
+            let response = self.get_with_retry(&tx_url)?;
             let tx = elements::Transaction::consensus_decode(&response.bytes()?[..])?;
-            result.push(tx);
-        }
-        Ok(result)
+            Ok(tx)
+        })
     }
 
     fn get_headers(
@@ -74,71 +161,83 @@ impl BlockchainBackend for EsploraClient {
         heights: &[Height],
         height_blockhash: &HashMap<Height, BlockHash>,
     ) -> Result<Vec<elements::BlockHeader>, Error> {
-        let mut result = vec![];
-        for height in heights.iter() {
+        parallel_map(self.concurrency, heights, |height| {
             let block_hash = match height_blockhash.get(height) {
                 Some(block_hash) => *block_hash,
                 None => {
                     let block_height = format!("{}/block-height/{}", self.base_url, height);
-                    let response = get_with_retry(&block_height, 0)?;
+                    let response = self.get_with_retry(&block_height)?;
                     BlockHash::from_str(&response.text()?)?
                 }
             };
 
             let block_header = format!("{}/block/{}/header", self.base_url, block_hash);
-            let response = get_with_retry(&block_header, 0)?;
+            let response = self.get_with_retry(&block_header)?;
             let header_bytes = Vec::<u8>::from_hex(&response.text()?)?;
 
             let header = elements::BlockHeader::consensus_decode(&header_bytes[..])?;
-
-            result.push(header);
-        }
-        Ok(result)
+            Ok(header)
+        })
     }
 
     // examples:
     // https://blockstream.info/liquidtestnet/api/address/tex1qntw9m0j2e93n84x975t47ddhgkzx3x8lhfv2nj/txs
     // https://blockstream.info/liquidtestnet/api/scripthash/b50a2a798d876db54acfa0d8dfdc49154ea8defed37b225ec4c9ec7415358ba3/txs
     fn get_scripts_history(&self, scripts: &[&Script]) -> Result<Vec<Vec<History>>, Error> {
-        let mut result: Vec<_> = vec![];
-        for script in scripts.iter() {
+        parallel_map(self.concurrency, scripts, |script| {
             let script = elements::bitcoin::Script::from_bytes(script.as_bytes());
             let script_hash = sha256::Hash::hash(script.as_bytes()).to_byte_array();
             let url = format!("{}/scripthash/{}/txs", self.base_url, script_hash.to_hex());
             // TODO must handle paging -> https://github.com/blockstream/esplora/blob/master/API.md#addresses
-            let response = get_with_retry(&url, 0)?;
+            let response = self.get_with_retry(&url)?;
             let json: Vec<EsploraTx> = response.json()?;
 
             let history: Vec<History> = json.into_iter().map(Into::into).collect();
-            result.push(history)
-        }
-        Ok(result)
+            Ok(history)
+        })
+    }
+
+    fn estimate_fee(&self, target_blocks: usize) -> Result<FeeRate, Error> {
+        let url = format!("{}/fee-estimates", self.base_url);
+        let response = self.get_with_retry(&url)?;
+        let estimates: HashMap<String, f64> = response.json()?;
+        Ok(fee_rate_from_estimates(&estimates, target_blocks))
     }
 }
 
-fn get_with_retry(url: &str, attempt: usize) -> Result<Response, Error> {
-    let response = reqwest::blocking::get(url)?;
-    tracing::debug!(
-        "{} status_code:{} body bytes:{:?}",
-        &url,
-        response.status(),
-        response.content_length(),
-    );
-
-    // 429 Too many requests
-    // 503 Service Temporarily Unavailable
-    if response.status() == 429 || response.status() == 503 {
-        if attempt > 6 {
-            return Err(Error::Generic("Too many retry".to_string()));
-        }
-        let secs = 1 << attempt;
+/// The delay requested by the server with the `Retry-After` header, if expressed in seconds
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?;
+    let secs: u64 = value.to_str().ok()?.parse().ok()?;
+    Some(Duration::from_secs(secs))
+}
 
-        tracing::debug!("waiting {secs}");
-        std::thread::sleep(std::time::Duration::from_secs(secs));
-        get_with_retry(url, attempt + 1)
-    } else {
-        Ok(response)
+/// Apply `f` to every item using up to `concurrency` threads, the results keep the items order
+fn parallel_map<T, R, F>(concurrency: usize, items: &[T], f: F) -> Result<Vec<R>, Error>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> Result<R, Error> + Sync,
+{
+    if concurrency <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
     }
+    let chunk_size = (items.len() + concurrency - 1) / concurrency;
+    let f = &f;
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Result<Vec<_>, _>>()))
+            .collect();
+        let mut result = Vec::with_capacity(items.len());
+        for handle in handles {
+            let chunk_result = handle
+                .join()
+                .map_err(|_| Error::Generic("Request thread panicked".to_string()))?;
+            result.extend(chunk_result?);
+        }
+        Ok(result)
+    })
 }
 
 impl From<EsploraTx> for History {
@@ -169,9 +268,9 @@ struct Status {
 mod tests {
     use std::collections::HashMap;
 
-    use super::EsploraClient;
-    use crate::BlockchainBackend;
-    use elements::{encode::Decodable, BlockHash};
+    use super::{parallel_map, EsploraClient};
+    use crate::{BlockchainBackend, Error};
+    use elements::{encode::Decodable, hashes::Hash, BlockHash};
 
     fn get_block(base_url: &str, hash: BlockHash) -> elements::Block {
         let url = format!("{}/block/{}/raw", base_url, hash);
@@ -219,4 +318,62 @@ mod tests {
         let histories = client.get_scripts_history(&[existing_script]).unwrap();
         assert!(!histories.is_empty())
     }
+
+    #[test]
+    fn test_headers_are_sent() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let hash = BlockHash::all_zeros().to_string();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buf = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                hash.len(),
+                hash
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8(request).unwrap().to_lowercase()
+        });
+
+        let mut client = EsploraClient::builder(&url)
+            .header("X-Api-Key", "secret")
+            .bearer_auth("token")
+            .user_agent("lwk-test")
+            .build()
+            .unwrap();
+        assert_eq!(client.last_block_hash().unwrap(), BlockHash::all_zeros());
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("get /blocks/tip/hash"));
+        assert!(request.contains("x-api-key: secret"));
+        assert!(request.contains("authorization: bearer token"));
+        assert!(request.contains("user-agent: lwk-test"));
+    }
+
+    #[test]
+    fn test_parallel_map() {
+        let items: Vec<u32> = (0..50).collect();
+        let expected: Vec<u32> = items.iter().map(|i| i * 2).collect();
+        for concurrency in [1, 3, 4, 50, 100] {
+            let result = parallel_map(concurrency, &items, |i| Ok(i * 2)).unwrap();
+            assert_eq!(result, expected);
+        }
+
+        let result = parallel_map(4, &items, |i| match i {
+            42 => Err(Error::Generic("fail".to_string())),
+            i => Ok(*i),
+        });
+        assert!(result.is_err());
+
+        let empty: Vec<u32> = vec![];
+        assert!(parallel_map(4, &empty, |i| Ok(*i)).unwrap().is_empty());
+    }
 }
@@ -1,7 +1,7 @@
 use crate::{
-    store::{Height, Store, Timestamp, BATCH_SIZE},
-    update::{DownloadTxResult, Update},
-    Chain, Error, Wollet, WolletDescriptor, EC,
+    store::{is_pruned_tx, Height, Store, Timestamp},
+    update::{DownloadTxResult, Reorg, Update},
+    Chain, Error, FeeRate, Wollet, WolletDescriptor, EC,
 };
 use elements::{
     bitcoin::bip32::ChildNumber,
@@ -11,7 +11,12 @@ use elements::{
 use elements::{BlockHash, BlockHeader, Transaction, Txid};
 use lwk_common::derive_blinding_key;
 use std::collections::{HashMap, HashSet};
-use std::sync::atomic;
+use std::future::Future;
+use std::sync::{atomic, Arc};
+use std::task::{Context, Poll, Wake, Waker};
+
+#[cfg(any(feature = "esplora", feature = "esplora_wasm"))]
+pub(crate) mod esplora_builder;
 
 #[cfg(feature = "esplora")]
 pub(crate) mod esplora_client;
@@ -19,9 +24,17 @@ pub(crate) mod esplora_client;
 #[cfg(feature = "electrum")]
 pub(crate) mod electrum_client;
 
+#[cfg(feature = "electrum_async")]
+pub(crate) mod async_electrum_client;
+
 #[cfg(feature = "esplora_wasm")]
 pub(crate) mod esplora_wasm_client;
 
+#[cfg(feature = "elements_rpc")]
+pub(crate) mod elements_rpc_client;
+
+pub(crate) mod multi_backend;
+
 pub trait BlockchainBackend {
     fn tip(&mut self) -> Result<BlockHeader, Error>;
     fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error>;
@@ -36,216 +49,614 @@ pub trait BlockchainBackend {
 
     fn get_scripts_history(&self, scripts: &[&Script]) -> Result<Vec<Vec<History>>, Error>;
 
-    fn full_scan(&mut self, wollet: &Wollet) -> Result<Option<Update>, Error> {
-        let descriptor = wollet.wollet_descriptor();
-        let store = &wollet.store;
-        let mut txid_height = HashMap::new();
-        let mut scripts = HashMap::new();
-
-        let mut last_unused_external = 0;
-        let mut last_unused_internal = 0;
-        let mut height_blockhash = HashMap::new();
-
-        for descriptor in descriptor.descriptor().clone().into_single_descriptors()? {
-            let mut batch_count = 0;
-            let chain: Chain = (&descriptor).try_into().unwrap_or(Chain::External);
-            loop {
-                let batch = store.get_script_batch(batch_count, &descriptor)?;
-
-                let s: Vec<_> = batch.value.iter().map(|e| &e.0).collect();
-                let result: Vec<Vec<History>> = self.get_scripts_history(&s)?;
-                if !batch.cached {
-                    scripts.extend(batch.value);
-                }
-                let max = result
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, v)| !v.is_empty())
-                    .map(|(i, _)| i as u32)
-                    .max();
-                if let Some(max) = max {
-                    match chain {
-                        Chain::External => {
-                            last_unused_external = 1 + max + batch_count * BATCH_SIZE
-                        }
-                        Chain::Internal => {
-                            last_unused_internal = 1 + max + batch_count * BATCH_SIZE
-                        }
-                    }
-                };
+    /// Estimate the fee rate needed for a transaction to confirm within `target_blocks` blocks
+    ///
+    /// If the backend doesn't have enough data, the minimum relay fee rate is returned, which is
+    /// also what backends without fee estimation return.
+    fn estimate_fee(&self, _target_blocks: usize) -> Result<FeeRate, Error> {
+        Ok(FeeRate::MIN_RELAY)
+    }
 
-                let flattened: Vec<History> = result.into_iter().flatten().collect();
+    /// The status of the given scripts as defined by the electrum protocol, `None` if the backend
+    /// doesn't support them
+    ///
+    /// When supported, the history is downloaded only for the scripts whose status differs from
+    /// the one stored in the wallet.
+    fn get_scripts_status(
+        &mut self,
+        _scripts: &[&Script],
+    ) -> Result<Option<Vec<Option<ScriptStatusHash>>>, Error> {
+        Ok(None)
+    }
 
-                if flattened.is_empty() {
-                    break;
-                }
+    /// Return true if the server notified a new block or a change in the status of the wallet
+    /// scripts since the last call, backends without notifications always return false
+    fn has_notifications(&mut self) -> Result<bool, Error> {
+        Ok(false)
+    }
 
-                for el in flattened {
-                    // el.height = -1 means unconfirmed with unconfirmed parents
-                    // el.height =  0 means unconfirmed with confirmed parents
-                    // but we threat those tx the same
-                    let height = el.height.max(0);
-                    let txid = el.txid;
-                    if height == 0 {
-                        txid_height.insert(txid, None);
-                    } else {
-                        txid_height.insert(txid, Some(height as u32));
-                        if let Some(block_hash) = el.block_hash {
-                            height_blockhash.insert(height as u32, block_hash);
-                        }
-                    }
-                }
+    /// Scan the wallet scripts, stopping after [`Wollet::gap_limit()`] consecutive unused ones,
+    /// and return the [`Update`] to apply to the wallet if anything changed
+    fn full_scan(&mut self, wollet: &Wollet) -> Result<Option<Update>, Error> {
+        self.full_scan_with_gap_limit(wollet, wollet.gap_limit())
+    }
 
-                batch_count += 1;
-            }
+    /// Like [`BlockchainBackend::full_scan()`] but stopping after `gap_limit` consecutive unused
+    /// scripts, a value greater than the wallet one performs a deep rescan
+    ///
+    /// Scripts with history found this way are kept by the following scans, which always reach
+    /// the last used script known by the wallet.
+    fn full_scan_with_gap_limit(
+        &mut self,
+        wollet: &Wollet,
+        gap_limit: u32,
+    ) -> Result<Option<Update>, Error> {
+        block_on_ready(scan(&mut SyncBackend(self), wollet, gap_limit))
+    }
+
+    /// The wallet transaction `txid` with all its proofs, downloaded again if pruned from the
+    /// wallet cache, see [`Wollet::set_pruned_cache()`]
+    fn full_transaction(&self, wollet: &Wollet, txid: &Txid) -> Result<Transaction, Error> {
+        match cached_full_transaction(wollet, txid)? {
+            Some(tx) => Ok(tx),
+            None => find_transaction(self.get_transactions(&[*txid])?, txid),
         }
+    }
+}
 
-        let tip = self.tip()?;
+/// The asynchronous version of [`BlockchainBackend`], the scan logic is shared between the two
+///
+/// The futures are not required to be `Send` so that the trait can be implemented by clients
+/// running in the browser.
+#[allow(async_fn_in_trait)]
+pub trait AsyncBlockchainBackend {
+    async fn tip(&mut self) -> Result<BlockHeader, Error>;
+    async fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error>;
 
-        let history_txs_id: HashSet<Txid> = txid_height.keys().cloned().collect();
-        let new_txs = self.download_txs(&history_txs_id, &scripts, store, &descriptor)?;
-        let history_txs_heights_plus_tip: HashSet<Height> = txid_height
-            .values()
-            .filter_map(|e| *e)
-            .chain(std::iter::once(tip.height))
-            .collect();
-        let timestamps =
-            self.download_headers(&history_txs_heights_plus_tip, &height_blockhash, store)?;
+    async fn get_transactions(&self, txids: &[Txid]) -> Result<Vec<Transaction>, Error>;
 
-        let store_last_unused_external = store
-            .cache
-            .last_unused_external
-            .load(atomic::Ordering::Relaxed);
-        let store_last_unused_internal = store
-            .cache
-            .last_unused_internal
-            .load(atomic::Ordering::Relaxed);
+    async fn get_headers(
+        &self,
+        heights: &[Height],
+        height_blockhash: &HashMap<Height, BlockHash>,
+    ) -> Result<Vec<BlockHeader>, Error>;
+
+    async fn get_scripts_history(&self, scripts: &[&Script]) -> Result<Vec<Vec<History>>, Error>;
 
-        let last_unused_changed = store_last_unused_external != last_unused_external
-            || store_last_unused_internal != last_unused_internal;
+    /// See [`BlockchainBackend::estimate_fee()`]
+    async fn estimate_fee(&self, _target_blocks: usize) -> Result<FeeRate, Error> {
+        Ok(FeeRate::MIN_RELAY)
+    }
 
-        let changed = !new_txs.txs.is_empty()
-            || last_unused_changed
-            || !scripts.is_empty()
-            || !timestamps.is_empty()
-            || store.cache.tip != (tip.height, tip.block_hash());
+    /// See [`BlockchainBackend::get_scripts_status()`]
+    async fn get_scripts_status(
+        &mut self,
+        _scripts: &[&Script],
+    ) -> Result<Option<Vec<Option<ScriptStatusHash>>>, Error> {
+        Ok(None)
+    }
 
-        if changed {
-            tracing::debug!("something changed: !new_txs.txs.is_empty():{} last_unused_changed:{} !scripts.is_empty():{} !timestamps.is_empty():{}", !new_txs.txs.is_empty(), last_unused_changed, !scripts.is_empty(), !timestamps.is_empty() );
+    /// See [`BlockchainBackend::full_scan()`]
+    async fn full_scan(&mut self, wollet: &Wollet) -> Result<Option<Update>, Error> {
+        self.full_scan_with_gap_limit(wollet, wollet.gap_limit())
+            .await
+    }
 
-            let txid_height_new: Vec<_> = txid_height
-                .iter()
-                .filter(|(k, v)| match store.cache.heights.get(*k) {
-                    Some(e) => e != *v,
-                    None => true,
-                })
-                .map(|(k, v)| (*k, *v))
-                .collect();
-            let txid_height_delete: Vec<_> = store
-                .cache
-                .heights
-                .keys()
-                .filter(|k| txid_height.get(*k).is_none())
-                .cloned()
-                .collect();
-
-            let update = Update {
-                new_txs,
-                txid_height_new,
-                txid_height_delete,
-                timestamps,
-                scripts,
-                tip,
-            };
-            Ok(Some(update))
-        } else {
-            Ok(None)
+    /// See [`BlockchainBackend::full_scan_with_gap_limit()`]
+    async fn full_scan_with_gap_limit(
+        &mut self,
+        wollet: &Wollet,
+        gap_limit: u32,
+    ) -> Result<Option<Update>, Error> {
+        scan(self, wollet, gap_limit).await
+    }
+
+    /// See [`BlockchainBackend::full_transaction()`]
+    async fn full_transaction(&self, wollet: &Wollet, txid: &Txid) -> Result<Transaction, Error> {
+        match cached_full_transaction(wollet, txid)? {
+            Some(tx) => Ok(tx),
+            None => find_transaction(self.get_transactions(&[*txid]).await?, txid),
         }
     }
+}
+
+/// The wallet transaction `txid` from the cache, `None` if pruned and to be downloaded again
+fn cached_full_transaction(wollet: &Wollet, txid: &Txid) -> Result<Option<Transaction>, Error> {
+    match wollet.store.cache.all_txs.get(txid) {
+        Some(tx) if is_pruned_tx(tx) => Ok(None),
+        Some(tx) => Ok(Some(tx.clone())),
+        None => Err(Error::MissingTransaction),
+    }
+}
+
+fn find_transaction(txs: Vec<Transaction>, txid: &Txid) -> Result<Transaction, Error> {
+    txs.into_iter()
+        .find(|tx| tx.txid() == *txid)
+        .ok_or(Error::MissingTransaction)
+}
+
+/// Adapter to run the shared scan with a [`BlockchainBackend`], its futures are immediately ready
+struct SyncBackend<'a, B: ?Sized>(&'a mut B);
+
+impl<B: BlockchainBackend + ?Sized> AsyncBlockchainBackend for SyncBackend<'_, B> {
+    async fn tip(&mut self) -> Result<BlockHeader, Error> {
+        self.0.tip()
+    }
+
+    async fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
+        self.0.broadcast(tx)
+    }
+
+    async fn get_transactions(&self, txids: &[Txid]) -> Result<Vec<Transaction>, Error> {
+        self.0.get_transactions(txids)
+    }
 
-    fn download_txs(
+    async fn get_headers(
         &self,
-        history_txs_id: &HashSet<Txid>,
-        scripts: &HashMap<Script, (Chain, ChildNumber)>,
-        store: &Store,
-        descriptor: &WolletDescriptor,
-    ) -> Result<DownloadTxResult, Error> {
-        let mut txs = vec![];
-        let mut unblinds = vec![];
-
-        let mut txs_in_db = store.cache.all_txs.keys().cloned().collect();
-        let txs_to_download: Vec<Txid> = history_txs_id.difference(&txs_in_db).cloned().collect();
-        if !txs_to_download.is_empty() {
-            let txs_downloaded = self.get_transactions(&txs_to_download)?;
-
-            let previous_txs_to_download = HashSet::new();
-            for tx in txs_downloaded.into_iter() {
-                let txid = tx.txid();
-                txs_in_db.insert(txid);
-
-                for (i, output) in tx.output.iter().enumerate() {
-                    // could be the searched script it's not yet in the store, because created in the current run, thus it's searched also in the `scripts`
-                    if store.cache.paths.contains_key(&output.script_pubkey)
-                        || scripts.contains_key(&output.script_pubkey)
-                    {
-                        let vout = i as u32;
-                        let outpoint = OutPoint {
-                            txid: tx.txid(),
-                            vout,
-                        };
-
-                        match try_unblind(output.clone(), descriptor) {
-                            Ok(unblinded) => unblinds.push((outpoint, unblinded)),
-                            Err(_) => tracing::info!("{} cannot unblind, ignoring (could be sender messed up with the blinding process)", outpoint),
+        heights: &[Height],
+        height_blockhash: &HashMap<Height, BlockHash>,
+    ) -> Result<Vec<BlockHeader>, Error> {
+        self.0.get_headers(heights, height_blockhash)
+    }
+
+    async fn get_scripts_history(&self, scripts: &[&Script]) -> Result<Vec<Vec<History>>, Error> {
+        self.0.get_scripts_history(scripts)
+    }
+
+    async fn estimate_fee(&self, target_blocks: usize) -> Result<FeeRate, Error> {
+        self.0.estimate_fee(target_blocks)
+    }
+
+    async fn get_scripts_status(
+        &mut self,
+        scripts: &[&Script],
+    ) -> Result<Option<Vec<Option<ScriptStatusHash>>>, Error> {
+        self.0.get_scripts_status(scripts)
+    }
+}
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// Drive a future whose awaited futures are all immediately ready, like the ones of
+/// [`SyncBackend`], without an async runtime
+///
+/// It's polled only once, there is no runtime to wake it up if it's pending.
+fn block_on_ready<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+    let future = std::pin::pin!(future);
+    match future.poll(&mut cx) {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("sync backend futures are always ready"),
+    }
+}
+
+/// Scan the wallet scripts and return the [`Update`] to reach the latest status, if anything
+/// changed
+///
+/// Shared by [`BlockchainBackend::full_scan()`] and [`AsyncBlockchainBackend::full_scan()`],
+/// which only differ in how the requests are performed.
+pub(crate) async fn scan<B: AsyncBlockchainBackend + ?Sized>(
+    backend: &mut B,
+    wollet: &Wollet,
+    gap_limit: u32,
+) -> Result<Option<Update>, Error> {
+    let gap_limit = gap_limit.max(1);
+    let descriptor = wollet.wollet_descriptor();
+    let store = &wollet.store;
+    let mut txid_height = HashMap::new();
+    let mut scripts = HashMap::new();
+
+    let mut last_unused_external = 0;
+    let mut last_unused_internal = 0;
+    let mut height_blockhash = HashMap::new();
+    let mut scripts_status = vec![];
+    let mut cached_history = None;
+
+    for descriptor in descriptor.descriptor().clone().into_single_descriptors()? {
+        let mut batch_count = 0;
+        let chain: Chain = (&descriptor).try_into().unwrap_or(Chain::External);
+        // Scripts found by a previous deep rescan are scanned even beyond the gap limit
+        let store_last_unused = match chain {
+            Chain::External => &store.cache.last_unused_external,
+            Chain::Internal => &store.cache.last_unused_internal,
+        }
+        .load(atomic::Ordering::Relaxed);
+        loop {
+            let batch = store.get_script_batch(batch_count, gap_limit, &descriptor)?;
+
+            let s: Vec<_> = batch.value.iter().map(|e| &e.0).collect();
+            let result: Vec<Vec<History>> = match backend.get_scripts_status(&s).await? {
+                Some(statuses) => {
+                    let changed = changed_scripts(&s, &statuses, store);
+                    let mut downloaded: HashMap<&Script, Vec<History>> = HashMap::new();
+                    if !changed.is_empty() {
+                        downloaded.extend(
+                            changed
+                                .iter()
+                                .cloned()
+                                .zip(backend.get_scripts_history(&changed).await?),
+                        );
+                    }
+                    let cached =
+                        cached_history.get_or_insert_with(|| cached_scripts_history(store));
+                    for (script, status) in s.iter().zip(statuses) {
+                        if changed.contains(script) {
+                            scripts_status.push(((*script).clone(), status));
                         }
                     }
+                    s.iter()
+                        .map(|script| match downloaded.remove(script) {
+                            Some(history) => history,
+                            None => cached.get(*script).cloned().unwrap_or_default(),
+                        })
+                        .collect()
                 }
+                None => backend.get_scripts_history(&s).await?,
+            };
+            if !batch.cached {
+                scripts.extend(batch.value);
+            }
+            let max = result
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| !v.is_empty())
+                .map(|(i, _)| i as u32)
+                .max();
+            if let Some(max) = max {
+                match chain {
+                    Chain::External => last_unused_external = 1 + max + batch_count * gap_limit,
+                    Chain::Internal => last_unused_internal = 1 + max + batch_count * gap_limit,
+                }
+            };
 
-                // FIXME: If no output is unblinded we should ignore this transaction,
-                // also we should not insert this in `heights`.
-                txs.push((txid, tx));
+            let flattened: Vec<History> = result.into_iter().flatten().collect();
+
+            if flattened.is_empty() && (batch_count + 1) * gap_limit >= store_last_unused {
+                break;
             }
 
-            let txs_to_download: Vec<Txid> = previous_txs_to_download
-                .difference(&txs_in_db)
-                .cloned()
-                .collect();
-            if !txs_to_download.is_empty() {
-                for tx in self.get_transactions(&txs_to_download)? {
-                    txs.push((tx.txid(), tx));
+            for el in flattened {
+                // el.height = -1 means unconfirmed with unconfirmed parents
+                // el.height =  0 means unconfirmed with confirmed parents
+                // but we threat those tx the same
+                let height = el.height.max(0);
+                let txid = el.txid;
+                if height == 0 {
+                    txid_height.insert(txid, None);
+                } else {
+                    txid_height.insert(txid, Some(height as u32));
+                    if let Some(block_hash) = el.block_hash {
+                        height_blockhash.insert(height as u32, block_hash);
+                    }
                 }
             }
-            Ok(DownloadTxResult { txs, unblinds })
-        } else {
-            Ok(DownloadTxResult::default())
+
+            batch_count += 1;
         }
     }
 
-    fn download_headers(
-        &self,
-        history_txs_heights_plus_tip: &HashSet<Height>,
-        height_blockhash: &HashMap<Height, BlockHash>,
-        store: &Store,
-    ) -> Result<Vec<(Height, Timestamp)>, Error> {
-        let mut result = vec![];
-        let heights_in_db: HashSet<Height> =
-            store.cache.heights.iter().filter_map(|(_, h)| *h).collect();
-        let heights_to_download: Vec<Height> = history_txs_heights_plus_tip
-            .difference(&heights_in_db)
+    let tip = backend.tip().await?;
+    let reorg = detect_reorg(backend, &tip, &height_blockhash, store).await?;
+    if let Some(reorg) = reorg {
+        tracing::warn!("reorg detected: {:?}", reorg);
+    }
+
+    let history_txs_id: HashSet<Txid> = txid_height.keys().cloned().collect();
+    let new_txs = download_txs(backend, &history_txs_id, &scripts, store, &descriptor).await?;
+    let history_txs_heights_plus_tip: HashSet<Height> = txid_height
+        .values()
+        .filter_map(|e| *e)
+        .chain(std::iter::once(tip.height))
+        .collect();
+    let heights_to_download =
+        heights_to_download(&history_txs_heights_plus_tip, reorg.as_ref(), store);
+    let (timestamps, block_hashes) =
+        download_headers(backend, &heights_to_download, &height_blockhash).await?;
+
+    let store_last_unused_external = store
+        .cache
+        .last_unused_external
+        .load(atomic::Ordering::Relaxed);
+    let store_last_unused_internal = store
+        .cache
+        .last_unused_internal
+        .load(atomic::Ordering::Relaxed);
+
+    let last_unused_changed = store_last_unused_external != last_unused_external
+        || store_last_unused_internal != last_unused_internal;
+
+    let changed = !new_txs.txs.is_empty()
+        || last_unused_changed
+        || !scripts.is_empty()
+        || !timestamps.is_empty()
+        || reorg.is_some()
+        || !scripts_status.is_empty()
+        || store.cache.tip != (tip.height, tip.block_hash());
+
+    if changed {
+        tracing::debug!("something changed: !new_txs.txs.is_empty():{} last_unused_changed:{} !scripts.is_empty():{} !timestamps.is_empty():{} reorg:{:?}", !new_txs.txs.is_empty(), last_unused_changed, !scripts.is_empty(), !timestamps.is_empty(), reorg);
+
+        let txid_height_new = txid_height_new(&txid_height, reorg.as_ref(), store);
+        let txid_height_delete: Vec<_> = store
+            .cache
+            .heights
+            .keys()
+            .filter(|k| txid_height.get(*k).is_none())
             .cloned()
             .collect();
-        if !heights_to_download.is_empty() {
-            for h in self.get_headers(&heights_to_download, height_blockhash)? {
-                result.push((h.height, h.time))
+
+        let update = Update {
+            new_txs,
+            txid_height_new,
+            txid_height_delete,
+            timestamps,
+            scripts,
+            tip,
+            block_hashes,
+            reorg,
+            scripts_status,
+        };
+        Ok(Some(update))
+    } else {
+        Ok(None)
+    }
+}
+
+async fn download_txs<B: AsyncBlockchainBackend + ?Sized>(
+    backend: &B,
+    history_txs_id: &HashSet<Txid>,
+    scripts: &HashMap<Script, (Chain, ChildNumber)>,
+    store: &Store,
+    descriptor: &WolletDescriptor,
+) -> Result<DownloadTxResult, Error> {
+    let mut txs = vec![];
+    let mut unblinds = vec![];
+
+    let mut txs_in_db = store.cache.all_txs.keys().cloned().collect();
+    let txs_to_download: Vec<Txid> = history_txs_id.difference(&txs_in_db).cloned().collect();
+    if !txs_to_download.is_empty() {
+        let txs_downloaded = backend.get_transactions(&txs_to_download).await?;
+
+        let previous_txs_to_download = HashSet::new();
+        for tx in txs_downloaded.into_iter() {
+            let txid = tx.txid();
+            txs_in_db.insert(txid);
+
+            for (i, output) in tx.output.iter().enumerate() {
+                // could be the searched script it's not yet in the store, because created in the current run, thus it's searched also in the `scripts`
+                if store.cache.paths.contains_key(&output.script_pubkey)
+                    || scripts.contains_key(&output.script_pubkey)
+                {
+                    let vout = i as u32;
+                    let outpoint = OutPoint {
+                        txid: tx.txid(),
+                        vout,
+                    };
+
+                    match try_unblind(output.clone(), descriptor) {
+                        Ok(unblinded) => unblinds.push((outpoint, unblinded)),
+                        Err(_) => tracing::info!("{} cannot unblind, ignoring (could be sender messed up with the blinding process)", outpoint),
+                    }
+                }
             }
 
-            tracing::debug!("{} headers_downloaded", heights_to_download.len());
+            // FIXME: If no output is unblinded we should ignore this transaction,
+            // also we should not insert this in `heights`.
+            txs.push((txid, tx));
+        }
+
+        let txs_to_download: Vec<Txid> = previous_txs_to_download
+            .difference(&txs_in_db)
+            .cloned()
+            .collect();
+        if !txs_to_download.is_empty() {
+            for tx in backend.get_transactions(&txs_to_download).await? {
+                txs.push((tx.txid(), tx));
+            }
+        }
+        Ok(DownloadTxResult { txs, unblinds })
+    } else {
+        Ok(DownloadTxResult::default())
+    }
+}
+
+async fn download_headers<B: AsyncBlockchainBackend + ?Sized>(
+    backend: &B,
+    heights_to_download: &[Height],
+    height_blockhash: &HashMap<Height, BlockHash>,
+) -> Result<HeadersResult, Error> {
+    let mut timestamps = vec![];
+    let mut block_hashes = vec![];
+    if !heights_to_download.is_empty() {
+        for h in backend
+            .get_headers(heights_to_download, height_blockhash)
+            .await?
+        {
+            timestamps.push((h.height, h.time));
+            block_hashes.push((h.height, h.block_hash()));
         }
 
-        Ok(result)
+        tracing::debug!("{} headers_downloaded", heights_to_download.len());
+    }
+
+    Ok((timestamps, block_hashes))
+}
+
+/// Check the blocks known by the wallet are still part of the chain ending in `tip`
+///
+/// If the previous tip of the wallet is still in the chain, the blocks below it are too,
+/// otherwise the block hashes at the confirmation heights of the wallet transactions are
+/// compared to find the lowest replaced block.
+async fn detect_reorg<B: AsyncBlockchainBackend + ?Sized>(
+    backend: &B,
+    tip: &BlockHeader,
+    height_blockhash: &HashMap<Height, BlockHash>,
+    store: &Store,
+) -> Result<Option<Reorg>, Error> {
+    let (store_tip_height, store_tip_hash) = store.cache.tip;
+    if store_tip_height == 0 || store_tip_height > tip.height {
+        // Never scanned, or the server is behind and we can't compare
+        return Ok(None);
     }
+    let current_store_tip_hash = if store_tip_height == tip.height {
+        tip.block_hash()
+    } else {
+        match backend
+            .get_headers(&[store_tip_height], height_blockhash)
+            .await?
+            .first()
+        {
+            Some(header) => header.block_hash(),
+            None => return Ok(None),
+        }
+    };
+    if current_store_tip_hash == store_tip_hash {
+        return Ok(None);
+    }
+
+    let known = known_block_hashes(store);
+    let heights: Vec<Height> = known.iter().map(|(h, _)| *h).collect();
+    let mut current: HashMap<Height, BlockHash> = backend
+        .get_headers(&heights, height_blockhash)
+        .await?
+        .iter()
+        .map(|h| (h.height, h.block_hash()))
+        .collect();
+    current.insert(store_tip_height, current_store_tip_hash);
+
+    Ok(find_reorg(&known, &current, store))
+}
+
+/// The hash identifying the history of a script, as defined by the electrum protocol
+pub type ScriptStatusHash = [u8; 32];
+
+/// The scripts whose status differs from the one stored in the wallet
+pub(crate) fn changed_scripts<'a>(
+    scripts: &[&'a Script],
+    statuses: &[Option<ScriptStatusHash>],
+    store: &Store,
+) -> Vec<&'a Script> {
+    scripts
+        .iter()
+        .zip(statuses.iter())
+        .filter(|(script, status)| store.cache.script_status.get(**script) != status.as_ref())
+        .map(|(script, _)| *script)
+        .collect()
+}
+
+/// The history of the wallet scripts built from the transactions in the store, used for the
+/// scripts whose status didn't change
+pub(crate) fn cached_scripts_history(store: &Store) -> HashMap<Script, Vec<History>> {
+    let cache = &store.cache;
+    let mut result: HashMap<Script, Vec<History>> = HashMap::new();
+    for (txid, height) in cache.heights.iter() {
+        let tx = match cache.all_txs.get(txid) {
+            Some(tx) => tx,
+            None => continue,
+        };
+        let prevouts = tx.input.iter().filter_map(|i| {
+            cache
+                .all_txs
+                .get(&i.previous_output.txid)
+                .and_then(|prev| prev.output.get(i.previous_output.vout as usize))
+        });
+        let scripts: HashSet<&Script> = tx
+            .output
+            .iter()
+            .chain(prevouts)
+            .map(|o| &o.script_pubkey)
+            .filter(|s| cache.paths.contains_key(*s))
+            .collect();
+        for script in scripts {
+            result.entry(script.clone()).or_default().push(History {
+                txid: *txid,
+                height: height.map(|h| h as i32).unwrap_or(0),
+                block_hash: height.and_then(|h| cache.block_hashes.get(&h).cloned()),
+            });
+        }
+    }
+    result
+}
+
+/// Timestamps and block hashes of the downloaded headers
+pub(crate) type HeadersResult = (Vec<(Height, Timestamp)>, Vec<(Height, BlockHash)>);
+
+/// The heights whose header must be downloaded, the ones of the wallet transactions whose block
+/// hash is known and not replaced by a reorg are skipped
+pub(crate) fn heights_to_download(
+    history_txs_heights_plus_tip: &HashSet<Height>,
+    reorg: Option<&Reorg>,
+    store: &Store,
+) -> Vec<Height> {
+    let heights_in_db: HashSet<Height> = store
+        .cache
+        .heights
+        .iter()
+        .filter_map(|(_, h)| *h)
+        .filter(|h| store.cache.block_hashes.contains_key(h))
+        .filter(|h| reorg.map(|r| *h < r.height).unwrap_or(true))
+        .collect();
+    history_txs_heights_plus_tip
+        .difference(&heights_in_db)
+        .cloned()
+        .collect()
+}
+
+/// The transactions whose height changed, including the ones confirmed in replaced blocks which
+/// are unconfirmed by the reorg rollback
+pub(crate) fn txid_height_new(
+    txid_height: &HashMap<Txid, Option<Height>>,
+    reorg: Option<&Reorg>,
+    store: &Store,
+) -> Vec<(Txid, Option<Height>)> {
+    txid_height
+        .iter()
+        .filter(|(k, v)| match store.cache.heights.get(*k) {
+            Some(Some(h)) if reorg.map(|r| *h >= r.height).unwrap_or(false) => true,
+            Some(e) => e != *v,
+            None => true,
+        })
+        .map(|(k, v)| (*k, *v))
+        .collect()
+}
+
+/// The block hashes stored at the confirmation heights of the wallet transactions, sorted by height
+pub(crate) fn known_block_hashes(store: &Store) -> Vec<(Height, BlockHash)> {
+    let heights: HashSet<Height> = store.cache.heights.values().filter_map(|h| *h).collect();
+    let mut known: Vec<(Height, BlockHash)> = heights
+        .into_iter()
+        .filter_map(|h| store.cache.block_hashes.get(&h).map(|b| (h, *b)))
+        .collect();
+    known.sort();
+    known
 }
 
+/// Find the lowest height whose `known` block hash differs from the `current` one, the previous
+/// tip of the wallet is considered too
+pub(crate) fn find_reorg(
+    known: &[(Height, BlockHash)],
+    current: &HashMap<Height, BlockHash>,
+    store: &Store,
+) -> Option<Reorg> {
+    let (store_tip_height, store_tip_hash) = store.cache.tip;
+    known
+        .iter()
+        .chain(std::iter::once(&(store_tip_height, store_tip_hash)))
+        .filter(|(h, hash)| current.get(h) != Some(hash))
+        .map(|(h, _)| *h)
+        .min()
+        .map(|height| Reorg {
+            height,
+            depth: store_tip_height.saturating_sub(height) + 1,
+        })
+}
+
+#[derive(Clone)]
 pub struct History {
     txid: Txid,
 
@@ -273,15 +684,58 @@ pub fn try_unblind(output: TxOut, descriptor: &WolletDescriptor) -> Result<TxOut
     }
 }
 
+/// Pick the fee rate for `target_blocks` from an esplora `/fee-estimates` response, which maps
+/// some confirmation targets to fee rates in sat/vb
+///
+/// The estimate of the closest lower target is used.
+#[cfg(any(feature = "esplora", feature = "esplora_wasm"))]
+pub(crate) fn fee_rate_from_estimates(
+    estimates: &HashMap<String, f64>,
+    target_blocks: usize,
+) -> FeeRate {
+    estimates
+        .iter()
+        .filter_map(|(target, sat_vb)| Some((target.parse::<usize>().ok()?, *sat_vb)))
+        .filter(|(target, _)| *target <= target_blocks.max(1))
+        .max_by_key(|(target, _)| *target)
+        .map(|(_, sat_vb)| FeeRate::from_sat_vb(sat_vb as f32).at_least_min_relay())
+        .unwrap_or(FeeRate::MIN_RELAY)
+}
+
 #[cfg(test)]
 mod tests {
-    use std::time::Instant;
+    use std::{collections::HashMap, time::Instant};
 
     use crate::{
         clients::esplora_client::EsploraClient, BlockchainBackend, ElectrumClient, ElectrumUrl,
-        ElementsNetwork,
+        ElementsNetwork, FeeRate,
     };
 
+    use super::{block_on_ready, fee_rate_from_estimates};
+
+    #[test]
+    fn test_fee_rate_from_estimates() {
+        let estimates: HashMap<String, f64> =
+            serde_json::from_str(r#"{"1":3.0,"2":2.0,"6":1.0,"144":0.01}"#).unwrap();
+        let estimate = |target| fee_rate_from_estimates(&estimates, target);
+        assert_eq!(estimate(0), FeeRate::from_sat_vb(3.0));
+        assert_eq!(estimate(1), FeeRate::from_sat_vb(3.0));
+        assert_eq!(estimate(5), FeeRate::from_sat_vb(2.0));
+        assert_eq!(estimate(6), FeeRate::from_sat_vb(1.0));
+        assert_eq!(estimate(1000), FeeRate::MIN_RELAY);
+        assert_eq!(
+            fee_rate_from_estimates(&HashMap::new(), 1),
+            FeeRate::MIN_RELAY
+        );
+    }
+
+    #[test]
+    fn test_block_on_ready() {
+        assert_eq!(block_on_ready(async { 1 }), 1);
+        let pending = std::panic::catch_unwind(|| block_on_ready(std::future::pending::<()>()));
+        assert!(pending.is_err());
+    }
+
     #[test]
     #[ignore = "test with prod servers"]
     fn esplora_electrum_compare() {
@@ -293,12 +747,20 @@ mod tests {
             "blockstream.info:465",
             "https://blockstream.info/liquidtestnet/api",
             "https://liquid.network/liquidtestnet/api",
+            "https://blockstream.info/liquidtestnet/api",
         ];
 
         let vec: Vec<Box<dyn BlockchainBackend>> = vec![
             Box::new(ElectrumClient::new(&ElectrumUrl::new(urls[0], true, true)).unwrap()),
             Box::new(EsploraClient::new(urls[1])),
             Box::new(EsploraClient::new(urls[2])),
+            // serial requests must give the same results
+            Box::new(
+                EsploraClient::builder(urls[3])
+                    .concurrency(1)
+                    .build()
+                    .unwrap(),
+            ),
         ];
 
         let mut prec = None;
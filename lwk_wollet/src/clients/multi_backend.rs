@@ -0,0 +1,581 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use elements::{BlockHash, BlockHeader, Script, Transaction, Txid};
+
+use crate::store::Height;
+use crate::{BlockchainBackend, Error, FeeRate, Update, Wollet};
+
+use super::{History, ScriptStatusHash};
+
+/// Default number of blocks a backend can be behind the others before being considered lagging
+pub const DEFAULT_MAX_LAG: u32 = 2;
+
+/// The health of a backend of a [`MultiBackend`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendHealth {
+    /// The name given to the backend
+    pub name: String,
+
+    /// Number of consecutive failed requests
+    pub consecutive_failures: u32,
+
+    /// The error of the last failed request
+    pub last_error: Option<String>,
+
+    /// The tip height of the backend at the last cross-check
+    pub tip_height: Option<Height>,
+
+    /// True if at the last cross-checked scan the backend returned different wallet transactions
+    /// than the majority of the backends, or if there was no majority
+    ///
+    /// It's kept until the backend agrees again with the majority.
+    pub disagreeing: bool,
+
+    /// True if at the last cross-check the backend was lagging, on a different chain than the
+    /// others or disagreeing
+    pub suspicious: bool,
+}
+
+struct Entry {
+    backend: Box<dyn BlockchainBackend + Send>,
+    health: Mutex<BackendHealth>,
+
+    /// True if at the last cross-check the backend was lagging or on a different chain
+    off_chain: AtomicBool,
+}
+
+/// A [`BlockchainBackend`] using several backends, failing over to the next one when a request
+/// fails.
+///
+/// Requests are sent to the last backend which succeeded, backends are then tried in the order
+/// they have been added. Optionally the tips and the scan results of different backends are
+/// cross-checked, to detect lagging servers or servers hiding wallet transactions.
+pub struct MultiBackend {
+    entries: Vec<Entry>,
+    current: AtomicUsize,
+    cross_check: bool,
+    max_lag: u32,
+}
+
+impl std::fmt::Debug for MultiBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiBackend")
+            .field("health", &self.health())
+            .field("cross_check", &self.cross_check)
+            .finish()
+    }
+}
+
+impl Default for MultiBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiBackend {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![],
+            current: AtomicUsize::new(0),
+            cross_check: false,
+            max_lag: DEFAULT_MAX_LAG,
+        }
+    }
+
+    /// Add a backend, identified by `name` in the health report
+    pub fn add<B: BlockchainBackend + Send + 'static>(mut self, name: &str, backend: B) -> Self {
+        self.entries.push(Entry {
+            backend: Box::new(backend),
+            health: Mutex::new(BackendHealth {
+                name: name.to_string(),
+                consecutive_failures: 0,
+                last_error: None,
+                tip_height: None,
+                disagreeing: false,
+                suspicious: false,
+            }),
+            off_chain: AtomicBool::new(false),
+        });
+        self
+    }
+
+    /// Before every scan compare the tips of all the backends, and compare the scan result with
+    /// the one of another backend.
+    ///
+    /// Lagging backends are marked as suspicious and used only if there are no other backends.
+    /// If the scan results differ, the other backends are scanned as well and the result of the
+    /// majority is returned, the backends not agreeing with it are marked as suspicious. Without
+    /// a majority, for instance with only two backends, the scan fails with
+    /// [`Error::BackendsMismatch`].
+    pub fn with_cross_check(mut self) -> Self {
+        self.cross_check = true;
+        self
+    }
+
+    /// Set how many blocks a backend can be behind the others before it's considered lagging
+    pub fn with_max_lag(mut self, max_lag: u32) -> Self {
+        self.max_lag = max_lag;
+        self
+    }
+
+    /// The health of the backends, in the order they have been added
+    pub fn health(&self) -> Vec<BackendHealth> {
+        self.entries
+            .iter()
+            .map(|e| lock(&e.health).clone())
+            .collect()
+    }
+
+    /// The indexes of the backends in the order they must be tried
+    fn order(&self) -> Vec<usize> {
+        let len = self.entries.len();
+        let current = self.current.load(Ordering::Relaxed);
+        let (mut trusted, suspicious): (Vec<usize>, Vec<usize>) = (0..len)
+            .map(|i| (current + i) % len)
+            .partition(|i| !lock(&self.entries[*i].health).suspicious);
+        trusted.extend(suspicious);
+        trusted
+    }
+
+    fn record<T>(&self, i: usize, result: &Result<T, Error>) {
+        let mut health = lock(&self.entries[i].health);
+        match result {
+            Ok(_) => {
+                health.consecutive_failures = 0;
+                self.current.store(i, Ordering::Relaxed);
+            }
+            Err(e) => {
+                tracing::warn!("backend {} failed: {}", health.name, e);
+                health.consecutive_failures += 1;
+                health.last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    fn try_each<T>(
+        &self,
+        f: impl Fn(&dyn BlockchainBackend) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut last_error = Error::NoBackend;
+        for i in self.order() {
+            let result = f(self.entries[i].backend.as_ref());
+            self.record(i, &result);
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn try_each_mut<T>(
+        &mut self,
+        mut f: impl FnMut(&mut dyn BlockchainBackend) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut last_error = Error::NoBackend;
+        for i in self.order() {
+            let result = f(self.entries[i].backend.as_mut());
+            self.record(i, &result);
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Query the tips of all the backends and mark as suspicious the lagging ones and the ones
+    /// on a different chain than the majority
+    fn check_tips(&mut self) {
+        let mut tips = vec![];
+        for i in 0..self.entries.len() {
+            let result = self.entries[i].backend.tip();
+            self.record(i, &result);
+            tips.push(result.ok());
+        }
+        let max_height = tips.iter().flatten().map(|t| t.height).max();
+        let mut votes: HashMap<BlockHash, usize> = HashMap::new();
+        for tip in tips.iter().flatten() {
+            if Some(tip.height) == max_height {
+                *votes.entry(tip.block_hash()).or_default() += 1;
+            }
+        }
+        let max_votes = votes.values().max().cloned().unwrap_or(0);
+        let majority: Vec<BlockHash> = votes
+            .into_iter()
+            .filter(|(_, v)| *v == max_votes)
+            .map(|(h, _)| h)
+            .collect();
+
+        for (entry, tip) in self.entries.iter().zip(tips) {
+            let mut health = lock(&entry.health);
+            health.tip_height = tip.as_ref().map(|t| t.height);
+            let off_chain = match (tip, max_height) {
+                (Some(tip), Some(max_height)) => {
+                    let lagging = tip.height + self.max_lag < max_height;
+                    let forked = tip.height == max_height
+                        && majority.len() == 1
+                        && majority[0] != tip.block_hash();
+                    if lagging || forked {
+                        tracing::warn!(
+                            "backend {} suspicious, tip {} {}",
+                            health.name,
+                            tip.height,
+                            tip.block_hash()
+                        );
+                    }
+                    lagging || forked
+                }
+                _ => false,
+            };
+            entry.off_chain.store(off_chain, Ordering::Relaxed);
+            health.suspicious = off_chain || health.disagreeing;
+        }
+    }
+
+    /// Compare the scans done so far, if most of them agree return the index of one of those
+    /// and record which backends disagree
+    fn vote(&self, scans: &[(usize, Option<Update>)], wollet: &Wollet) -> Option<usize> {
+        let tip_height = |u: &Option<Update>| u.as_ref().map(|u| u.tip.height);
+        let height = scans
+            .iter()
+            .filter_map(|(_, u)| tip_height(u))
+            .min()
+            .unwrap_or(wollet.store.cache.tip.0);
+        let txids: Vec<_> = scans
+            .iter()
+            .map(|(_, u)| {
+                u.as_ref()
+                    .map(|u| confirmed_txids(u, height))
+                    .unwrap_or_default()
+            })
+            .collect();
+        let (best, votes) = txids
+            .iter()
+            .map(|t| (t, txids.iter().filter(|other| *other == t).count()))
+            .max_by_key(|(_, votes)| *votes)?;
+        if votes < 2 || votes * 2 <= scans.len() {
+            return None;
+        }
+        for ((i, _), t) in scans.iter().zip(txids.iter()) {
+            let disagreeing = t != best;
+            let mut health = lock(&self.entries[*i].health);
+            if disagreeing {
+                tracing::warn!("backend {} disagrees with the others", health.name);
+            }
+            health.disagreeing = disagreeing;
+            health.suspicious = self.entries[*i].off_chain.load(Ordering::Relaxed) || disagreeing;
+        }
+        txids.iter().position(|t| t == best)
+    }
+
+    /// Scan with the backend `i`, recording the result in its health
+    fn scan_with(
+        &mut self,
+        i: usize,
+        wollet: &Wollet,
+        gap_limit: u32,
+    ) -> Result<Option<Update>, Error> {
+        let result = self.entries[i]
+            .backend
+            .full_scan_with_gap_limit(wollet, gap_limit);
+        self.record(i, &result);
+        result
+    }
+}
+
+/// The wallet transactions confirmed up to `height` and the ones deleted by the update, which
+/// must be the same for every honest backend
+fn confirmed_txids(update: &Update, height: Height) -> HashSet<(Txid, bool)> {
+    update
+        .txid_height_new
+        .iter()
+        .filter(|(_, h)| h.map(|h| h <= height).unwrap_or(false))
+        .map(|(txid, _)| (*txid, true))
+        .chain(update.txid_height_delete.iter().map(|txid| (*txid, false)))
+        .collect()
+}
+
+fn lock(health: &Mutex<BackendHealth>) -> std::sync::MutexGuard<'_, BackendHealth> {
+    // The health is only informative, it can be used even if a thread panicked while holding it
+    health.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl BlockchainBackend for MultiBackend {
+    fn tip(&mut self) -> Result<BlockHeader, Error> {
+        self.try_each_mut(|b| b.tip())
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, Error> {
+        self.try_each(|b| b.broadcast(tx))
+    }
+
+    fn get_transactions(&self, txids: &[Txid]) -> Result<Vec<Transaction>, Error> {
+        self.try_each(|b| b.get_transactions(txids))
+    }
+
+    fn get_headers(
+        &self,
+        heights: &[Height],
+        height_blockhash: &HashMap<Height, BlockHash>,
+    ) -> Result<Vec<BlockHeader>, Error> {
+        self.try_each(|b| b.get_headers(heights, height_blockhash))
+    }
+
+    fn get_scripts_history(&self, scripts: &[&Script]) -> Result<Vec<Vec<History>>, Error> {
+        self.try_each(|b| b.get_scripts_history(scripts))
+    }
+
+    fn estimate_fee(&self, target_blocks: usize) -> Result<FeeRate, Error> {
+        self.try_each(|b| b.estimate_fee(target_blocks))
+    }
+
+    fn get_scripts_status(
+        &mut self,
+        scripts: &[&Script],
+    ) -> Result<Option<Vec<Option<ScriptStatusHash>>>, Error> {
+        self.try_each_mut(|b| b.get_scripts_status(scripts))
+    }
+
+    fn has_notifications(&mut self) -> Result<bool, Error> {
+        let current = self.current.load(Ordering::Relaxed);
+        match self.entries.get_mut(current) {
+            Some(entry) => entry.backend.has_notifications(),
+            None => Err(Error::NoBackend),
+        }
+    }
+
+    /// Scan the whole wallet with a single backend, so that the update is consistent, failing
+    /// over to the next one if the scan fails
+    fn full_scan_with_gap_limit(
+        &mut self,
+        wollet: &Wollet,
+        gap_limit: u32,
+    ) -> Result<Option<Update>, Error> {
+        if self.cross_check {
+            self.check_tips();
+        }
+
+        let mut last_error = Error::NoBackend;
+        let mut scans: Vec<(usize, Option<Update>)> = vec![];
+        // Lagging backends are used only if the others fail
+        let mut order = self.order();
+        order.sort_by_key(|i| self.entries[*i].off_chain.load(Ordering::Relaxed));
+        for i in order {
+            let off_chain = self.entries[i].off_chain.load(Ordering::Relaxed);
+            if off_chain && !scans.is_empty() {
+                break;
+            }
+            let update = match self.scan_with(i, wollet, gap_limit) {
+                Ok(update) => update,
+                Err(e) => {
+                    last_error = e;
+                    continue;
+                }
+            };
+            if !self.cross_check || off_chain {
+                return Ok(update);
+            }
+
+            // Stop as soon as most of the backends scanned agree
+            scans.push((i, update));
+            if scans.len() > 1 {
+                if let Some(pos) = self.vote(&scans, wollet) {
+                    return Ok(scans.swap_remove(pos).1);
+                }
+            }
+        }
+        match scans.len() {
+            0 => Err(last_error),
+            // Nothing to compare with
+            1 => Ok(scans.pop().and_then(|(_, update)| update)),
+            _ => {
+                let name = |k: usize| lock(&self.entries[scans[k].0].health).name.clone();
+                for (i, _) in scans.iter() {
+                    let mut health = lock(&self.entries[*i].health);
+                    health.disagreeing = true;
+                    health.suspicious = true;
+                }
+                Err(Error::BackendsMismatch(name(0), name(1)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ElementsNetwork, NoPersist, WolletDescriptor};
+
+    struct MockBackend {
+        fail: bool,
+        height: Height,
+        confirmed: Vec<Txid>,
+    }
+
+    fn mock(height: Height, confirmed: &[Txid]) -> MockBackend {
+        MockBackend {
+            fail: false,
+            height,
+            confirmed: confirmed.to_vec(),
+        }
+    }
+
+    fn txid(n: u8) -> Txid {
+        use elements::hashes::Hash;
+        Txid::from_byte_array([n; 32])
+    }
+
+    fn wollet() -> Wollet {
+        let desc: WolletDescriptor = lwk_test_util::TEST_DESCRIPTOR.parse().unwrap();
+        Wollet::new(ElementsNetwork::LiquidTestnet, NoPersist::new(), desc).unwrap()
+    }
+
+    fn header(height: Height) -> BlockHeader {
+        let bytes = lwk_test_util::update_test_vector_bytes();
+        let mut header = Update::deserialize(&bytes[..]).unwrap().tip;
+        header.height = height;
+        header
+    }
+
+    impl BlockchainBackend for MockBackend {
+        fn tip(&mut self) -> Result<BlockHeader, Error> {
+            if self.fail {
+                return Err(Error::Generic("down".to_string()));
+            }
+            Ok(header(self.height))
+        }
+
+        fn broadcast(&self, _tx: &Transaction) -> Result<Txid, Error> {
+            Err(Error::Generic("not supported by the mock".to_string()))
+        }
+
+        fn get_transactions(&self, _txids: &[Txid]) -> Result<Vec<Transaction>, Error> {
+            Err(Error::Generic("not supported by the mock".to_string()))
+        }
+
+        fn get_headers(
+            &self,
+            _heights: &[Height],
+            _height_blockhash: &HashMap<Height, BlockHash>,
+        ) -> Result<Vec<BlockHeader>, Error> {
+            Err(Error::Generic("not supported by the mock".to_string()))
+        }
+
+        fn get_scripts_history(&self, _scripts: &[&Script]) -> Result<Vec<Vec<History>>, Error> {
+            Err(Error::Generic("not supported by the mock".to_string()))
+        }
+
+        fn full_scan_with_gap_limit(
+            &mut self,
+            _wollet: &Wollet,
+            _gap_limit: u32,
+        ) -> Result<Option<Update>, Error> {
+            if self.fail {
+                return Err(Error::Generic("down".to_string()));
+            }
+            let bytes = lwk_test_util::update_test_vector_bytes();
+            let mut update = Update::deserialize(&bytes[..]).unwrap();
+            update.tip = header(self.height);
+            update.txid_height_new = self.confirmed.iter().map(|t| (*t, Some(1))).collect();
+            update.txid_height_delete = vec![];
+            Ok(Some(update))
+        }
+
+        fn estimate_fee(&self, _target_blocks: usize) -> Result<FeeRate, Error> {
+            if self.fail {
+                return Err(Error::Generic("down".to_string()));
+            }
+            Ok(FeeRate::from_sat_kvb(self.height as f32))
+        }
+    }
+
+    #[test]
+    fn test_multi_backend_failover() {
+        let mut multi = MultiBackend::new()
+            .add(
+                "a",
+                MockBackend {
+                    fail: true,
+                    height: 1,
+                    confirmed: vec![],
+                },
+            )
+            .add("b", mock(2, &[]));
+        assert_eq!(multi.tip().unwrap().height, 2);
+        assert_eq!(multi.estimate_fee(1).unwrap().to_sat_kvb(), 2.0);
+
+        let health = multi.health();
+        assert_eq!(health[0].consecutive_failures, 1);
+        assert_eq!(health[0].last_error, Some("down".to_string()));
+        assert_eq!(health[1].consecutive_failures, 0);
+
+        let empty = MultiBackend::new();
+        assert!(matches!(empty.estimate_fee(1), Err(Error::NoBackend)));
+    }
+
+    #[test]
+    fn test_multi_backend_lagging() {
+        let mut multi = MultiBackend::new()
+            .add("lagging", mock(10, &[]))
+            .add("ok", mock(20, &[]))
+            .with_cross_check();
+        multi.check_tips();
+        let health = multi.health();
+        assert!(health[0].suspicious);
+        assert_eq!(health[0].tip_height, Some(10));
+        assert!(!health[1].suspicious);
+
+        // The lagging backend is tried last
+        assert_eq!(multi.tip().unwrap().height, 20);
+    }
+
+    #[test]
+    fn test_multi_backend_cross_check() {
+        let wollet = wollet();
+        let (a, b) = (txid(1), txid(2));
+
+        // Two backends disagreeing, there is no way to tell which one is right
+        let mut multi = MultiBackend::new()
+            .add("honest", mock(10, &[a, b]))
+            .add("liar", mock(10, &[a]))
+            .with_cross_check();
+        for _ in 0..2 {
+            let err = multi.full_scan(&wollet).unwrap_err();
+            assert!(matches!(err, Error::BackendsMismatch(_, _)));
+            // The mismatch is not forgotten when the tips are checked again
+            assert!(multi.health().iter().all(|h| h.disagreeing && h.suspicious));
+        }
+
+        // With a third backend the majority wins, the odd one out is marked as suspicious
+        let mut multi = MultiBackend::new()
+            .add("liar", mock(10, &[a]))
+            .add("honest1", mock(10, &[a, b]))
+            .add("honest2", mock(10, &[a, b]))
+            .with_cross_check();
+        for _ in 0..2 {
+            let update = multi.full_scan(&wollet).unwrap().unwrap();
+            assert_eq!(update.txid_height_new.len(), 2);
+            let health = multi.health();
+            assert!(health[0].disagreeing && health[0].suspicious);
+            assert!(!health[1].disagreeing && !health[1].suspicious);
+            assert!(!health[2].disagreeing && !health[2].suspicious);
+        }
+
+        // Lagging backends don't vote
+        let mut multi = MultiBackend::new()
+            .add("honest1", mock(10, &[a, b]))
+            .add("lagging", mock(5, &[a]))
+            .add("honest2", mock(10, &[a, b]))
+            .add("liar", mock(10, &[a]))
+            .with_cross_check();
+        let update = multi.full_scan(&wollet).unwrap().unwrap();
+        assert_eq!(update.txid_height_new.len(), 2);
+        let health = multi.health();
+        assert!(!health[1].disagreeing && health[1].suspicious);
+        assert!(health[3].disagreeing);
+        assert!(!health[0].disagreeing && !health[2].disagreeing);
+    }
+}
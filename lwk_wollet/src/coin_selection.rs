@@ -0,0 +1,373 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, WalletTxOut};
+
+/// Approximated weight of a confidential output, including its rangeproof and surjection proof.
+///
+/// Used only to drive the coin selection, the fee of the transaction is computed on the
+/// actual blinded transaction.
+pub(crate) const CONFIDENTIAL_OUTPUT_WEIGHT: usize = 4_800;
+
+/// Weight of the non-witness part of an input (prevout, empty script_sig, sequence)
+pub(crate) const INPUT_BASE_WEIGHT: usize = (32 + 4 + 1 + 4) * 4;
+
+/// Maximum number of branches explored by [`CoinSelection::BranchAndBound`]
+const BNB_MAX_TRIES: usize = 100_000;
+
+/// The strategy used by the [`crate::TxBuilder`] to choose the UTXOs spent by a transaction
+///
+/// Selection is done independently for each asset sent, L-BTC is selected last so that the fee
+/// of the whole transaction is considered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum CoinSelection {
+    /// Look for a set of UTXOs that doesn't need a change output, if none is found fallback to
+    /// [`CoinSelection::LargestFirst`]
+    #[default]
+    BranchAndBound,
+
+    /// Spend the UTXOs with the highest value first
+    LargestFirst,
+
+    /// Spend the UTXOs with the lowest confirmation height first, unconfirmed UTXOs are spent last
+    OldestFirst,
+
+    /// Spend a single UTXO for every asset sent when possible, the smallest covering the amount,
+    /// to avoid linking UTXOs of the wallet together. If none is big enough fallback to
+    /// [`CoinSelection::LargestFirst`]
+    Privacy,
+}
+
+impl CoinSelection {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CoinSelection::BranchAndBound => "branch-and-bound",
+            CoinSelection::LargestFirst => "largest-first",
+            CoinSelection::OldestFirst => "oldest-first",
+            CoinSelection::Privacy => "privacy",
+        }
+    }
+}
+
+impl Display for CoinSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for CoinSelection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "branch-and-bound" => CoinSelection::BranchAndBound,
+            "largest-first" => CoinSelection::LargestFirst,
+            "oldest-first" => CoinSelection::OldestFirst,
+            "privacy" => CoinSelection::Privacy,
+            _ => return Err(Error::InvalidCoinSelection(s.to_string())),
+        })
+    }
+}
+
+/// Which unconfirmed UTXOs the [`crate::TxBuilder`] can select
+///
+/// Unconfirmed UTXOs received from others can be double spent by the sender, invalidating the
+/// transactions spending them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum SpendUnconfirmed {
+    /// Any unconfirmed UTXO can be selected
+    #[default]
+    All,
+
+    /// Only unconfirmed UTXOs created by transactions spending only wallet UTXOs, like our change
+    OwnOnly,
+
+    /// Unconfirmed UTXOs are never selected
+    None,
+}
+
+impl SpendUnconfirmed {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SpendUnconfirmed::All => "all",
+            SpendUnconfirmed::OwnOnly => "own-only",
+            SpendUnconfirmed::None => "none",
+        }
+    }
+}
+
+impl Display for SpendUnconfirmed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for SpendUnconfirmed {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "all" => SpendUnconfirmed::All,
+            "own-only" => SpendUnconfirmed::OwnOnly,
+            "none" => SpendUnconfirmed::None,
+            _ => return Err(Error::InvalidSpendUnconfirmed(s.to_string())),
+        })
+    }
+}
+
+impl CoinSelection {
+    /// Select UTXOs from `utxos` (all of the same asset) to cover `target`.
+    ///
+    /// `input_cost` is the amount each added input contributes to the target, it's the fee
+    /// of the input when selecting the policy asset, 0 otherwise.
+    ///
+    /// `cost_of_change` is the maximum amount we are willing to lose (giving it to fees) to avoid
+    /// creating a change output, it must be 0 for assets other than the policy asset.
+    ///
+    /// Returns the selected UTXOs or [`Error::InsufficientFunds`] if all the `utxos` are not
+    /// enough to cover the target.
+    pub(crate) fn select(
+        &self,
+        mut utxos: Vec<WalletTxOut>,
+        target: u64,
+        input_cost: u64,
+        cost_of_change: u64,
+    ) -> Result<Vec<WalletTxOut>, Error> {
+        if target == 0 {
+            return Ok(vec![]);
+        }
+        // Spending UTXOs which cost more than their value is never convenient
+        utxos.retain(|u| u.unblinded.value > input_cost);
+        sort_largest_first(&mut utxos);
+
+        match self {
+            CoinSelection::LargestFirst => accumulate(utxos, target, input_cost),
+            CoinSelection::OldestFirst => {
+                utxos.sort_by_key(|u| (u.height.unwrap_or(u32::MAX), u.outpoint));
+                accumulate(utxos, target, input_cost)
+            }
+            CoinSelection::BranchAndBound => {
+                match branch_and_bound(&utxos, target, input_cost, cost_of_change) {
+                    Some(selected) => Ok(selected),
+                    None => accumulate(utxos, target, input_cost),
+                }
+            }
+            CoinSelection::Privacy => {
+                let single = utxos
+                    .iter()
+                    .rev() // smallest first
+                    .find(|u| u.unblinded.value - input_cost >= target);
+                match single {
+                    Some(utxo) => Ok(vec![utxo.clone()]),
+                    None => accumulate(utxos, target, input_cost),
+                }
+            }
+        }
+    }
+}
+
+fn sort_largest_first(utxos: &mut [WalletTxOut]) {
+    utxos.sort_by(|a, b| {
+        b.unblinded
+            .value
+            .cmp(&a.unblinded.value)
+            .then(a.outpoint.cmp(&b.outpoint))
+    });
+}
+
+/// Take UTXOs in the given order until the target is reached
+fn accumulate(
+    utxos: Vec<WalletTxOut>,
+    target: u64,
+    input_cost: u64,
+) -> Result<Vec<WalletTxOut>, Error> {
+    let mut selected = vec![];
+    let mut effective_in = 0;
+    for utxo in utxos {
+        effective_in += utxo.unblinded.value - input_cost;
+        selected.push(utxo);
+        if effective_in >= target {
+            return Ok(selected);
+        }
+    }
+    Err(Error::InsufficientFunds)
+}
+
+/// Depth first search of a set of UTXOs whose effective value is between `target` and
+/// `target + cost_of_change`, UTXOs must be sorted largest first.
+fn branch_and_bound(
+    utxos: &[WalletTxOut],
+    target: u64,
+    input_cost: u64,
+    cost_of_change: u64,
+) -> Option<Vec<WalletTxOut>> {
+    let values: Vec<u64> = utxos
+        .iter()
+        .map(|u| u.unblinded.value - input_cost)
+        .collect();
+    let upper_bound = target.saturating_add(cost_of_change);
+
+    // remaining[i] is the sum of the effective values from i to the end
+    let mut remaining = vec![0u64; values.len() + 1];
+    for i in (0..values.len()).rev() {
+        remaining[i] = remaining[i + 1] + values[i];
+    }
+    if remaining[0] < target {
+        return None;
+    }
+
+    let mut included = vec![false; values.len()];
+    let mut current = 0u64;
+    let mut index = 0usize;
+    for _ in 0..BNB_MAX_TRIES {
+        let backtrack = if current > upper_bound || current + remaining[index] < target {
+            true
+        } else if current >= target {
+            let selected = utxos
+                .iter()
+                .zip(included.iter())
+                .filter(|(_, i)| **i)
+                .map(|(u, _)| u.clone())
+                .collect();
+            return Some(selected);
+        } else {
+            index == values.len()
+        };
+
+        if backtrack {
+            // Go back to the last included UTXO and try the branch excluding it
+            loop {
+                if index == 0 {
+                    return None;
+                }
+                index -= 1;
+                if included[index] {
+                    break;
+                }
+            }
+            included[index] = false;
+            current -= values[index];
+            index += 1;
+        } else {
+            included[index] = true;
+            current += values[index];
+            index += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use elements::{AssetId, OutPoint, Script, TxOutSecrets, Txid};
+
+    use super::*;
+    use crate::hashes::Hash;
+    use crate::Chain;
+
+    fn utxo(vout: u32, value: u64, height: Option<u32>) -> WalletTxOut {
+        WalletTxOut {
+            outpoint: OutPoint::new(Txid::all_zeros(), vout),
+            script_pubkey: Script::default(),
+            height,
+            unblinded: TxOutSecrets {
+                asset: AssetId::default(),
+                value,
+                asset_bf: elements::confidential::AssetBlindingFactor::zero(),
+                value_bf: elements::confidential::ValueBlindingFactor::zero(),
+            },
+            wildcard_index: vout,
+            ext_int: Chain::External,
+        }
+    }
+
+    fn utxos() -> Vec<WalletTxOut> {
+        vec![
+            utxo(0, 1_000, Some(10)),
+            utxo(1, 5_000, None),
+            utxo(2, 3_000, Some(5)),
+            utxo(3, 2_000, Some(20)),
+        ]
+    }
+
+    fn vouts(selected: &[WalletTxOut]) -> Vec<u32> {
+        selected.iter().map(|u| u.outpoint.vout).collect()
+    }
+
+    #[test]
+    fn test_coin_selection_roundtrip() {
+        for c in [
+            CoinSelection::BranchAndBound,
+            CoinSelection::LargestFirst,
+            CoinSelection::OldestFirst,
+            CoinSelection::Privacy,
+        ] {
+            assert_eq!(c, c.to_string().parse().unwrap());
+        }
+        assert!("unknown".parse::<CoinSelection>().is_err());
+
+        for s in [
+            SpendUnconfirmed::All,
+            SpendUnconfirmed::OwnOnly,
+            SpendUnconfirmed::None,
+        ] {
+            assert_eq!(s, s.to_string().parse().unwrap());
+        }
+        assert!("unknown".parse::<SpendUnconfirmed>().is_err());
+    }
+
+    #[test]
+    fn test_largest_first() {
+        let s = CoinSelection::LargestFirst;
+        assert_eq!(vouts(&s.select(utxos(), 6_000, 0, 0).unwrap()), vec![1, 2]);
+        assert_eq!(
+            vouts(&s.select(utxos(), 0, 0, 0).unwrap()),
+            Vec::<u32>::new()
+        );
+        assert!(matches!(
+            s.select(utxos(), 11_001, 0, 0),
+            Err(Error::InsufficientFunds)
+        ));
+        // the input cost is added for every input
+        assert!(matches!(
+            s.select(utxos(), 10_800, 100, 0),
+            Err(Error::InsufficientFunds)
+        ));
+    }
+
+    #[test]
+    fn test_oldest_first() {
+        let s = CoinSelection::OldestFirst;
+        assert_eq!(vouts(&s.select(utxos(), 3_500, 0, 0).unwrap()), vec![2, 0]);
+        assert_eq!(
+            vouts(&s.select(utxos(), 7_000, 0, 0).unwrap()),
+            vec![2, 0, 3, 1]
+        );
+    }
+
+    #[test]
+    fn test_branch_and_bound() {
+        let s = CoinSelection::BranchAndBound;
+        // exact match without change
+        assert_eq!(vouts(&s.select(utxos(), 6_000, 0, 0).unwrap()), vec![1, 0]);
+        assert_eq!(vouts(&s.select(utxos(), 4_000, 0, 0).unwrap()), vec![2, 0]);
+        // match within the cost of change
+        assert_eq!(
+            vouts(&s.select(utxos(), 7_700, 100, 200).unwrap()),
+            vec![1, 2]
+        );
+        // no match, fallback to largest first
+        assert_eq!(vouts(&s.select(utxos(), 5_500, 0, 0).unwrap()), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_privacy() {
+        let s = CoinSelection::Privacy;
+        assert_eq!(vouts(&s.select(utxos(), 1_500, 0, 0).unwrap()), vec![3]);
+        assert_eq!(vouts(&s.select(utxos(), 4_000, 0, 0).unwrap()), vec![1]);
+        // no single utxo is enough
+        assert_eq!(vouts(&s.select(utxos(), 6_000, 0, 0).unwrap()), vec![1, 2]);
+    }
+}
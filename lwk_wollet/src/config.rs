@@ -1,5 +1,6 @@
 use crate::elements::{AddressParams, AssetId};
 use crate::error::Error;
+use crate::FeePolicy;
 use std::str::FromStr;
 
 const LIQUID_POLICY_ASSET_STR: &str =
@@ -43,6 +44,16 @@ impl ElementsNetwork {
         }
     }
 
+    /// The fee policy used by default for the transactions of this network
+    ///
+    /// It's [`FeePolicy::Vsize`] for every network, since nodes not accepting discounted fees
+    /// reject transactions paying less. Callers knowing that the nodes relaying their
+    /// transactions accept them can opt in [`FeePolicy::DiscountedVsize`] with
+    /// [`crate::TxBuilder::fee_policy()`].
+    pub fn fee_policy(&self) -> FeePolicy {
+        FeePolicy::Vsize
+    }
+
     #[cfg(feature = "bindings")]
     pub fn tx_builder(&self) -> crate::TxBuilder {
         crate::TxBuilder::new(*self)
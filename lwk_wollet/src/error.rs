@@ -28,6 +28,14 @@ pub enum Error {
     #[error(transparent)]
     ClientError(#[from] electrum_client::Error),
 
+    #[cfg(feature = "electrum_async")]
+    #[error(transparent)]
+    Join(#[from] tokio::task::JoinError),
+
+    #[cfg(feature = "elements_rpc")]
+    #[error("Elements RPC error {code}: {message}")]
+    ElementsRpc { code: i64, message: String },
+
     #[error(transparent)]
     ElementsEncode(#[from] crate::elements::encode::Error),
 
@@ -76,16 +84,31 @@ pub enum Error {
     #[error(transparent)]
     ParseInt(#[from] std::num::ParseIntError),
 
-    #[cfg(any(feature = "esplora_wasm", feature = "esplora"))]
+    #[cfg(any(
+        feature = "esplora_wasm",
+        feature = "esplora",
+        feature = "elements_rpc"
+    ))]
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
 
+    #[cfg(any(feature = "esplora_wasm", feature = "esplora"))]
+    #[error(transparent)]
+    InvalidHeaderName(#[from] reqwest::header::InvalidHeaderName),
+
+    #[cfg(any(feature = "esplora_wasm", feature = "esplora"))]
+    #[error(transparent)]
+    InvalidHeaderValue(#[from] reqwest::header::InvalidHeaderValue),
+
     #[error(transparent)]
     PersistError(#[from] crate::persister::PersistError),
 
     #[error("Address must be confidential")]
     NotConfidentialAddress,
 
+    #[error("Gap limit must be greater than 0")]
+    InvalidGapLimit,
+
     #[error("Insufficient funds")]
     InsufficientFunds,
 
@@ -148,6 +171,18 @@ pub enum Error {
     #[error("Contract does not commit to asset id")]
     ContractDoesNotCommitToAssetId,
 
+    #[error("Invalid backup")]
+    InvalidBackup,
+
+    #[error("Backup version {version} is not supported, the latest supported is {latest}")]
+    UnsupportedBackupVersion { version: u8, latest: u8 },
+
+    #[error("The backup belongs to another wallet or network")]
+    BackupWalletMismatch,
+
+    #[error("Update version {version} is not supported, the latest supported is {latest}")]
+    UnsupportedUpdateVersion { version: u8, latest: u8 },
+
     #[error("Update height {update_tip_height} too old (internal height {store_tip_height})")]
     UpdateHeightTooOld {
         update_tip_height: u32,
@@ -156,6 +191,48 @@ pub enum Error {
 
     #[error("An issuance has already being set on this tx builder")]
     IssuanceAlreadySet,
+
+    #[error("Invalid coin selection strategy '{0}', valid values are: branch-and-bound, largest-first, oldest-first, privacy")]
+    InvalidCoinSelection(String),
+
+    #[error("Invalid unconfirmed spend policy '{0}', valid values are: all, own-only, none")]
+    InvalidSpendUnconfirmed(String),
+
+    #[error("Wallet does not have the spendable utxo {0}")]
+    MissingWalletUtxo(crate::elements::OutPoint),
+
+    #[error("The utxo {0} is locked")]
+    LockedUtxo(crate::elements::OutPoint),
+
+    #[error("Transaction {0} is already confirmed")]
+    TxAlreadyConfirmed(crate::elements::Txid),
+
+    #[error("Cannot bump the fee of a transaction with inputs not belonging to the wallet")]
+    BumpFeeForeignInput,
+
+    #[error("Cannot bump the fee of a transaction with (re)issuances")]
+    BumpFeeIssuance,
+
+    #[error("Missing recipient for the output {0} of the replaced transaction, pass the original PSET or the recipient")]
+    BumpFeeMissingRecipient(u32),
+
+    #[error("The new fee {new} must be at least {min}, the fees of the replaced transaction and its descendants {original} plus the minimum relay fee of the replacement")]
+    BumpFeeTooLow { original: u64, min: u64, new: u64 },
+
+    #[error("Transaction {0} has no wallet outputs that can be spent")]
+    CpfpNoSpendableOutput(crate::elements::Txid),
+
+    #[error("Transaction {0} doesn't spend or receive wallet funds")]
+    TxNotRelevant(crate::elements::Txid),
+
+    #[error("The wallet must be scanned at least once")]
+    NeverScanned,
+
+    #[error("No blockchain backend available")]
+    NoBackend,
+
+    #[error("Backends {0} and {1} returned different wallet transactions")]
+    BackendsMismatch(String, String),
 }
 
 // cannot derive automatically with this error because of trait bound
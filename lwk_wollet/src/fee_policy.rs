@@ -0,0 +1,115 @@
+use crate::elements::Transaction;
+
+/// Weight saved by a confidential value (33 bytes) counted as an explicit one (9 bytes)
+const VALUE_DISCOUNT_WEIGHT: usize = (33 - 9) * 4;
+
+/// Weight saved by a confidential nonce (33 bytes) counted as an empty one (1 byte)
+const NONCE_DISCOUNT_WEIGHT: usize = (33 - 1) * 4;
+
+/// How the size of a transaction is measured to compute its fee
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeePolicy {
+    /// The virtual size is the weight divided by 4, rangeproofs and surjection proofs of
+    /// confidential outputs are included in the weight
+    Vsize,
+
+    /// The discounted virtual size (ELIP-200), confidential outputs are counted with the weight
+    /// they would have if they were explicit
+    DiscountedVsize,
+}
+
+impl FeePolicy {
+    /// The weight of the transaction according to this policy
+    pub fn weight(&self, tx: &Transaction) -> usize {
+        match self {
+            FeePolicy::Vsize => tx.weight(),
+            FeePolicy::DiscountedVsize => discount_weight(tx),
+        }
+    }
+
+    /// The virtual size of the transaction according to this policy
+    pub fn vsize(&self, tx: &Transaction) -> usize {
+        (self.weight(tx) + 4 - 1) / 4
+    }
+}
+
+/// The weight of the transaction with the confidential outputs counted as explicit ones
+///
+/// Mirrors the discounted weight computed by Elements nodes accepting discounted CT fees.
+fn discount_weight(tx: &Transaction) -> usize {
+    let mut weight = tx.weight();
+    for output in tx.output.iter() {
+        let surjection_proof_len = output
+            .witness
+            .surjection_proof
+            .as_ref()
+            .map(|p| p.serialize().len())
+            .unwrap_or(0);
+        let rangeproof_len = output
+            .witness
+            .rangeproof
+            .as_ref()
+            .map(|p| p.serialize().len())
+            .unwrap_or(0);
+        // Empty proofs are serialized with 1 byte each
+        let witness_weight = varint_len(surjection_proof_len)
+            + surjection_proof_len
+            + varint_len(rangeproof_len)
+            + rangeproof_len;
+        weight = weight.saturating_sub(witness_weight.saturating_sub(2));
+
+        if output.value.is_confidential() {
+            weight = weight.saturating_sub(VALUE_DISCOUNT_WEIGHT);
+        }
+        if output.nonce.is_confidential() {
+            weight = weight.saturating_sub(NONCE_DISCOUNT_WEIGHT);
+        }
+    }
+    weight
+}
+
+/// Length of the consensus encoding of `n` as a variable length integer
+fn varint_len(n: usize) -> usize {
+    match n as u64 {
+        0..=0xfc => 1,
+        0xfd..=0xffff => 3,
+        0x10000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FeePolicy;
+    use crate::ElementsNetwork;
+    use elements::hex::FromHex;
+    use elements::Transaction;
+
+    #[test]
+    fn test_fee_policy() {
+        let tx_hex = include_str!("../tests/data/usdt-issuance-tx.hex");
+        let tx: Transaction =
+            elements::encode::deserialize(&Vec::<u8>::from_hex(tx_hex).unwrap()).unwrap();
+        assert!(tx.output.iter().any(|o| o.value.is_confidential()));
+
+        assert_eq!(FeePolicy::Vsize.weight(&tx), tx.weight());
+        assert_eq!(FeePolicy::Vsize.vsize(&tx), tx.vsize());
+        let discounted = FeePolicy::DiscountedVsize.vsize(&tx);
+        assert!(discounted < tx.vsize());
+
+        // Without confidential outputs the policies agree
+        let mut tx = tx;
+        tx.output.retain(|o| o.is_fee());
+        assert_eq!(
+            FeePolicy::DiscountedVsize.weight(&tx),
+            FeePolicy::Vsize.weight(&tx)
+        );
+
+        // Discounted fees are opt-in
+        assert_eq!(ElementsNetwork::Liquid.fee_policy(), FeePolicy::Vsize);
+        assert_eq!(
+            ElementsNetwork::LiquidTestnet.fee_policy(),
+            FeePolicy::Vsize
+        );
+    }
+}
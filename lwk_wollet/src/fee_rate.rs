@@ -0,0 +1,76 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// A fee rate, in satoshi per 1000 virtual bytes (sat/kvb) like [`crate::TxBuilder::fee_rate()`]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct FeeRate(f32);
+
+impl FeeRate {
+    /// The minimum fee rate relayed by Liquid nodes, 0.1 sat/vb
+    pub const MIN_RELAY: FeeRate = FeeRate(100.0);
+
+    /// Create a fee rate from satoshi per 1000 virtual bytes
+    pub fn from_sat_kvb(sat_kvb: f32) -> Self {
+        FeeRate(sat_kvb)
+    }
+
+    /// Create a fee rate from satoshi per virtual byte
+    pub fn from_sat_vb(sat_vb: f32) -> Self {
+        FeeRate(sat_vb * 1000.0)
+    }
+
+    /// Create a fee rate from BTC per 1000 virtual bytes, the unit used by the nodes and by
+    /// electrum servers
+    pub fn from_btc_kvb(btc_kvb: f64) -> Self {
+        FeeRate((btc_kvb * 100_000_000.0) as f32)
+    }
+
+    /// The fee rate in satoshi per 1000 virtual bytes
+    pub fn to_sat_kvb(&self) -> f32 {
+        self.0
+    }
+
+    /// The fee rate in satoshi per virtual byte
+    pub fn to_sat_vb(&self) -> f32 {
+        self.0 / 1000.0
+    }
+
+    /// Backends return estimates lower than the minimum relay fee rate, or negative ones, if they
+    /// don't have enough data. Such fee rates would make the transaction not relayed.
+    pub(crate) fn at_least_min_relay(self) -> Self {
+        if self.0 < Self::MIN_RELAY.0 {
+            Self::MIN_RELAY
+        } else {
+            self
+        }
+    }
+}
+
+impl Display for FeeRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} sat/vb", self.to_sat_vb())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FeeRate;
+
+    #[test]
+    fn test_fee_rate() {
+        let fee_rate = FeeRate::from_sat_vb(0.1);
+        assert_eq!(fee_rate, FeeRate::MIN_RELAY);
+        assert_eq!(fee_rate.to_sat_kvb(), 100.0);
+        assert_eq!(fee_rate.to_string(), "0.1 sat/vb");
+        assert_eq!(FeeRate::from_btc_kvb(0.000001), FeeRate::MIN_RELAY);
+        assert_eq!(FeeRate::from_sat_kvb(250.0).to_sat_vb(), 0.25);
+
+        assert_eq!(
+            FeeRate::from_btc_kvb(-1.0).at_least_min_relay(),
+            FeeRate::MIN_RELAY
+        );
+        let high = FeeRate::from_sat_vb(2.0);
+        assert_eq!(high.at_least_min_relay(), high);
+    }
+}
@@ -8,39 +8,63 @@
 //!
 //! For an entry point see [`Wollet::new()`]
 
+mod backup;
+mod bump_fee;
 mod clients;
+mod coin_selection;
 mod config;
 mod descriptor;
 mod domain;
 mod error;
+mod fee_policy;
+mod fee_rate;
 mod model;
 mod persister;
 mod pset_create;
 mod registry;
+#[cfg(feature = "sqlite")]
+mod sqlite_persister;
 mod store;
 mod tx_builder;
 mod update;
 mod util;
+mod utxo_lock;
 mod wollet;
 
-pub use crate::clients::BlockchainBackend;
+pub use crate::backup::BACKUP_VERSION;
+pub use crate::clients::multi_backend::{BackendHealth, MultiBackend};
+pub use crate::clients::{AsyncBlockchainBackend, BlockchainBackend};
+pub use crate::coin_selection::{CoinSelection, SpendUnconfirmed};
 pub use crate::config::ElementsNetwork;
 pub use crate::descriptor::{Chain, WolletDescriptor};
 pub use crate::error::Error;
+pub use crate::fee_policy::FeePolicy;
+pub use crate::fee_rate::FeeRate;
 pub use crate::model::{
-    AddressResult, IssuanceDetails, Recipient, UnvalidatedRecipient, WalletTx, WalletTxOut,
+    AddressResult, Balance, IssuanceDetails, Recipient, UnvalidatedRecipient, WalletTx,
+    WalletTxOut, WalletTxStatus,
 };
 pub use crate::persister::{FsPersister, NoPersist, PersistError, Persister};
 pub use crate::registry::{asset_ids, issuance_ids, Contract, Entity};
+#[cfg(feature = "sqlite")]
+pub use crate::sqlite_persister::SqlitePersister;
+pub use crate::store::DEFAULT_GAP_LIMIT;
 pub use crate::tx_builder::{TxBuilder, WolletTxBuilder};
-pub use crate::update::Update;
+pub use crate::update::{Reorg, Update, UPDATE_VERSION};
 pub use crate::util::EC;
-pub use crate::wollet::{Tip, Wollet};
+pub use crate::utxo_lock::{UtxoLock, UtxoLocks, DEFAULT_LOCK_BLOCKS};
+pub use crate::wollet::{Tip, Wollet, WolletEvent};
 
 #[cfg(feature = "electrum")]
 pub use crate::wollet::full_scan_with_electrum_client;
 #[cfg(feature = "electrum")]
-pub use clients::electrum_client::{ElectrumClient, ElectrumUrl};
+pub use clients::electrum_client::{ElectrumClient, ElectrumOptions, ElectrumUrl};
+
+#[cfg(feature = "electrum_async")]
+pub use clients::async_electrum_client::AsyncElectrumClient;
+
+#[cfg(any(feature = "esplora", feature = "esplora_wasm"))]
+pub use clients::esplora_builder::EsploraClientBuilder;
 
 #[cfg(feature = "esplora")]
 pub use clients::esplora_client::EsploraClient;
@@ -48,6 +72,9 @@ pub use clients::esplora_client::EsploraClient;
 #[cfg(feature = "esplora_wasm")]
 pub use clients::esplora_wasm_client::EsploraWasmClient;
 
+#[cfg(feature = "elements_rpc")]
+pub use clients::elements_rpc_client::{ElementsRpcAuth, ElementsRpcClient};
+
 #[cfg(feature = "esplora_wasm")]
 pub use clients::esplora_wasm_client::async_sleep;
 
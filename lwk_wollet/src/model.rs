@@ -28,6 +28,11 @@ pub struct WalletTx {
     pub tx: Transaction,
     pub txid: Txid,
     pub height: Option<u32>,
+
+    /// Missing in transactions serialized by previous versions, which are read as
+    /// [`WalletTxStatus::Pending`]
+    #[serde(default)]
+    pub status: WalletTxStatus,
     pub balance: BTreeMap<AssetId, i64>,
     pub fee: u64,
     pub type_: String,
@@ -36,6 +41,100 @@ pub struct WalletTx {
     pub outputs: Vec<Option<WalletTxOut>>,
 }
 
+/// The state of a wallet transaction, see [`WalletTx::status`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WalletTxStatus {
+    /// Included in a block
+    Confirmed,
+
+    /// Waiting in the mempool, not conflicting with other wallet transactions
+    #[default]
+    Pending,
+
+    /// Evicted from the mempool by the given wallet transaction spending some of the same inputs,
+    /// which is not confirmed yet (e.g. replaced by fee)
+    Replaced(Txid),
+
+    /// Double spent by the given wallet transaction, which is either confirmed, or pending as
+    /// this transaction so that at most one of them will confirm
+    Conflicted(Txid),
+}
+
+impl WalletTxStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WalletTxStatus::Confirmed => "confirmed",
+            WalletTxStatus::Pending => "pending",
+            WalletTxStatus::Replaced(_) => "replaced",
+            WalletTxStatus::Conflicted(_) => "conflicted",
+        }
+    }
+
+    /// The transaction replacing or conflicting with this one
+    pub fn conflicting_txid(&self) -> Option<Txid> {
+        match self {
+            WalletTxStatus::Confirmed | WalletTxStatus::Pending => None,
+            WalletTxStatus::Replaced(txid) | WalletTxStatus::Conflicted(txid) => Some(*txid),
+        }
+    }
+}
+
+impl std::fmt::Display for WalletTxStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Value returned by [`crate::Wollet::balance_details()`], the wallet balance split by the
+/// state of the UTXOs.
+///
+/// Every UTXO is counted in exactly one of the maps, their sum is [`crate::Wollet::balance()`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Balance {
+    /// UTXOs confirmed in a block
+    pub confirmed: BTreeMap<AssetId, u64>,
+
+    /// Unconfirmed UTXOs created by transactions spending only wallet UTXOs, e.g. our own change
+    pub trusted_pending: BTreeMap<AssetId, u64>,
+
+    /// Unconfirmed UTXOs received from others, they could be double spent by the sender
+    pub untrusted_pending: BTreeMap<AssetId, u64>,
+
+    /// Coinbase UTXOs which can't be spent yet
+    pub immature: BTreeMap<AssetId, u64>,
+
+    /// UTXOs locked or frozen, see [`crate::Wollet::lock_utxos()`]
+    pub locked: BTreeMap<AssetId, u64>,
+}
+
+impl Balance {
+    /// The sum of all the UTXOs, whatever their state
+    pub fn total(&self) -> BTreeMap<AssetId, u64> {
+        sum(&[
+            &self.confirmed,
+            &self.trusted_pending,
+            &self.untrusted_pending,
+            &self.immature,
+            &self.locked,
+        ])
+    }
+
+    /// The UTXOs the [`crate::TxBuilder`] can spend without risk, confirmed or trusted pending
+    pub fn spendable(&self) -> BTreeMap<AssetId, u64> {
+        sum(&[&self.confirmed, &self.trusted_pending])
+    }
+}
+
+fn sum(maps: &[&BTreeMap<AssetId, u64>]) -> BTreeMap<AssetId, u64> {
+    let mut r = BTreeMap::new();
+    for map in maps {
+        for (asset, value) in map.iter() {
+            *r.entry(*asset).or_default() += value;
+        }
+    }
+    r
+}
+
 /// A recipient of a transaction.
 ///
 /// Note that, since it doesn't use the [`Address`] but the [`Script`] and the [`PublicKey`] it's
@@ -1,6 +1,7 @@
 use std::{
     fmt::Display,
     fs,
+    io::Write,
     ops::Add,
     path::{Path, PathBuf},
     str::FromStr,
@@ -9,7 +10,7 @@ use std::{
 
 use elements::{bitcoin::hashes::Hash, hashes::sha256t_hash_newtype};
 
-use crate::{ElementsNetwork, Error, Update, WolletDescriptor};
+use crate::{ElementsNetwork, Error, Update, UtxoLocks, WolletDescriptor};
 
 #[derive(thiserror::Error, Debug)]
 pub enum PersistError {
@@ -19,6 +20,10 @@ pub enum PersistError {
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+
     #[error("{0}")]
     Other(String),
 }
@@ -30,7 +35,32 @@ pub trait Persister {
     /// Push and persist an update.
     ///
     /// Implementors are encouraged to coalesce consequent updates with `update.only_tip() == true`
+    /// and may compact the persisted updates in a single one with [`Update::merge()`]
     fn push(&self, update: Update) -> Result<(), PersistError>;
+
+    /// Return the persisted UTXO locks
+    ///
+    /// The default implementation doesn't persist locks.
+    fn get_locks(&self) -> Result<UtxoLocks, PersistError> {
+        Ok(UtxoLocks::default())
+    }
+
+    /// Persist the UTXO locks, replacing the previously persisted ones
+    fn set_locks(&self, _locks: &UtxoLocks) -> Result<(), PersistError> {
+        Ok(())
+    }
+
+    /// Return the persisted gap limit, `None` if never set
+    ///
+    /// The default implementation doesn't persist the gap limit.
+    fn get_gap_limit(&self) -> Result<Option<u32>, PersistError> {
+        Ok(None)
+    }
+
+    /// Persist the gap limit, replacing the previously persisted one
+    fn set_gap_limit(&self, _gap_limit: u32) -> Result<(), PersistError> {
+        Ok(())
+    }
 }
 
 sha256t_hash_newtype! {
@@ -68,6 +98,15 @@ struct FsPersisterInner {
     /// Next free position to write an update
     next: Counter,
 
+    /// File where the UTXO locks are written
+    locks_path: PathBuf,
+
+    /// File where the gap limit is written
+    gap_limit_path: PathBuf,
+
+    /// Number of updates after which they are merged in a single snapshot, never if None
+    compaction_threshold: Option<usize>,
+
     /// used to create the cipher to encrypt data
     desc: WolletDescriptor,
 }
@@ -87,16 +126,16 @@ impl FsPersister {
         network: ElementsNetwork,
         desc: &WolletDescriptor,
     ) -> Result<Arc<Self>, Error> {
-        let mut path = path.as_ref().to_path_buf();
-        path.push(network.as_str());
-        path.push("enc_cache");
-        path.push(DirectoryIdHash::hash(desc.to_string().as_bytes()).to_string());
+        let path = fs_persister_dir(path, network, desc);
         if path.is_file() {
             return Err(Error::Generic("given path is a file".to_string()));
         }
+        recover_compaction(&path)?;
         if !path.exists() {
             fs::create_dir_all(&path)?;
         }
+        let locks_path = path.with_extension("locks");
+        let gap_limit_path = path.with_extension("gap_limit");
         let mut next = Counter::default();
         for el in path.read_dir()? {
             let entry = &el?;
@@ -114,10 +153,76 @@ impl FsPersister {
             inner: Mutex::new(FsPersisterInner {
                 path,
                 next,
+                locks_path,
+                gap_limit_path,
+                compaction_threshold: None,
                 desc: desc.clone(),
             }),
         }))
     }
+
+    /// Set the number of updates after which they are merged in a single snapshot when pushing,
+    /// `None`, the default, disables the automatic compaction
+    ///
+    /// The snapshot doesn't keep the unconfirmed transactions evicted after being persisted, see
+    /// [`Update::merge()`].
+    pub fn set_compaction_threshold(&self, threshold: Option<usize>) -> Result<(), PersistError> {
+        let mut inner = self.inner.lock().map_err(to_other)?;
+        inner.compaction_threshold = threshold;
+        Ok(())
+    }
+
+    /// Merge all the persisted updates in a single snapshot, see [`Update::merge()`]
+    ///
+    /// The snapshot is written in a new directory which then replaces the previous one, if the
+    /// process is interrupted the next [`FsPersister::new()`] completes or rolls back the
+    /// replacement.
+    pub fn compact(&self) -> Result<(), PersistError> {
+        let mut inner = self.inner.lock().map_err(to_other)?;
+        inner.compact()
+    }
+}
+
+/// Replace the content of the file at `path`, writing a temporary file first and renaming it so
+/// that a crash doesn't leave the file truncated
+fn write_atomic(path: &Path, content: &[u8]) -> Result<(), std::io::Error> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Complete or roll back an interrupted compaction of the updates in `path`
+fn recover_compaction(path: &Path) -> Result<(), std::io::Error> {
+    let compact_path = path.with_extension("compact");
+    let old_path = path.with_extension("old");
+    if !path.exists() && compact_path.exists() {
+        // The snapshot was completely written before moving away the previous updates
+        fs::rename(&compact_path, path)?;
+    }
+    if compact_path.exists() {
+        fs::remove_dir_all(&compact_path)?;
+    }
+    if old_path.exists() {
+        fs::remove_dir_all(&old_path)?;
+    }
+    Ok(())
+}
+
+/// The directory where a [`FsPersister`] created with the same arguments writes the updates
+pub(crate) fn fs_persister_dir<P: AsRef<Path>>(
+    path: P,
+    network: ElementsNetwork,
+    desc: &WolletDescriptor,
+) -> PathBuf {
+    let mut path = path.as_ref().to_path_buf();
+    path.push(network.as_str());
+    path.push("enc_cache");
+    path.push(DirectoryIdHash::hash(desc.to_string().as_bytes()).to_string());
+    path
 }
 
 impl FsPersisterInner {
@@ -148,9 +253,40 @@ impl FsPersisterInner {
             Ok(None)
         }
     }
+
+    fn compact(&mut self) -> Result<(), PersistError> {
+        if self.next.0 < 2 {
+            return Ok(());
+        }
+        let mut snapshot = self
+            .get(0)?
+            .ok_or_else(|| PersistError::Other("missing first update".to_string()))?;
+        for index in 1..self.next.0 {
+            if let Some(update) = self.get(index)? {
+                snapshot.merge(update);
+            }
+        }
+        let ciphertext = snapshot
+            .serialize_encrypted(&self.desc)
+            .map_err(|e| PersistError::Other(e.to_string()))?;
+
+        let compact_path = self.path.with_extension("compact");
+        let old_path = self.path.with_extension("old");
+        recover_compaction(&self.path)?;
+        fs::create_dir(&compact_path)?;
+        let mut file = fs::File::create(compact_path.join(Counter::default().to_string()))?;
+        file.write_all(&ciphertext)?;
+        file.sync_all()?;
+
+        fs::rename(&self.path, &old_path)?;
+        fs::rename(&compact_path, &self.path)?;
+        fs::remove_dir_all(&old_path)?;
+        self.next = Counter::from(1);
+        Ok(())
+    }
 }
 
-fn to_other<D: std::fmt::Debug>(d: D) -> PersistError {
+pub(crate) fn to_other<D: std::fmt::Debug>(d: D) -> PersistError {
     PersistError::Other(format!("{d:?}"))
 }
 
@@ -162,6 +298,9 @@ impl Persister for FsPersister {
 
     fn push(&self, update: Update) -> Result<(), PersistError> {
         let mut inner = self.inner.lock().map_err(to_other)?;
+        if !inner.path.exists() {
+            recover_compaction(&inner.path)?;
+        }
         if update.only_tip() {
             if let Ok(Some(prev_update)) = inner.last() {
                 if prev_update.only_tip() {
@@ -178,6 +317,48 @@ impl Persister for FsPersister {
 
         fs::write(path, ciphertext)?;
         inner.next = inner.next.clone() + 1;
+
+        if matches!(inner.compaction_threshold, Some(t) if inner.next.0 >= t) {
+            // The update is already persisted, a failed compaction is recovered at the next push
+            if let Err(e) = inner.compact() {
+                tracing::warn!("failed to compact updates: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    fn get_locks(&self) -> Result<UtxoLocks, PersistError> {
+        let inner = self.inner.lock().map_err(to_other)?;
+        if !inner.locks_path.exists() {
+            return Ok(UtxoLocks::default());
+        }
+        let bytes = fs::read(&inner.locks_path)?;
+        UtxoLocks::deserialize_decrypted(&bytes, &inner.desc)
+            .map_err(|e| PersistError::Other(e.to_string()))
+    }
+
+    fn set_locks(&self, locks: &UtxoLocks) -> Result<(), PersistError> {
+        let inner = self.inner.lock().map_err(to_other)?;
+        let ciphertext = locks
+            .serialize_encrypted(&inner.desc)
+            .map_err(|e| PersistError::Other(e.to_string()))?;
+        write_atomic(&inner.locks_path, &ciphertext)?;
+        Ok(())
+    }
+
+    fn get_gap_limit(&self) -> Result<Option<u32>, PersistError> {
+        let inner = self.inner.lock().map_err(to_other)?;
+        if !inner.gap_limit_path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&inner.gap_limit_path)?;
+        let gap_limit = content.trim().parse().map_err(to_other)?;
+        Ok(Some(gap_limit))
+    }
+
+    fn set_gap_limit(&self, gap_limit: u32) -> Result<(), PersistError> {
+        let inner = self.inner.lock().map_err(to_other)?;
+        write_atomic(&inner.gap_limit_path, gap_limit.to_string().as_bytes())?;
         Ok(())
     }
 }
@@ -234,9 +415,12 @@ mod test {
         sync::{Arc, Mutex},
     };
 
-    use crate::{ElementsNetwork, FsPersister, PersistError, Update, WolletDescriptor};
+    use crate::elements::OutPoint;
+    use crate::{
+        ElementsNetwork, FsPersister, PersistError, Update, UtxoLock, UtxoLocks, WolletDescriptor,
+    };
 
-    use super::{Counter, NoPersist, Persister};
+    use super::{fs_persister_dir, Counter, NoPersist, Persister};
 
     struct MemoryPersister(Mutex<Vec<Update>>);
     impl MemoryPersister {
@@ -311,6 +495,120 @@ mod test {
         inner_test_persister(persister, false);
     }
 
+    #[test]
+    fn test_fs_persister_locks() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let desc = wollet_descriptor_test_vector();
+        let n = ElementsNetwork::LiquidTestnet;
+        let persister = FsPersister::new(&tempdir, n, &desc).unwrap();
+        assert_eq!(persister.get_locks().unwrap(), UtxoLocks::default());
+
+        let mut locks = UtxoLocks::default();
+        locks.0.insert(OutPoint::null(), UtxoLock::Frozen);
+        persister.set_locks(&locks).unwrap();
+        let persister = FsPersister::new(&tempdir, n, &desc).unwrap();
+        assert_eq!(persister.get_locks().unwrap(), locks);
+
+        // Locks are replaced without leaving temporary files around
+        let path = fs_persister_dir(&tempdir, n, &desc);
+        let tmp_files = || {
+            path.parent()
+                .unwrap()
+                .read_dir()
+                .unwrap()
+                .filter(|e| e.as_ref().unwrap().path().extension() == Some("tmp".as_ref()))
+                .count()
+        };
+        persister.set_locks(&UtxoLocks::default()).unwrap();
+        assert_eq!(persister.get_locks().unwrap(), UtxoLocks::default());
+        assert_eq!(tmp_files(), 0);
+        persister.set_locks(&locks).unwrap();
+
+        // Locks file doesn't interfere with updates
+        inner_test_persister(persister, true);
+    }
+
+    #[test]
+    fn test_fs_persister_gap_limit() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let desc = wollet_descriptor_test_vector();
+        let n = ElementsNetwork::LiquidTestnet;
+        let persister = FsPersister::new(&tempdir, n, &desc).unwrap();
+        assert_eq!(persister.get_gap_limit().unwrap(), None);
+
+        persister.set_gap_limit(100).unwrap();
+        let persister = FsPersister::new(&tempdir, n, &desc).unwrap();
+        assert_eq!(persister.get_gap_limit().unwrap(), Some(100));
+
+        // Gap limit file doesn't interfere with updates
+        inner_test_persister(persister, true);
+    }
+
+    #[test]
+    fn test_fs_persister_compaction() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let desc = wollet_descriptor_test_vector();
+        let n = ElementsNetwork::LiquidTestnet;
+        let dir = fs_persister_dir(&tempdir, n, &desc);
+        let persister = FsPersister::new(&tempdir, n, &desc).unwrap();
+        inner_test_persister(persister.clone(), true);
+        let update1 = persister.get(0).unwrap().unwrap();
+        let update2 = persister.get(1).unwrap().unwrap();
+        let mut merged = update1.clone();
+        merged.merge(update2);
+
+        persister.compact().unwrap();
+        assert_eq!(persister.get(0).unwrap().unwrap(), merged);
+        assert!(persister.get(1).unwrap().is_none());
+        assert!(!dir.with_extension("compact").exists());
+        assert!(!dir.with_extension("old").exists());
+
+        let persister = FsPersister::new(&tempdir, n, &desc).unwrap();
+        assert_eq!(persister.get(0).unwrap().unwrap(), merged);
+        persister.push(update1.clone()).unwrap();
+        assert_eq!(persister.get(1).unwrap().unwrap(), update1);
+
+        // Interrupted while writing the snapshot, the previous updates are kept
+        std::fs::create_dir(dir.with_extension("compact")).unwrap();
+        std::fs::write(dir.with_extension("compact").join("partial"), b"x").unwrap();
+        let persister = FsPersister::new(&tempdir, n, &desc).unwrap();
+        assert!(!dir.with_extension("compact").exists());
+        assert_eq!(persister.get(1).unwrap().unwrap(), update1);
+
+        // Interrupted after moving away the previous updates, the snapshot is used
+        std::fs::rename(&dir, dir.with_extension("old")).unwrap();
+        std::fs::create_dir(dir.with_extension("compact")).unwrap();
+        std::fs::copy(
+            dir.with_extension("old").join(Counter::from(0).to_string()),
+            dir.with_extension("compact")
+                .join(Counter::from(0).to_string()),
+        )
+        .unwrap();
+        let persister = FsPersister::new(&tempdir, n, &desc).unwrap();
+        assert!(!dir.with_extension("old").exists());
+        assert_eq!(persister.get(0).unwrap().unwrap(), merged);
+        assert!(persister.get(1).unwrap().is_none());
+
+        // No automatic compaction by default
+        for _ in 0..3 {
+            persister.push(update1.clone()).unwrap();
+            merged.merge(update1.clone());
+        }
+        assert_eq!(persister.get(3).unwrap().unwrap(), update1);
+        persister.compact().unwrap();
+        assert_eq!(persister.get(0).unwrap().unwrap(), merged);
+
+        // Automatic compaction when pushing
+        persister.set_compaction_threshold(Some(3)).unwrap();
+        persister.push(update1.clone()).unwrap();
+        assert_eq!(persister.get(1).unwrap().unwrap(), update1);
+        persister.push(update1.clone()).unwrap();
+        assert!(persister.get(1).unwrap().is_none());
+        merged.merge(update1.clone());
+        merged.merge(update1);
+        assert_eq!(persister.get(0).unwrap().unwrap(), merged);
+    }
+
     #[test]
     fn test_counter() {
         let c = Counter::default();
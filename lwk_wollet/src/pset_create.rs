@@ -2,7 +2,9 @@ use crate::bitcoin::PublicKey as BitcoinPublicKey;
 use crate::elements::confidential::AssetBlindingFactor;
 use crate::elements::issuance::ContractHash;
 use crate::elements::pset::{Input, Output, PartiallySignedTransaction};
-use crate::elements::{Address, AssetId, OutPoint, Transaction, TxOut, TxOutSecrets, Txid};
+use crate::elements::{
+    Address, AssetId, OutPoint, Sequence, Transaction, TxOut, TxOutSecrets, Txid,
+};
 use crate::error::Error;
 use crate::hashes::Hash;
 use crate::model::{Recipient, WalletTxOut};
@@ -22,14 +24,6 @@ pub enum IssuanceRequest {
 }
 
 impl Wollet {
-    pub(crate) fn asset_utxos(&self, asset: &AssetId) -> Result<Vec<WalletTxOut>, Error> {
-        Ok(self
-            .utxos()?
-            .into_iter()
-            .filter(|utxo| &utxo.unblinded.asset == asset)
-            .collect())
-    }
-
     fn get_tx(&self, txid: &Txid) -> Result<Transaction, Error> {
         Ok(self
             .store
@@ -75,6 +69,8 @@ impl Wollet {
         utxo: &WalletTxOut,
     ) -> Result<usize, Error> {
         let mut input = Input::from_prevout(utxo.outpoint);
+        // Signal RBF so that the transaction can be replaced with Wollet::bump_fee
+        input.sequence = Some(Sequence::ENABLE_RBF_NO_LOCKTIME);
         let mut txout = self.get_txout(&utxo.outpoint)?;
         // This field is used by stateless blinders or signers to
         // learn the blinding factors and unblinded values of this input.
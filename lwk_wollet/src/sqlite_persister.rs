@@ -0,0 +1,381 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use elements::bitcoin::hashes::Hash;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::persister::{fs_persister_dir, to_other, DirectoryIdHash};
+use crate::{
+    ElementsNetwork, Error, FsPersister, PersistError, Persister, Update, UtxoLocks,
+    WolletDescriptor,
+};
+
+/// Name of the database file created in the network subdirectory
+const DB_FILE_NAME: &str = "lwk.sqlite";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS updates (
+    wallet_id TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    only_tip INTEGER NOT NULL,
+    data BLOB NOT NULL,
+    PRIMARY KEY (wallet_id, idx)
+);
+CREATE TABLE IF NOT EXISTS wallets (
+    wallet_id TEXT PRIMARY KEY NOT NULL,
+    locks BLOB,
+    gap_limit INTEGER
+);
+";
+
+/// A [`Persister`] writing the wallet updates in a SQLite database
+///
+/// Several wallets can share the same database, the rows of each wallet are identified by a
+/// one-way hash of its descriptor and their content is encrypted with a key derived from the
+/// descriptor, as in the [`FsPersister`]. Every write happens in a transaction, so an
+/// interrupted write never leaves a partial update.
+pub struct SqlitePersister {
+    conn: Mutex<Connection>,
+
+    /// Identifies the rows of this wallet
+    wallet_id: String,
+
+    network: ElementsNetwork,
+
+    /// used to create the cipher to encrypt data
+    desc: WolletDescriptor,
+}
+
+impl SqlitePersister {
+    /// Creates a persister writing in the database `lwk.sqlite` in the network subdirectory of
+    /// the given path, creating it if needed
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        network: ElementsNetwork,
+        desc: &WolletDescriptor,
+    ) -> Result<Arc<Self>, Error> {
+        let mut db_path = path.as_ref().to_path_buf();
+        db_path.push(network.as_str());
+        std::fs::create_dir_all(&db_path)?;
+        db_path.push(DB_FILE_NAME);
+        let conn = Connection::open(db_path).map_err(PersistError::from)?;
+        conn.execute_batch(SCHEMA).map_err(PersistError::from)?;
+
+        Ok(Arc::new(Self {
+            conn: Mutex::new(conn),
+            wallet_id: DirectoryIdHash::hash(desc.to_string().as_bytes()).to_string(),
+            network,
+            desc: desc.clone(),
+        }))
+    }
+
+    /// Import the updates, the UTXO locks and the gap limit written by a [`FsPersister`] created
+    /// with the same path, network and descriptor
+    ///
+    /// Nothing is imported if this persister already contains updates of the wallet, so it can
+    /// be called at every start. The files of the [`FsPersister`] are not removed. Returns the
+    /// number of imported updates.
+    pub fn migrate_from_fs<P: AsRef<Path>>(&self, path: P) -> Result<usize, Error> {
+        if !fs_persister_dir(&path, self.network, &self.desc).is_dir() || self.len()? > 0 {
+            return Ok(0);
+        }
+        let fs = FsPersister::new(&path, self.network, &self.desc)?;
+        let mut updates = vec![];
+        for i in 0.. {
+            match fs.get(i)? {
+                Some(update) => updates.push(update),
+                None => break,
+            }
+        }
+        let locks = fs.get_locks()?;
+        let gap_limit = fs.get_gap_limit()?;
+
+        self.import(&updates, &locks, gap_limit)?;
+        Ok(updates.len())
+    }
+
+    /// Write all the given data in a single transaction
+    fn import(
+        &self,
+        updates: &[Update],
+        locks: &UtxoLocks,
+        gap_limit: Option<u32>,
+    ) -> Result<(), PersistError> {
+        let mut conn = self.conn.lock().map_err(to_other)?;
+        let tx = conn.transaction()?;
+        for (idx, update) in updates.iter().enumerate() {
+            let data = update.serialize_encrypted(&self.desc).map_err(to_other)?;
+            tx.execute(
+                "INSERT INTO updates (wallet_id, idx, only_tip, data) VALUES (?1, ?2, ?3, ?4)",
+                params![self.wallet_id, idx as i64, update.only_tip(), data],
+            )?;
+        }
+        let locks = locks.serialize_encrypted(&self.desc).map_err(to_other)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO wallets (wallet_id, locks, gap_limit) VALUES (?1, ?2, ?3)",
+            params![self.wallet_id, locks, gap_limit],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The number of updates persisted for the wallet
+    pub fn len(&self) -> Result<usize, PersistError> {
+        let conn = self.conn.lock().map_err(to_other)?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM updates WHERE wallet_id = ?1",
+            params![self.wallet_id],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    /// Returns true if no update has been persisted for the wallet
+    pub fn is_empty(&self) -> Result<bool, PersistError> {
+        Ok(self.len()? == 0)
+    }
+}
+
+impl Persister for SqlitePersister {
+    fn get(&self, index: usize) -> Result<Option<Update>, PersistError> {
+        let conn = self.conn.lock().map_err(to_other)?;
+        let data: Option<Vec<u8>> = conn
+            .query_row(
+                "SELECT data FROM updates WHERE wallet_id = ?1 AND idx = ?2",
+                params![self.wallet_id, index as i64],
+                |row| row.get(0),
+            )
+            .optional()?;
+        data.map(|data| Update::deserialize_decrypted(&data, &self.desc).map_err(to_other))
+            .transpose()
+    }
+
+    fn push(&self, update: Update) -> Result<(), PersistError> {
+        let data = update.serialize_encrypted(&self.desc).map_err(to_other)?;
+        let only_tip = update.only_tip();
+
+        let mut conn = self.conn.lock().map_err(to_other)?;
+        let tx = conn.transaction()?;
+        let last: Option<(i64, bool)> = tx
+            .query_row(
+                "SELECT idx, only_tip FROM updates WHERE wallet_id = ?1 ORDER BY idx DESC LIMIT 1",
+                params![self.wallet_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let idx = match last {
+            // since this update and the last are only an update of the tip, we can overwrite
+            // the last update
+            Some((idx, true)) if only_tip => idx,
+            Some((idx, _)) => idx + 1,
+            None => 0,
+        };
+        tx.execute(
+            "INSERT OR REPLACE INTO updates (wallet_id, idx, only_tip, data) VALUES (?1, ?2, ?3, ?4)",
+            params![self.wallet_id, idx, only_tip, data],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_locks(&self) -> Result<UtxoLocks, PersistError> {
+        let conn = self.conn.lock().map_err(to_other)?;
+        let locks: Option<Vec<u8>> = conn
+            .query_row(
+                "SELECT locks FROM wallets WHERE wallet_id = ?1",
+                params![self.wallet_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        match locks {
+            Some(locks) => UtxoLocks::deserialize_decrypted(&locks, &self.desc).map_err(to_other),
+            None => Ok(UtxoLocks::default()),
+        }
+    }
+
+    fn set_locks(&self, locks: &UtxoLocks) -> Result<(), PersistError> {
+        let locks = locks.serialize_encrypted(&self.desc).map_err(to_other)?;
+        let conn = self.conn.lock().map_err(to_other)?;
+        conn.execute(
+            "INSERT INTO wallets (wallet_id, locks) VALUES (?1, ?2)
+             ON CONFLICT (wallet_id) DO UPDATE SET locks = excluded.locks",
+            params![self.wallet_id, locks],
+        )?;
+        Ok(())
+    }
+
+    fn get_gap_limit(&self) -> Result<Option<u32>, PersistError> {
+        let conn = self.conn.lock().map_err(to_other)?;
+        Ok(conn
+            .query_row(
+                "SELECT gap_limit FROM wallets WHERE wallet_id = ?1",
+                params![self.wallet_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten())
+    }
+
+    fn set_gap_limit(&self, gap_limit: u32) -> Result<(), PersistError> {
+        let conn = self.conn.lock().map_err(to_other)?;
+        conn.execute(
+            "INSERT INTO wallets (wallet_id, gap_limit) VALUES (?1, ?2)
+             ON CONFLICT (wallet_id) DO UPDATE SET gap_limit = excluded.gap_limit",
+            params![self.wallet_id, gap_limit],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use elements::OutPoint;
+
+    use super::SqlitePersister;
+    use crate::{
+        ElementsNetwork, FsPersister, Persister, Update, UtxoLock, UtxoLocks, Wollet,
+        WolletDescriptor,
+    };
+
+    fn updates() -> (Update, Update) {
+        let update1 = Update::deserialize(&lwk_test_util::update_test_vector_bytes()).unwrap();
+        let mut update2 = update1.clone();
+        update2.timestamps.push((22, 55));
+        (update1, update2)
+    }
+
+    #[test]
+    fn test_sqlite_persister() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let desc = lwk_test_util::wollet_descriptor_string().parse().unwrap();
+        let n = ElementsNetwork::LiquidTestnet;
+        let (update1, update2) = updates();
+
+        let persister = SqlitePersister::new(&tempdir, n, &desc).unwrap();
+        assert!(persister.is_empty().unwrap());
+        assert_eq!(persister.get(0).unwrap(), None);
+        persister.push(update1.clone()).unwrap();
+        persister.push(update2.clone()).unwrap();
+
+        let persister = SqlitePersister::new(&tempdir, n, &desc).unwrap();
+        assert_eq!(persister.len().unwrap(), 2);
+        assert_eq!(persister.get(0).unwrap().unwrap(), update1);
+        assert_eq!(persister.get(1).unwrap().unwrap(), update2);
+        assert!(persister.get(2).unwrap().is_none());
+
+        // Another wallet in the same database doesn't see the updates
+        let desc2 = lwk_test_util::wollet_descriptor_string2().parse().unwrap();
+        let other = SqlitePersister::new(&tempdir, n, &desc2).unwrap();
+        assert!(other.get(0).unwrap().is_none());
+
+        // Locks and gap limit
+        assert_eq!(persister.get_locks().unwrap(), UtxoLocks::default());
+        assert_eq!(persister.get_gap_limit().unwrap(), None);
+        let mut locks = UtxoLocks::default();
+        locks.0.insert(OutPoint::null(), UtxoLock::Frozen);
+        persister.set_locks(&locks).unwrap();
+        persister.set_gap_limit(100).unwrap();
+        let persister = SqlitePersister::new(&tempdir, n, &desc).unwrap();
+        assert_eq!(persister.get_locks().unwrap(), locks);
+        assert_eq!(persister.get_gap_limit().unwrap(), Some(100));
+        assert_eq!(persister.len().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_sqlite_persister_only_tip() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let desc = lwk_test_util::wollet_descriptor_string().parse().unwrap();
+        let n = ElementsNetwork::LiquidTestnet;
+        let (update, _) = updates();
+        let mut tip1 = update.clone();
+        tip1.new_txs = Default::default();
+        tip1.txid_height_new.clear();
+        tip1.txid_height_delete.clear();
+        tip1.scripts.clear();
+        tip1.reorg = None;
+        tip1.scripts_status.clear();
+        assert!(tip1.only_tip());
+        let mut tip2 = tip1.clone();
+        tip2.timestamps.push((22, 55));
+
+        let persister = SqlitePersister::new(&tempdir, n, &desc).unwrap();
+        persister.push(update.clone()).unwrap();
+        persister.push(tip1).unwrap();
+        persister.push(tip2.clone()).unwrap();
+        assert_eq!(persister.len().unwrap(), 2);
+        assert_eq!(persister.get(1).unwrap().unwrap(), tip2);
+    }
+
+    #[test]
+    fn test_sqlite_persister_encrypted() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let desc = lwk_test_util::wollet_descriptor_string().parse().unwrap();
+        let n = ElementsNetwork::LiquidTestnet;
+        let (update, _) = updates();
+        let persister = SqlitePersister::new(&tempdir, n, &desc).unwrap();
+        persister.push(update.clone()).unwrap();
+
+        let conn = persister.conn.lock().unwrap();
+        let data: Vec<u8> = conn
+            .query_row("SELECT data FROM updates", [], |row| row.get(0))
+            .unwrap();
+        assert_ne!(data, update.serialize().unwrap());
+        assert_eq!(Update::deserialize_decrypted(&data, &desc).unwrap(), update);
+    }
+
+    #[test]
+    fn test_sqlite_persister_migration() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let desc = lwk_test_util::wollet_descriptor_string().parse().unwrap();
+        let n = ElementsNetwork::LiquidTestnet;
+        let (update1, update2) = updates();
+
+        // Nothing to migrate
+        let persister = SqlitePersister::new(&tempdir, n, &desc).unwrap();
+        assert_eq!(persister.migrate_from_fs(&tempdir).unwrap(), 0);
+
+        let fs = FsPersister::new(&tempdir, n, &desc).unwrap();
+        fs.push(update1.clone()).unwrap();
+        fs.push(update2.clone()).unwrap();
+        let mut locks = UtxoLocks::default();
+        locks.0.insert(OutPoint::null(), UtxoLock::UntilHeight(10));
+        fs.set_locks(&locks).unwrap();
+        fs.set_gap_limit(50).unwrap();
+
+        assert_eq!(persister.migrate_from_fs(&tempdir).unwrap(), 2);
+        assert_eq!(persister.get(0).unwrap().unwrap(), update1);
+        assert_eq!(persister.get(1).unwrap().unwrap(), update2);
+        assert_eq!(persister.get_locks().unwrap(), locks);
+        assert_eq!(persister.get_gap_limit().unwrap(), Some(50));
+
+        // Migrating again doesn't duplicate the updates
+        assert_eq!(persister.migrate_from_fs(&tempdir).unwrap(), 0);
+        assert_eq!(persister.len().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_wollet_with_sqlite_persist() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let desc: WolletDescriptor = lwk_test_util::wollet_descriptor_string().parse().unwrap();
+        let n = ElementsNetwork::LiquidTestnet;
+        let (update, _) = updates();
+
+        let mut wollet = Wollet::with_fs_persist(n, desc.clone(), &tempdir).unwrap();
+        wollet.apply_update(update).unwrap();
+        let status = wollet.status();
+        drop(wollet);
+
+        let wollet = Wollet::with_sqlite_persist(n, desc.clone(), &tempdir).unwrap();
+        assert_eq!(wollet.status(), status);
+        drop(wollet);
+
+        // The data is read from the database
+        let fs_dir = crate::persister::fs_persister_dir(&tempdir, n, &desc);
+        std::fs::remove_dir_all(fs_dir).unwrap();
+        let wollet = Wollet::with_sqlite_persist(n, desc, &tempdir).unwrap();
+        assert_eq!(wollet.status(), status);
+    }
+}
@@ -1,5 +1,6 @@
+use crate::clients::ScriptStatusHash;
 use crate::descriptor::Chain;
-use crate::elements::{BlockHash, OutPoint, Script, Transaction, TxOutSecrets, Txid};
+use crate::elements::{BlockHash, BlockHeader, OutPoint, Script, Transaction, TxOutSecrets, Txid};
 use crate::hashes::Hash;
 use crate::Error;
 use elements::bitcoin::bip32::ChildNumber;
@@ -8,7 +9,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicU32, Ordering};
 
-pub const BATCH_SIZE: u32 = 20;
+/// Default number of consecutive unused addresses after which the scan stops, see
+/// [`crate::Wollet::set_gap_limit()`]
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
 pub type Height = u32;
 pub type Timestamp = u32;
 
@@ -19,7 +22,7 @@ pub struct RawCache {
     /// contains all my tx and all prevouts
     pub all_txs: HashMap<Txid, Transaction>,
 
-    /// contains all my script up to an empty batch of BATCHSIZE
+    /// contains all my script up to an empty batch of gap limit size
     pub paths: HashMap<Script, (Chain, ChildNumber)>,
 
     /// inverse of `paths`
@@ -28,15 +31,32 @@ pub struct RawCache {
     /// contains only my wallet txs with the relative heights (None if unconfirmed)
     pub heights: HashMap<Txid, Option<Height>>,
 
+    /// unconfirmed wallet txs removed from `heights` because another wallet tx spending some of
+    /// the same inputs was found, with the txid of the latter
+    pub evicted: HashMap<Txid, Txid>,
+
     /// unblinded values
     pub unblinded: HashMap<OutPoint, TxOutSecrets>,
 
     /// height and hash of tip of the blockchain
     pub tip: (Height, BlockHash),
 
+    /// header of the tip of the blockchain, used to create updates not moving the tip such as the
+    /// ones of [`crate::Wollet::apply_transaction()`]
+    #[serde(skip)]
+    pub tip_header: Option<BlockHeader>,
+
     /// Contains the time of blocks at the given height. There are only heights containinig wallet txs
     pub timestamps: HashMap<Height, Timestamp>,
 
+    /// Contains the hash of blocks at the given height, used to detect reorgs. There are the same
+    /// heights of `timestamps`
+    pub block_hashes: HashMap<Height, BlockHash>,
+
+    /// status of the wallet scripts as defined by the electrum protocol, used to download the
+    /// history only of the changed scripts
+    pub script_status: HashMap<Script, ScriptStatusHash>,
+
     /// last unused index for external addresses for current descriptor
     pub last_unused_external: AtomicU32,
 
@@ -51,11 +71,15 @@ impl Default for RawCache {
             paths: HashMap::default(),
             scripts: HashMap::default(),
             heights: HashMap::default(),
+            evicted: HashMap::default(),
             unblinded: HashMap::default(),
             tip: (0, BlockHash::all_zeros()),
+            tip_header: None,
             last_unused_internal: 0.into(),
             last_unused_external: 0.into(),
             timestamps: HashMap::default(),
+            block_hashes: HashMap::default(),
+            script_status: HashMap::default(),
         }
     }
 }
@@ -78,6 +102,13 @@ impl std::hash::Hash for RawCache {
         vec.sort();
         vec.hash(state);
 
+        // Skipped when empty so that the hash of caches without evicted txs doesn't change
+        if !self.evicted.is_empty() {
+            let mut vec: Vec<_> = self.evicted.iter().collect();
+            vec.sort();
+            vec.hash(state);
+        }
+
         let mut vec: Vec<_> = self.unblinded.iter().collect();
         vec.sort_by_key(|kv| kv.0);
         vec.hash(state);
@@ -88,6 +119,20 @@ impl std::hash::Hash for RawCache {
         vec.sort();
         vec.hash(state);
 
+        // Skipped when empty so that the hash of caches without block hashes doesn't change
+        if !self.block_hashes.is_empty() {
+            let mut vec: Vec<_> = self.block_hashes.iter().collect();
+            vec.sort();
+            vec.hash(state);
+        }
+
+        // Skipped when empty so that the hash of caches without script status doesn't change
+        if !self.script_status.is_empty() {
+            let mut vec: Vec<_> = self.script_status.iter().collect();
+            vec.sort();
+            vec.hash(state);
+        }
+
         self.last_unused_external
             .load(Ordering::Relaxed)
             .hash(state);
@@ -113,6 +158,7 @@ impl Store {
     pub fn get_script_batch(
         &self,
         batch: u32,
+        batch_size: u32,
         descriptor: &Descriptor<DescriptorPublicKey>, // non confidential (we need only script_pubkey), non multipath (we need to be able to derive with index)
     ) -> Result<ScriptBatch, Error> {
         let mut result = ScriptBatch {
@@ -120,8 +166,8 @@ impl Store {
             ..Default::default()
         };
 
-        let start = batch * BATCH_SIZE;
-        let end = start + BATCH_SIZE;
+        let start = batch * batch_size;
+        let end = start + batch_size;
         let ext_int: Chain = descriptor.try_into().unwrap_or(Chain::External);
         for j in start..end {
             let child = ChildNumber::from_normal_idx(j)?;
@@ -139,20 +185,57 @@ impl Store {
         Ok(result)
     }
 
+    /// The outpoints spent by the wallet transactions, transactions no longer in `heights`, such as
+    /// the evicted ones, don't spend their inputs
     pub fn spent(&self) -> Result<HashSet<OutPoint>, Error> {
         Ok(self
             .cache
             .all_txs
-            .values()
-            .flat_map(|tx| tx.input.iter())
+            .iter()
+            .filter(|(txid, _)| self.cache.heights.contains_key(*txid))
+            .flat_map(|(_, tx)| tx.input.iter())
             .map(|i| i.previous_output)
             .collect())
     }
+
+    /// Prune the proofs of all the cached transactions, see [`prune_tx()`]
+    pub fn prune(&mut self) {
+        let unblinded = &self.cache.unblinded;
+        for (txid, tx) in self.cache.all_txs.iter_mut() {
+            prune_tx(*txid, tx, |outpoint| unblinded.contains_key(outpoint));
+        }
+    }
+}
+
+/// Remove the surjection proofs of the outputs of `tx` and the rangeproofs of the outputs not
+/// unblinded by the wallet
+///
+/// What remains is enough to compute balances and fees and to spend the wallet outputs, whose
+/// rangeproofs are needed by the signers.
+pub(crate) fn prune_tx(txid: Txid, tx: &mut Transaction, unblinded: impl Fn(&OutPoint) -> bool) {
+    for (vout, output) in tx.output.iter_mut().enumerate() {
+        output.witness.surjection_proof = None;
+        if !unblinded(&OutPoint::new(txid, vout as u32)) {
+            output.witness.rangeproof = None;
+        }
+    }
+}
+
+/// Whether some proofs of the confidential outputs of `tx` are missing, for instance because
+/// pruned with [`prune_tx()`]
+pub(crate) fn is_pruned_tx(tx: &Transaction) -> bool {
+    tx.output.iter().any(|o| {
+        (o.asset.is_confidential() && o.witness.surjection_proof.is_none())
+            || (o.value.is_confidential() && o.witness.rangeproof.is_none())
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{store::Store, WolletDescriptor};
+    use crate::{
+        store::{Store, DEFAULT_GAP_LIMIT},
+        WolletDescriptor,
+    };
     use elements::Txid;
     use elements_miniscript::ConfidentialDescriptor;
     use std::{
@@ -182,10 +265,20 @@ mod tests {
         let store = Store::default();
 
         let x = store
-            .get_script_batch(0, &desc.as_ref().descriptor)
+            .get_script_batch(0, DEFAULT_GAP_LIMIT, &desc.as_ref().descriptor)
             .unwrap();
         assert_eq!(format!("{:?}", x.value[0]), "(Script(OP_0 OP_PUSHBYTES_20 d11ef9e68385138627b09d52d6fe12662d049224), (External, Normal { index: 0 }))");
         assert_ne!(x.value[0], x.value[1]);
+        assert_eq!(x.value.len(), DEFAULT_GAP_LIMIT as usize);
+
+        let x = store
+            .get_script_batch(1, 50, &desc.as_ref().descriptor)
+            .unwrap();
+        assert_eq!(x.value.len(), 50);
+        assert_eq!(
+            format!("{:?}", x.value[0].1),
+            "(External, Normal { index: 50 })"
+        );
     }
 
     #[test]
@@ -5,15 +5,17 @@ use elements::{
     issuance::ContractHash,
     pset::{Output, PartiallySignedTransaction},
     secp256k1_zkp::ZERO_TWEAK,
-    Address, AssetId, Script, Transaction,
+    Address, AssetId, OutPoint, Script, Transaction, TxOutSecrets, Txid,
 };
 use rand::thread_rng;
 
 use crate::{
+    coin_selection::{CONFIDENTIAL_OUTPUT_WEIGHT, INPUT_BASE_WEIGHT},
     hashes::Hash,
     model::{IssuanceDetails, Recipient},
     pset_create::{validate_address, IssuanceRequest},
-    Contract, ElementsNetwork, Error, UnvalidatedRecipient, Wollet, EC,
+    BlockchainBackend, CoinSelection, Contract, ElementsNetwork, Error, FeePolicy, FeeRate,
+    SpendUnconfirmed, UnvalidatedRecipient, WalletTxOut, Wollet, EC,
 };
 
 pub fn extract_issuances(tx: &Transaction) -> Vec<IssuanceDetails> {
@@ -65,9 +67,19 @@ pub struct TxBuilder {
     network: ElementsNetwork,
     recipients: Vec<Recipient>,
     fee_rate: f32,
+    fee_policy: FeePolicy,
     issuance_request: IssuanceRequest,
     drain_lbtc: bool,
     drain_to: Option<Address>,
+    coin_selection: CoinSelection,
+    utxos_must_spend: Vec<OutPoint>,
+    utxos_excluded: HashSet<OutPoint>,
+    manually_selected_only: bool,
+    min_confirmations: u32,
+    spend_unconfirmed: SpendUnconfirmed,
+    lock_blocks: u32,
+    replaced_txid: Option<Txid>,
+    cpfp_parent: Option<Txid>,
 }
 
 impl TxBuilder {
@@ -76,10 +88,20 @@ impl TxBuilder {
         TxBuilder {
             network,
             recipients: vec![],
-            fee_rate: 100.0,
+            fee_rate: FeeRate::MIN_RELAY.to_sat_kvb(),
+            fee_policy: network.fee_policy(),
             issuance_request: IssuanceRequest::None,
             drain_lbtc: false,
             drain_to: None,
+            coin_selection: CoinSelection::default(),
+            utxos_must_spend: vec![],
+            utxos_excluded: HashSet::new(),
+            manually_selected_only: false,
+            min_confirmations: 0,
+            spend_unconfirmed: SpendUnconfirmed::default(),
+            lock_blocks: 0,
+            replaced_txid: None,
+            cpfp_parent: None,
         }
     }
 
@@ -150,6 +172,117 @@ impl TxBuilder {
         self
     }
 
+    /// Set the fee rate estimated by `client` for the transaction to confirm within
+    /// `target_blocks` blocks, see [`BlockchainBackend::estimate_fee()`]
+    pub fn fee_target<B: BlockchainBackend>(
+        mut self,
+        target_blocks: usize,
+        client: &B,
+    ) -> Result<Self, Error> {
+        self.fee_rate = client.estimate_fee(target_blocks)?.to_sat_kvb();
+        Ok(self)
+    }
+
+    /// Set how the transaction size is measured to compute the fee, default
+    /// [`ElementsNetwork::fee_policy()`]
+    pub fn fee_policy(mut self, fee_policy: FeePolicy) -> Self {
+        self.fee_policy = fee_policy;
+        self
+    }
+
+    /// Set the strategy used to select the UTXOs to spend, default [`CoinSelection::BranchAndBound`]
+    pub fn coin_selection(mut self, coin_selection: CoinSelection) -> Self {
+        self.coin_selection = coin_selection;
+        self
+    }
+
+    /// Spend the given wallet UTXO, in addition to the ones chosen by the coin selection
+    ///
+    /// If the UTXO was previously excluded with [`TxBuilder::exclude_utxo()`], it's not excluded
+    /// anymore.
+    pub fn add_utxo(mut self, outpoint: OutPoint) -> Self {
+        self.utxos_excluded.remove(&outpoint);
+        if !self.utxos_must_spend.contains(&outpoint) {
+            self.utxos_must_spend.push(outpoint);
+        }
+        self
+    }
+
+    /// Spend exactly the given wallet UTXOs, see [`TxBuilder::manually_selected_only()`]
+    pub fn add_utxos_only(mut self, outpoints: &[OutPoint]) -> Self {
+        for outpoint in outpoints {
+            self = self.add_utxo(*outpoint);
+        }
+        self.manually_selected_only()
+    }
+
+    /// Never spend the given wallet UTXO
+    ///
+    /// If the UTXO was previously added with [`TxBuilder::add_utxo()`], it's not added anymore.
+    pub fn exclude_utxo(mut self, outpoint: OutPoint) -> Self {
+        self.utxos_must_spend.retain(|o| o != &outpoint);
+        self.utxos_excluded.insert(outpoint);
+        self
+    }
+
+    /// Spend only the UTXOs added with [`TxBuilder::add_utxo()`], the coin selection will not
+    /// add other inputs and the transaction fails with [`Error::InsufficientFunds`] if they are
+    /// not enough.
+    ///
+    /// Every added UTXO is spent even if not needed, excess amounts are sent back as change.
+    pub fn manually_selected_only(mut self) -> Self {
+        self.manually_selected_only = true;
+        self
+    }
+
+    /// Select only UTXOs with at least `min_confirmations` confirmations, default 0
+    ///
+    /// If 0, unconfirmed UTXOs are selected according to [`TxBuilder::spend_unconfirmed()`],
+    /// otherwise they are never selected. UTXOs added with [`TxBuilder::add_utxo()`] are spent
+    /// regardless.
+    pub fn min_confirmations(mut self, min_confirmations: u32) -> Self {
+        self.min_confirmations = min_confirmations;
+        self
+    }
+
+    /// Set which unconfirmed UTXOs can be selected, default [`SpendUnconfirmed::All`]
+    ///
+    /// UTXOs added with [`TxBuilder::add_utxo()`] are spent regardless.
+    pub fn spend_unconfirmed(mut self, spend_unconfirmed: SpendUnconfirmed) -> Self {
+        self.spend_unconfirmed = spend_unconfirmed;
+        self
+    }
+
+    /// Lock the inputs of the transaction for the given number of blocks once
+    /// [`TxBuilder::finish()`] returns, default 0 which doesn't lock them.
+    ///
+    /// Locked UTXOs are not selected by following transactions, see [`Wollet::lock_utxos()`].
+    /// [`crate::DEFAULT_LOCK_BLOCKS`] is about a week.
+    pub fn lock_inputs_for(mut self, blocks: u32) -> Self {
+        self.lock_blocks = blocks;
+        self
+    }
+
+    /// Build a transaction replacing the given unconfirmed wallet transaction
+    ///
+    /// The inputs of the replaced transaction are spendable again, while its outputs are not.
+    /// Used by [`Wollet::bump_fee()`] which also sets the recipients and the inputs to spend.
+    pub(crate) fn replace_tx(mut self, txid: Txid) -> Self {
+        self.replaced_txid = Some(txid);
+        self
+    }
+
+    /// Accelerate the unconfirmed transaction `parent_txid` with a child paying for it
+    ///
+    /// The wallet outputs of the parent transaction are spent, except the ones excluded or
+    /// locked, and the fee is set so that the parent and the child transactions together pay
+    /// `fee_rate` (sat/kvb). Other inputs are added if the parent outputs are not enough.
+    pub fn cpfp(mut self, parent_txid: Txid, fee_rate: f32) -> Self {
+        self.cpfp_parent = Some(parent_txid);
+        self.fee_rate = fee_rate;
+        self
+    }
+
     /// Issue an asset
     ///
     /// There will be `asset_sats` units of this asset that will be received by
@@ -256,8 +389,117 @@ impl TxBuilder {
             .into_iter()
             .partition(|a| a.asset == policy_asset);
 
+        // When replacing a transaction its inputs are spendable again and its outputs are not
+        let replaced = match self.replaced_txid {
+            Some(txid) => Some(
+                wollet
+                    .transaction(&txid)?
+                    .ok_or_else(|| Error::MissingTransaction)?,
+            ),
+            None => None,
+        };
+        // Its descendants are replaced too, their outputs are not spendable either
+        let descendants = match replaced.as_ref() {
+            Some(replaced) => wollet.pending_descendants(&replaced.txid)?,
+            None => vec![],
+        };
+        let mut wallet_utxos = wollet.utxos()?;
+        let current_utxos: HashSet<_> = wallet_utxos.iter().map(|u| u.outpoint).collect();
+        if let Some(replaced) = replaced.as_ref() {
+            let txids: HashSet<_> = descendants
+                .iter()
+                .map(|tx| tx.txid)
+                .chain([replaced.txid])
+                .collect();
+            wallet_utxos.retain(|u| !txids.contains(&u.outpoint.txid));
+            wallet_utxos.extend(replaced.inputs.iter().flatten().cloned());
+        }
+
+        // UTXOs that must be spent and UTXOs available to the coin selection
+        // Locks on the inputs of a replaced transaction are stale, ignore them
+        let locked: HashSet<_> = wollet
+            .locked_utxos()?
+            .into_iter()
+            .map(|(outpoint, _)| outpoint)
+            .filter(|outpoint| current_utxos.contains(outpoint))
+            .collect();
+        let mut must_spend = self.utxos_must_spend.clone();
+
+        // With CPFP the parent outputs are spent and the fee accounts for the parent
+        let cpfp_parent = match self.cpfp_parent {
+            Some(txid) => {
+                let parent = wollet
+                    .transaction(&txid)?
+                    .ok_or_else(|| Error::MissingTransaction)?;
+                if parent.height.is_some() {
+                    return Err(Error::TxAlreadyConfirmed(txid));
+                }
+                let outpoints: Vec<_> = wallet_utxos
+                    .iter()
+                    .map(|u| u.outpoint)
+                    .filter(|o| {
+                        o.txid == txid
+                            && !self.utxos_excluded.contains(o)
+                            && !locked.contains(o)
+                            && !must_spend.contains(o)
+                    })
+                    .collect();
+                must_spend.extend(outpoints);
+                if !must_spend.iter().any(|o| o.txid == txid) {
+                    return Err(Error::CpfpNoSpendableOutput(txid));
+                }
+                Some((self.fee_policy.weight(&parent.tx), parent.fee))
+            }
+            None => None,
+        };
+
+        let mut utxos_must_spend = vec![];
+        for outpoint in must_spend.iter() {
+            let utxo = wallet_utxos
+                .iter()
+                .find(|u| &u.outpoint == outpoint)
+                .ok_or_else(|| Error::MissingWalletUtxo(*outpoint))?;
+            if locked.contains(outpoint) {
+                return Err(Error::LockedUtxo(*outpoint));
+            }
+            utxos_must_spend.push(utxo.clone());
+        }
+        // Every coin selection path, including (re)issuance and drain, picks from these
+        let tip_height = wollet.tip().height();
+        let trusted = match self.spend_unconfirmed {
+            SpendUnconfirmed::OwnOnly => wollet.trusted_pending_txids()?,
+            _ => HashSet::new(),
+        };
+        let confirmed_enough = |u: &WalletTxOut| match u.height {
+            Some(height) => tip_height.saturating_sub(height) + 1 >= self.min_confirmations,
+            None if self.min_confirmations > 0 => false,
+            None => match self.spend_unconfirmed {
+                SpendUnconfirmed::All => true,
+                SpendUnconfirmed::OwnOnly => trusted.contains(&u.outpoint.txid),
+                SpendUnconfirmed::None => false,
+            },
+        };
+        let utxos_available: Vec<_> = if self.manually_selected_only {
+            vec![]
+        } else {
+            wallet_utxos
+                .into_iter()
+                .filter(|u| {
+                    !self.utxos_excluded.contains(&u.outpoint)
+                        && !must_spend.contains(&u.outpoint)
+                        && !locked.contains(&u.outpoint)
+                        && confirmed_enough(u)
+                })
+                .collect()
+        };
+
         // Assets inputs and outputs
-        let assets: HashSet<_> = addressees_asset.iter().map(|a| a.asset).collect();
+        let assets: HashSet<_> = addressees_asset
+            .iter()
+            .map(|a| a.asset)
+            .chain(utxos_must_spend.iter().map(|u| u.unblinded.asset))
+            .filter(|a| a != &policy_asset)
+            .collect();
         for asset in assets {
             let mut satoshi_out = 0;
             let mut satoshi_in = 0;
@@ -265,24 +507,25 @@ impl TxBuilder {
                 wollet.add_output(&mut pset, addressee)?;
                 satoshi_out += addressee.satoshi;
             }
-            for utxo in wollet.asset_utxos(&asset)? {
+            for utxo in asset_utxos(&utxos_must_spend, &asset) {
                 wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
                 satoshi_in += utxo.unblinded.value;
-                if satoshi_in >= satoshi_out {
-                    if satoshi_in > satoshi_out {
-                        let satoshi_change = satoshi_in - satoshi_out;
-                        let addressee = wollet.addressee_change(
-                            satoshi_change,
-                            asset,
-                            &mut last_unused_internal,
-                        )?;
-                        wollet.add_output(&mut pset, &addressee)?;
-                    }
-                    break;
-                }
             }
-            if satoshi_in < satoshi_out {
-                return Err(Error::InsufficientFunds);
+            let utxos = self.coin_selection.select(
+                asset_utxos(&utxos_available, &asset),
+                satoshi_out.saturating_sub(satoshi_in),
+                0,
+                0,
+            )?;
+            for utxo in utxos {
+                wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
+                satoshi_in += utxo.unblinded.value;
+            }
+            if satoshi_in > satoshi_out {
+                let satoshi_change = satoshi_in - satoshi_out;
+                let addressee =
+                    wollet.addressee_change(satoshi_change, asset, &mut last_unused_internal)?;
+                wollet.add_output(&mut pset, &addressee)?;
             }
         }
 
@@ -295,8 +538,26 @@ impl TxBuilder {
             satoshi_out += addressee.satoshi;
         }
 
-        // FIXME: For implementation simplicity now we always add all L-BTC inputs
-        for utxo in wollet.asset_utxos(&wollet.policy_asset())? {
+        for utxo in asset_utxos(&utxos_must_spend, &policy_asset) {
+            wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
+            satoshi_in += utxo.unblinded.value;
+        }
+        let mut lbtc_utxos = asset_utxos(&utxos_available, &policy_asset);
+        if self.drain_lbtc {
+            for utxo in lbtc_utxos.drain(..) {
+                wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
+                satoshi_in += utxo.unblinded.value;
+            }
+        } else if pset.inputs().is_empty()
+            && matches!(self.issuance_request, IssuanceRequest::Issuance(..))
+        {
+            // The issuance needs an input, pick a L-BTC one according to the strategy,
+            // it will be considered when selecting the L-BTC to pay the fee.
+            let utxo = self
+                .coin_selection
+                .select(lbtc_utxos.clone(), 1, 0, 0)?
+                .remove(0);
+            lbtc_utxos.retain(|u| u.outpoint != utxo.outpoint);
             wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
             satoshi_in += utxo.unblinded.value;
         }
@@ -311,7 +572,8 @@ impl TxBuilder {
                 address_token,
                 contract,
             ) => {
-                // At least a L-BTC input for the fee was added.
+                // At least an input was added, either manually, for an asset or for the fee.
+                // None of them has an issuance yet, so we can use the first one.
                 let idx = 0;
                 let (asset, token) =
                     wollet.set_issuance(&mut pset, idx, satoshi_asset, satoshi_token, contract)?;
@@ -355,7 +617,7 @@ impl TxBuilder {
                         Some((idx, u)) => (*idx, u.asset_bf),
                         None => {
                             // Add an input sending the token,
-                            let utxos_token = wollet.asset_utxos(&token)?;
+                            let utxos_token = asset_utxos(&utxos_available, &token);
                             let utxo_token = utxos_token
                                 .first()
                                 .ok_or_else(|| Error::InsufficientFunds)?;
@@ -400,47 +662,123 @@ impl TxBuilder {
             }
         }
 
-        // Add a temporary fee, and always add a change output,
-        // then we'll tweak those values to match the given fee rate.
-        let temp_fee = 1000;
-        if satoshi_in < (satoshi_out + temp_fee) {
-            return Err(Error::InsufficientFunds);
-        }
-        let satoshi_change = satoshi_in - satoshi_out - temp_fee;
-        let addressee = if let Some(address) = self.drain_to {
-            Recipient::from_address(satoshi_change, &address, wollet.policy_asset())
-        } else {
-            wollet.addressee_change(
-                satoshi_change,
-                wollet.policy_asset(),
-                &mut last_unused_internal,
-            )?
+        // Select the L-BTC inputs paying for the L-BTC outputs and the fee.
+        // The fee depends on the number of inputs and on the presence of the change output,
+        // so we iterate until the selected inputs cover the fee computed on the blinded tx.
+        let input_cost = match lbtc_utxos.first() {
+            Some(utxo) => {
+                let desc = wollet.definite_descriptor(&utxo.script_pubkey)?;
+                fee_for_weight(
+                    INPUT_BASE_WEIGHT + desc.max_weight_to_satisfy()?,
+                    self.fee_rate,
+                )
+            }
+            None => 0,
         };
-        wollet.add_output(&mut pset, &addressee)?;
-        let fee_output =
-            Output::new_explicit(Script::default(), temp_fee, wollet.policy_asset(), None);
-        pset.add_output(fee_output);
+        let mut cost_of_change = fee_for_weight(CONFIDENTIAL_OUTPUT_WEIGHT, self.fee_rate);
+        let mut fee_estimate = 0;
+        let (selected, satoshi_change, fee, weight) = loop {
+            let target = (satoshi_out + fee_estimate).saturating_sub(satoshi_in);
+            // Without a change we must still have a blinded output to blind the transaction
+            let changeless_allowed = !self.drain_lbtc
+                && self.drain_to.is_none()
+                && pset.outputs().iter().any(|o| o.blinding_key.is_some());
+            let selected = self.coin_selection.select(
+                lbtc_utxos.clone(),
+                target,
+                input_cost,
+                if changeless_allowed {
+                    cost_of_change
+                } else {
+                    0
+                },
+            )?;
 
-        let weight = {
-            let mut rng = thread_rng();
             let mut temp_pset = pset.clone();
-            temp_pset.blind_last(&mut rng, &EC, &inp_txout_sec)?;
-            inp_weight + temp_pset.extract_tx()?.weight()
+            let mut temp_inp_txout_sec = inp_txout_sec.clone();
+            let mut temp_inp_weight = inp_weight;
+            let mut temp_satoshi_in = satoshi_in;
+            for utxo in selected.iter() {
+                wollet.add_input(
+                    &mut temp_pset,
+                    &mut temp_inp_txout_sec,
+                    &mut temp_inp_weight,
+                    utxo,
+                )?;
+                temp_satoshi_in += utxo.unblinded.value;
+            }
+
+            // Amounts of the temporary change and fee outputs don't affect the weight
+            let mut temp_last_unused_internal = last_unused_internal;
+            let change =
+                wollet.addressee_change(1, policy_asset, &mut temp_last_unused_internal)?;
+            let fee_output = Output::new_explicit(Script::default(), 1, policy_asset, None);
+            let changeless = if changeless_allowed {
+                let mut temp_pset = temp_pset.clone();
+                temp_pset.add_output(fee_output.clone());
+                let weight = temp_inp_weight
+                    + blinded_weight(temp_pset, &temp_inp_txout_sec, self.fee_policy)?;
+                Some((child_fee(weight, self.fee_rate, cpfp_parent), weight))
+            } else {
+                None
+            };
+            wollet.add_output(&mut temp_pset, &change)?;
+            temp_pset.add_output(fee_output);
+            let weight =
+                temp_inp_weight + blinded_weight(temp_pset, &temp_inp_txout_sec, self.fee_policy)?;
+            let fee_change = child_fee(weight, self.fee_rate, cpfp_parent);
+
+            if let Some((fee_changeless, weight_changeless)) = changeless {
+                cost_of_change = fee_change.saturating_sub(fee_changeless);
+                if temp_satoshi_in >= satoshi_out + fee_changeless
+                    && temp_satoshi_in <= satoshi_out + fee_change
+                {
+                    // The excess is not enough to pay for a change, give it to fees
+                    let fee = temp_satoshi_in - satoshi_out;
+                    break (selected, None, fee, weight_changeless);
+                }
+            }
+            if temp_satoshi_in > satoshi_out + fee_change {
+                let satoshi_change = temp_satoshi_in - satoshi_out - fee_change;
+                break (selected, Some(satoshi_change), fee_change, weight);
+            }
+            if self.drain_lbtc {
+                return Err(Error::InsufficientFunds);
+            }
+            // Ensure progress, selection fails when there are no more funds
+            fee_estimate = changeless
+                .map(|(fee, _)| fee)
+                .unwrap_or(fee_change)
+                .max(fee_estimate + 1);
         };
 
-        let vsize = (weight + 4 - 1) / 4;
-        let fee = (vsize as f32 * self.fee_rate / 1000.0).ceil() as u64;
-        if satoshi_in < (satoshi_out + temp_fee) {
-            return Err(Error::InsufficientFunds);
+        if let Some(replaced) = replaced.as_ref() {
+            // The replacement must pay the fees of all the transactions it evicts (BIP125 rule 3)
+            // and for its own relay (BIP125 rule 4)
+            let original = replaced.fee + descendants.iter().map(|tx| tx.fee).sum::<u64>();
+            let min = original + fee_for_weight(weight, FeeRate::MIN_RELAY.to_sat_kvb());
+            if fee < min {
+                return Err(Error::BumpFeeTooLow {
+                    original,
+                    min,
+                    new: fee,
+                });
+            }
+        }
+
+        for utxo in selected {
+            wollet.add_input(&mut pset, &mut inp_txout_sec, &mut inp_weight, &utxo)?;
+        }
+        if let Some(satoshi_change) = satoshi_change {
+            let addressee = if let Some(address) = self.drain_to {
+                Recipient::from_address(satoshi_change, &address, policy_asset)
+            } else {
+                wollet.addressee_change(satoshi_change, policy_asset, &mut last_unused_internal)?
+            };
+            wollet.add_output(&mut pset, &addressee)?;
         }
-        let satoshi_change = satoshi_in - satoshi_out - fee;
-        // Replace change and fee outputs
-        let n_outputs = pset.n_outputs();
-        let outputs = pset.outputs_mut();
-        let change_output = &mut outputs[n_outputs - 2]; // index check: we always have the lbtc change and the fee output at least
-        change_output.amount = Some(satoshi_change);
-        let fee_output = &mut outputs[n_outputs - 1];
-        fee_output.amount = Some(fee);
+        let fee_output = Output::new_explicit(Script::default(), fee, policy_asset, None);
+        pset.add_output(fee_output);
 
         // TODO inputs/outputs(except fee) randomization, not trivial because of blinder_index on inputs
 
@@ -451,10 +789,63 @@ impl TxBuilder {
         // Add details to the pset from our descriptor, like bip32derivation and keyorigin
         wollet.add_details(&mut pset)?;
 
+        // Reserve the inputs so that following transactions don't spend them,
+        // the inputs of a replaced transaction are already spent by it
+        if self.lock_blocks > 0 {
+            let outpoints: Vec<_> = pset
+                .inputs()
+                .iter()
+                .map(|i| OutPoint::new(i.previous_txid, i.previous_output_index))
+                .filter(|outpoint| current_utxos.contains(outpoint))
+                .collect();
+            wollet.lock_utxos(&outpoints, self.lock_blocks)?;
+        }
+
         Ok(pset)
     }
 }
 
+/// UTXOs of the given asset
+fn asset_utxos(utxos: &[WalletTxOut], asset: &AssetId) -> Vec<WalletTxOut> {
+    utxos
+        .iter()
+        .filter(|u| &u.unblinded.asset == asset)
+        .cloned()
+        .collect()
+}
+
+/// Fee for the given weight at the given fee rate (sat/kvb)
+fn fee_for_weight(weight: usize, fee_rate: f32) -> u64 {
+    let vsize = (weight + 4 - 1) / 4;
+    (vsize as f32 * fee_rate / 1000.0).ceil() as u64
+}
+
+/// Fee for a transaction of the given weight at the given fee rate (sat/kvb)
+///
+/// If `parent` has the weight and the fee of an unconfirmed parent, the fee is increased so that
+/// the package of the parent and the child pays the fee rate.
+fn child_fee(weight: usize, fee_rate: f32, parent: Option<(usize, u64)>) -> u64 {
+    let fee = fee_for_weight(weight, fee_rate);
+    match parent {
+        Some((parent_weight, parent_fee)) => fee_for_weight(weight + parent_weight, fee_rate)
+            .saturating_sub(parent_fee)
+            .max(fee),
+        None => fee,
+    }
+}
+
+/// Weight of the transaction once blinded according to the fee policy, not including the weight
+/// to satisfy the inputs
+fn blinded_weight(
+    mut pset: PartiallySignedTransaction,
+    inp_txout_sec: &HashMap<usize, TxOutSecrets>,
+    fee_policy: FeePolicy,
+) -> Result<usize, Error> {
+    let mut rng = thread_rng();
+    pset.blind_last(&mut rng, &EC, inp_txout_sec)?;
+    Ok(fee_policy.weight(&pset.extract_tx()?))
+}
+
 /// A transaction builder.
 #[derive(Debug)]
 pub struct WolletTxBuilder<'a> {
@@ -543,6 +934,106 @@ impl<'a> WolletTxBuilder<'a> {
         }
     }
 
+    /// Wrapper of [`TxBuilder::fee_target()`]
+    pub fn fee_target<B: BlockchainBackend>(
+        self,
+        target_blocks: usize,
+        client: &B,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            wollet: self.wollet,
+            inner: self.inner.fee_target(target_blocks, client)?,
+        })
+    }
+
+    /// Wrapper of [`TxBuilder::fee_policy()`]
+    pub fn fee_policy(self, fee_policy: FeePolicy) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.fee_policy(fee_policy),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::coin_selection()`]
+    pub fn coin_selection(self, coin_selection: CoinSelection) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.coin_selection(coin_selection),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::add_utxo()`]
+    pub fn add_utxo(self, outpoint: OutPoint) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.add_utxo(outpoint),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::add_utxos_only()`]
+    pub fn add_utxos_only(self, outpoints: &[OutPoint]) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.add_utxos_only(outpoints),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::exclude_utxo()`]
+    pub fn exclude_utxo(self, outpoint: OutPoint) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.exclude_utxo(outpoint),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::manually_selected_only()`]
+    pub fn manually_selected_only(self) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.manually_selected_only(),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::min_confirmations()`]
+    pub fn min_confirmations(self, min_confirmations: u32) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.min_confirmations(min_confirmations),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::spend_unconfirmed()`]
+    pub fn spend_unconfirmed(self, spend_unconfirmed: SpendUnconfirmed) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.spend_unconfirmed(spend_unconfirmed),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::lock_inputs_for()`]
+    pub fn lock_inputs_for(self, blocks: u32) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.lock_inputs_for(blocks),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::replace_tx()`]
+    pub(crate) fn replace_tx(self, txid: Txid) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.replace_tx(txid),
+        }
+    }
+
+    /// Wrapper of [`TxBuilder::cpfp()`]
+    pub fn cpfp(self, parent_txid: Txid, fee_rate: f32) -> Self {
+        Self {
+            wollet: self.wollet,
+            inner: self.inner.cpfp(parent_txid, fee_rate),
+        }
+    }
+
     /// Wrapper of [`TxBuilder::issue_asset()`]
     pub fn issue_asset(
         self,
@@ -1,8 +1,9 @@
+use crate::clients::{try_unblind, ScriptStatusHash};
 use crate::descriptor::Chain;
-use crate::elements::{OutPoint, Script, Transaction, TxOutSecrets, Txid};
+use crate::elements::{BlockHash, OutPoint, Script, Transaction, TxOutSecrets, Txid};
 use crate::error::Error;
-use crate::store::{Height, Timestamp};
-use crate::{Wollet, WolletDescriptor};
+use crate::store::{prune_tx, Height, Timestamp};
+use crate::{Wollet, WolletDescriptor, WolletEvent};
 use aes_gcm_siv::aead::generic_array::GenericArray;
 use aes_gcm_siv::aead::AeadMutInPlace;
 use base64::prelude::*;
@@ -26,6 +27,17 @@ impl DownloadTxResult {
     }
 }
 
+/// A chain reorganization detected while scanning, the blocks known by the wallet from
+/// `height` onwards are not part of the current chain anymore.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Reorg {
+    /// The lowest height whose block was replaced
+    pub height: Height,
+
+    /// The number of blocks replaced, from `height` to the previous tip of the wallet
+    pub depth: u32,
+}
+
 /// Passing a wallet to [`crate::BlockchainBackend::full_scan()`] returns this structure which
 /// contains the delta of information to be applied to the wallet to reach the latest status.
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -36,6 +48,19 @@ pub struct Update {
     pub timestamps: Vec<(Height, Timestamp)>,
     pub scripts: HashMap<Script, (Chain, ChildNumber)>, // TODO should be Vec<(Script,(Chain,ChildNumber))>
     pub tip: BlockHeader,
+
+    /// Block hashes at the heights of `timestamps`
+    pub block_hashes: Vec<(Height, BlockHash)>,
+
+    /// If some, confirmations and timestamps at or above the reorg height are rolled back before
+    /// applying the rest of the update
+    pub reorg: Option<Reorg>,
+
+    /// The new status of the scripts whose history changed, present only if the backend
+    /// supports them, see [`crate::BlockchainBackend::get_scripts_status()`]
+    ///
+    /// `None` if the history of the script is now empty, the stored status is removed.
+    pub scripts_status: Vec<(Script, Option<ScriptStatusHash>)>,
 }
 
 impl Update {
@@ -44,31 +69,117 @@ impl Update {
             && self.txid_height_new.is_empty()
             && self.txid_height_delete.is_empty()
             && self.scripts.is_empty()
+            && self.reorg.is_none()
+            && self.scripts_status.is_empty()
     }
+
+    /// Merge `other`, an update following this one, so that applying the result is equivalent
+    /// to applying this update and then `other`
+    ///
+    /// Useful to persisters to compact the updates of a wallet in a single snapshot. Unconfirmed
+    /// transactions added by this update and removed by `other` are dropped, instead of being kept
+    /// as evicted by the wallet.
+    pub fn merge(&mut self, other: Update) {
+        let Update {
+            new_txs,
+            txid_height_new,
+            txid_height_delete,
+            timestamps,
+            scripts,
+            tip,
+            block_hashes,
+            reorg,
+            scripts_status,
+        } = other;
+
+        if let Some(reorg) = reorg {
+            for (_, height) in self.txid_height_new.iter_mut() {
+                if matches!(height, Some(h) if *h >= reorg.height) {
+                    *height = None;
+                }
+            }
+            self.timestamps.retain(|(h, _)| *h < reorg.height);
+            self.block_hashes.retain(|(h, _)| *h < reorg.height);
+            self.reorg = Some(match self.reorg {
+                Some(prev) => {
+                    let height = prev.height.min(reorg.height);
+                    let end = (prev.height + prev.depth).max(reorg.height + reorg.depth);
+                    Reorg {
+                        height,
+                        depth: end - height,
+                    }
+                }
+                None => reorg,
+            });
+        }
+
+        let txids: HashSet<Txid> = new_txs.txs.iter().map(|(txid, _)| *txid).collect();
+        self.new_txs.txs.retain(|(txid, _)| !txids.contains(txid));
+        self.new_txs.txs.extend(new_txs.txs);
+        let outpoints: HashSet<OutPoint> = new_txs.unblinds.iter().map(|(o, _)| *o).collect();
+        self.new_txs
+            .unblinds
+            .retain(|(o, _)| !outpoints.contains(o));
+        self.new_txs.unblinds.extend(new_txs.unblinds);
+
+        let txids: HashSet<Txid> = txid_height_new
+            .iter()
+            .map(|(txid, _)| *txid)
+            .chain(txid_height_delete.iter().cloned())
+            .collect();
+        self.txid_height_new
+            .retain(|(txid, _)| !txids.contains(txid));
+        self.txid_height_new.extend(txid_height_new);
+        for txid in txid_height_delete {
+            if !self.txid_height_delete.contains(&txid) {
+                self.txid_height_delete.push(txid);
+            }
+        }
+
+        let heights: HashSet<Height> = timestamps.iter().map(|(h, _)| *h).collect();
+        self.timestamps.retain(|(h, _)| !heights.contains(h));
+        self.timestamps.extend(timestamps);
+        let heights: HashSet<Height> = block_hashes.iter().map(|(h, _)| *h).collect();
+        self.block_hashes.retain(|(h, _)| !heights.contains(h));
+        self.block_hashes.extend(block_hashes);
+
+        self.scripts.extend(scripts);
+        let scripts: HashSet<Script> = scripts_status.iter().map(|(s, _)| s.clone()).collect();
+        self.scripts_status.retain(|(s, _)| !scripts.contains(s));
+        self.scripts_status.extend(scripts_status);
+
+        self.tip = tip;
+    }
+
     pub fn serialize(&self) -> Result<Vec<u8>, elements::encode::Error> {
         let mut vec = vec![];
         self.consensus_encode(&mut vec)?;
         Ok(vec)
     }
+
     pub fn deserialize(bytes: &[u8]) -> Result<Update, elements::encode::Error> {
         Update::consensus_decode(bytes)
     }
 
-    pub fn serialize_encrypted(&self, desc: &WolletDescriptor) -> Result<Vec<u8>, Error> {
-        let mut plaintext = self.serialize()?;
-
-        let mut nonce_bytes = [0u8; 12];
-        thread_rng().fill(&mut nonce_bytes);
-        let nonce = GenericArray::from_slice(&nonce_bytes);
-
-        desc.cipher().encrypt_in_place(nonce, b"", &mut plaintext)?;
-        let ciphertext = plaintext;
-
-        let mut result = Vec::with_capacity(ciphertext.len() + 12);
-        result.extend(nonce.as_slice());
-        result.extend(&ciphertext);
+    /// Deserialize an update serialized with any supported version, see [`UPDATE_VERSION`]
+    ///
+    /// Unlike [`Update::deserialize()`], updates serialized by later versions are reported with
+    /// [`Error::UnsupportedUpdateVersion`].
+    pub fn deserialize_versioned(bytes: &[u8]) -> Result<Update, Error> {
+        if bytes.len() > UPDATE_MAGIC_BYTES.len() && bytes[..4] == UPDATE_MAGIC_BYTES {
+            let version = bytes[4];
+            if version > UPDATE_VERSION {
+                return Err(Error::UnsupportedUpdateVersion {
+                    version,
+                    latest: UPDATE_VERSION,
+                });
+            }
+        }
+        Ok(Update::consensus_decode(bytes)?)
+    }
 
-        Ok(result)
+    pub fn serialize_encrypted(&self, desc: &WolletDescriptor) -> Result<Vec<u8>, Error> {
+        encrypt(self.serialize()?, desc)
     }
 
     pub fn serialize_encrypted_base64(&self, desc: &WolletDescriptor) -> Result<String, Error> {
@@ -77,16 +188,7 @@ impl Update {
     }
 
     pub fn deserialize_decrypted(bytes: &[u8], desc: &WolletDescriptor) -> Result<Update, Error> {
-        let nonce_bytes = &bytes[..12];
-        let mut ciphertext = bytes[12..].to_vec();
-
-        let nonce = GenericArray::from_slice(nonce_bytes);
-
-        desc.cipher()
-            .decrypt_in_place(nonce, b"", &mut ciphertext)?;
-        let plaintext = ciphertext;
-
-        Ok(Update::deserialize(&plaintext)?)
+        Update::deserialize_versioned(&decrypt(bytes, desc)?)
     }
 
     pub fn deserialize_decrypted_base64(
@@ -100,6 +202,36 @@ impl Update {
     }
 }
 
+/// Encrypt `plaintext` with a key derived from the given descriptor, prepending the random nonce
+pub(crate) fn encrypt(mut plaintext: Vec<u8>, desc: &WolletDescriptor) -> Result<Vec<u8>, Error> {
+    let mut nonce_bytes = [0u8; 12];
+    thread_rng().fill(&mut nonce_bytes);
+    let nonce = GenericArray::from_slice(&nonce_bytes);
+
+    desc.cipher().encrypt_in_place(nonce, b"", &mut plaintext)?;
+    let ciphertext = plaintext;
+
+    let mut result = Vec::with_capacity(ciphertext.len() + 12);
+    result.extend(nonce.as_slice());
+    result.extend(&ciphertext);
+
+    Ok(result)
+}
+
+/// Inverse of [`encrypt`]
+pub(crate) fn decrypt(bytes: &[u8], desc: &WolletDescriptor) -> Result<Vec<u8>, Error> {
+    let nonce_bytes = &bytes[..12];
+    let mut ciphertext = bytes[12..].to_vec();
+
+    let nonce = GenericArray::from_slice(nonce_bytes);
+
+    desc.cipher()
+        .decrypt_in_place(nonce, b"", &mut ciphertext)?;
+    let plaintext = ciphertext;
+
+    Ok(plaintext)
+}
+
 impl Wollet {
     pub fn apply_update(&mut self, update: Update) -> Result<(), Error> {
         self.apply_update_inner(update, true)
@@ -109,9 +241,93 @@ impl Wollet {
         self.apply_update_inner(update, false)
     }
 
-    fn apply_update_inner(&mut self, update: Update, do_persist: bool) -> Result<(), Error> {
+    /// Apply a transaction created by this wallet, or sending to it, as unconfirmed
+    ///
+    /// The wallet outputs are unblinded and the spent ones removed from the UTXOs without waiting
+    /// the next scan, so that a transaction just broadcast is considered when building the next
+    /// one. Pending wallet transactions spending some of the same inputs, for instance the one
+    /// replaced by [`Wollet::bump_fee()`], are evicted. The change is persisted as an [`Update`].
+    pub fn apply_transaction(&mut self, tx: Transaction) -> Result<(), Error> {
+        let txid = tx.txid();
+        if self.store.cache.heights.contains_key(&txid) {
+            return Ok(());
+        }
+        let tip = self
+            .store
+            .cache
+            .tip_header
+            .clone()
+            .ok_or(Error::NeverScanned)?;
+
+        let mut unblinds = vec![];
+        for (vout, output) in tx.output.iter().enumerate() {
+            if !self.store.cache.paths.contains_key(&output.script_pubkey) {
+                continue;
+            }
+            let outpoint = OutPoint::new(txid, vout as u32);
+            match try_unblind(output.clone(), &self.wollet_descriptor()) {
+                Ok(unblinded) => unblinds.push((outpoint, unblinded)),
+                Err(_) => tracing::info!("{} cannot unblind, ignoring", outpoint),
+            }
+        }
+        let spends_wallet_funds = tx
+            .input
+            .iter()
+            .any(|i| self.store.cache.unblinded.contains_key(&i.previous_output));
+        if unblinds.is_empty() && !spends_wallet_funds {
+            return Err(Error::TxNotRelevant(txid));
+        }
+
+        // Double spent pending transactions are removed, they're kept as replaced by this one
+        let spent: HashSet<OutPoint> = tx.input.iter().map(|i| i.previous_output).collect();
+        let txid_height_delete: Vec<Txid> = self
+            .store
+            .cache
+            .heights
+            .iter()
+            .filter(|(_, height)| height.is_none())
+            .filter(|(other, _)| {
+                self.store.cache.all_txs.get(*other).map_or(false, |other| {
+                    other
+                        .input
+                        .iter()
+                        .any(|i| spent.contains(&i.previous_output))
+                })
+            })
+            .map(|(other, _)| *other)
+            .collect();
+
+        let update = Update {
+            new_txs: DownloadTxResult {
+                txs: vec![(txid, tx)],
+                unblinds,
+            },
+            txid_height_new: vec![(txid, None)],
+            txid_height_delete,
+            timestamps: vec![],
+            scripts: HashMap::new(),
+            tip,
+            block_hashes: vec![],
+            reorg: None,
+            scripts_status: vec![],
+        };
+        self.apply_update(update)
+    }
+
+    fn apply_update_inner(&mut self, mut update: Update, do_persist: bool) -> Result<(), Error> {
         // TODO should accept &Update
 
+        if self.pruned_cache {
+            let unblinded = &self.store.cache.unblinded;
+            let new_unblinded: HashSet<OutPoint> =
+                update.new_txs.unblinds.iter().map(|(o, _)| *o).collect();
+            for (txid, tx) in update.new_txs.txs.iter_mut() {
+                prune_tx(*txid, tx, |o| {
+                    new_unblinded.contains(o) || unblinded.contains_key(o)
+                });
+            }
+        }
+
         let store = &mut self.store;
         let Update {
             new_txs,
@@ -120,9 +336,12 @@ impl Wollet {
             timestamps,
             scripts,
             tip,
+            block_hashes,
+            reorg,
+            scripts_status,
         } = update.clone();
 
-        if tip.height + 1 < store.cache.tip.0 {
+        if tip.height + 1 < store.cache.tip.0 && reorg.is_none() {
             // Checking we are not applying an old update while giving enough space for a single block reorg
             return Err(Error::UpdateHeightTooOld {
                 update_tip_height: tip.height,
@@ -130,7 +349,21 @@ impl Wollet {
             });
         }
 
+        if let Some(reorg) = reorg {
+            // Blocks from the reorg height are replaced, the update contains the new heights of
+            // the transactions confirmed again
+            for height in store.cache.heights.values_mut() {
+                if matches!(height, Some(h) if *h >= reorg.height) {
+                    *height = None;
+                }
+            }
+            store.cache.timestamps.retain(|h, _| *h < reorg.height);
+            store.cache.block_hashes.retain(|h, _| *h < reorg.height);
+            self.events.push(WolletEvent::Reorg(reorg));
+        }
+
         store.cache.tip = (tip.height, tip.block_hash());
+        store.cache.tip_header = Some(tip);
         store.cache.unblinded.extend(new_txs.unblinds);
         let mut txids_unblinded: HashSet<Txid> =
             store.cache.unblinded.keys().map(|o| o.txid).collect();
@@ -148,12 +381,49 @@ impl Wollet {
             .filter(|(txid, _)| txids_unblinded.contains(txid))
             .cloned()
             .collect();
+        let deleted_unconfirmed: Vec<Txid> = txid_height_delete
+            .iter()
+            .filter(|txid| store.cache.heights.get(*txid) == Some(&None))
+            .cloned()
+            .collect();
         store
             .cache
             .heights
             .retain(|k, _| !txid_height_delete.contains(k));
         store.cache.heights.extend(txid_height.clone());
+
+        // Unconfirmed transactions disappeared because another wallet transaction spends some
+        // of the same inputs are kept as evicted
+        let spenders: HashMap<OutPoint, Txid> = store
+            .cache
+            .heights
+            .keys()
+            .filter_map(|txid| store.cache.all_txs.get(txid).map(|tx| (txid, tx)))
+            .flat_map(|(txid, tx)| tx.input.iter().map(|i| (i.previous_output, *txid)))
+            .collect();
+        for txid in deleted_unconfirmed {
+            let replacing = store.cache.all_txs.get(&txid).and_then(|tx| {
+                tx.input
+                    .iter()
+                    .find_map(|i| spenders.get(&i.previous_output))
+            });
+            if let Some(replacing) = replacing {
+                store.cache.evicted.insert(txid, *replacing);
+            }
+        }
+        let heights = &store.cache.heights;
+        store
+            .cache
+            .evicted
+            .retain(|txid, _| !heights.contains_key(txid));
         store.cache.timestamps.extend(timestamps);
+        store.cache.block_hashes.extend(block_hashes);
+        for (script, status) in scripts_status {
+            match status {
+                Some(status) => store.cache.script_status.insert(script, status),
+                None => store.cache.script_status.remove(&script),
+            };
+        }
         store
             .cache
             .scripts
@@ -307,6 +577,22 @@ impl Decodable for EncodableTxOutSecrets {
 }
 
 const UPDATE_MAGIC_BYTES: [u8; 4] = [0x89, 0x61, 0xb8, 0xc8];
+
+/// How a script without history, and so without status, is encoded in [`Update::scripts_status`],
+/// a sha256 can't be all zeros in practice
+const NO_SCRIPT_STATUS: ScriptStatusHash = [0u8; 32];
+
+/// The latest version of the serialization of [`Update`]
+///
+/// An update is serialized as the magic bytes, the version byte and the fields supported by that
+/// version, using the lowest version supporting its content so that older releases can read it:
+/// * 0: transactions, unblinded outputs, heights, timestamps, scripts and tip
+/// * 1: adds block hashes and reorg
+/// * 2: adds scripts status
+///
+/// Every previous version is decoded, later versions are rejected with
+/// [`Error::UnsupportedUpdateVersion`].
+pub const UPDATE_VERSION: u8 = 2;
 impl Encodable for Update {
     fn consensus_encode<W: std::io::Write>(
         &self,
@@ -314,8 +600,16 @@ impl Encodable for Update {
     ) -> Result<usize, elements::encode::Error> {
         let mut bytes_written = 0;
 
+        // Updates are encoded with the lowest version supporting their content
+        let version = if !self.scripts_status.is_empty() {
+            2u8
+        } else if !self.block_hashes.is_empty() || self.reorg.is_some() {
+            1u8
+        } else {
+            0u8
+        };
         bytes_written += UPDATE_MAGIC_BYTES.consensus_encode(&mut w)?; // Magic bytes
-        bytes_written += 0u8.consensus_encode(&mut w)?; // Version
+        bytes_written += version.consensus_encode(&mut w)?; // Version
 
         bytes_written += self.new_txs.consensus_encode(&mut w)?;
 
@@ -351,6 +645,35 @@ impl Encodable for Update {
 
         bytes_written += self.tip.consensus_encode(&mut w)?;
 
+        if version > 0 {
+            bytes_written +=
+                elements::VarInt(self.block_hashes.len() as u64).consensus_encode(&mut w)?;
+            for (height, block_hash) in self.block_hashes.iter() {
+                bytes_written += height.consensus_encode(&mut w)?;
+                bytes_written += block_hash.consensus_encode(&mut w)?;
+            }
+
+            match self.reorg {
+                None => bytes_written += 0u8.consensus_encode(&mut w)?,
+                Some(reorg) => {
+                    bytes_written += 1u8.consensus_encode(&mut w)?;
+                    bytes_written += reorg.height.consensus_encode(&mut w)?;
+                    bytes_written += reorg.depth.consensus_encode(&mut w)?;
+                }
+            }
+        }
+
+        if version > 1 {
+            bytes_written +=
+                elements::VarInt(self.scripts_status.len() as u64).consensus_encode(&mut w)?;
+            for (script, status) in self.scripts_status.iter() {
+                bytes_written += script.consensus_encode(&mut w)?;
+                bytes_written += status
+                    .unwrap_or(NO_SCRIPT_STATUS)
+                    .consensus_encode(&mut w)?;
+            }
+        }
+
         Ok(bytes_written)
     }
 }
@@ -363,7 +686,7 @@ impl Decodable for Update {
         }
 
         let version = u8::consensus_decode(&mut d)?;
-        if version != 0 {
+        if version > UPDATE_VERSION {
             return Err(elements::encode::Error::ParseFailed("Unsupported version"));
         }
 
@@ -421,6 +744,41 @@ impl Decodable for Update {
 
         let tip = BlockHeader::consensus_decode(&mut d)?;
 
+        let (block_hashes, reorg) = if version > 0 {
+            let len = elements::VarInt::consensus_decode(&mut d)?.0;
+            let mut block_hashes = Vec::with_capacity(len as usize);
+            for _ in 0..len {
+                let h = u32::consensus_decode(&mut d)?;
+                let block_hash = BlockHash::consensus_decode(&mut d)?;
+                block_hashes.push((h, block_hash));
+            }
+
+            let reorg = match u8::consensus_decode(&mut d)? {
+                0 => None,
+                1 => Some(Reorg {
+                    height: u32::consensus_decode(&mut d)?,
+                    depth: u32::consensus_decode(&mut d)?,
+                }),
+                _ => return Err(elements::encode::Error::ParseFailed("Invalid reorg")),
+            };
+            (block_hashes, reorg)
+        } else {
+            (vec![], None)
+        };
+
+        let scripts_status = if version > 1 {
+            let len = elements::VarInt::consensus_decode(&mut d)?.0;
+            let mut vec = Vec::with_capacity(len as usize);
+            for _ in 0..len {
+                let script = Script::consensus_decode(&mut d)?;
+                let status: ScriptStatusHash = Decodable::consensus_decode(&mut d)?;
+                vec.push((script, Some(status).filter(|s| *s != NO_SCRIPT_STATUS)));
+            }
+            vec
+        } else {
+            vec![]
+        };
+
         Ok(Self {
             new_txs,
             txid_height_new,
@@ -428,6 +786,9 @@ impl Decodable for Update {
             timestamps,
             scripts,
             tip,
+            block_hashes,
+            reorg,
+            scripts_status,
         })
     }
 }
@@ -439,10 +800,13 @@ mod test {
 
     use elements::{
         encode::{Decodable, Encodable},
-        Script,
+        hashes::Hash,
+        BlockHash, Script,
     };
 
-    use crate::{update::DownloadTxResult, Chain, Update, WolletDescriptor};
+    use crate::{
+        update::DownloadTxResult, Chain, Error, Reorg, Update, WolletDescriptor, UPDATE_VERSION,
+    };
 
     use super::EncodableTxOutSecrets;
 
@@ -469,12 +833,21 @@ mod test {
             timestamps: Default::default(),
             scripts: Default::default(),
             tip,
+            block_hashes: Default::default(),
+            reorg: None,
+            scripts_status: Default::default(),
         };
         assert!(update.only_tip());
         update
             .txid_height_delete
             .push(<elements::Txid as elements::hashes::Hash>::all_zeros());
         assert!(!update.only_tip());
+        update.txid_height_delete.clear();
+        update.reorg = Some(Reorg {
+            height: 1,
+            depth: 1,
+        });
+        assert!(!update.only_tip());
     }
 
     #[test]
@@ -521,6 +894,9 @@ mod test {
             timestamps: vec![(12, 44), (12, 44)],
             scripts,
             tip,
+            block_hashes: vec![],
+            reorg: None,
+            scripts_status: vec![],
         };
 
         let mut vec = vec![];
@@ -530,7 +906,170 @@ mod test {
         assert_eq!(vec.len(), len);
 
         let back = Update::consensus_decode(&vec[..]).unwrap();
-        assert_eq!(update, back)
+        assert_eq!(update, back);
+
+        // With block hashes and reorg the update is encoded with version 1
+        let mut update = update;
+        update.block_hashes = vec![(12, BlockHash::from_byte_array([0x11; 32]))];
+        update.reorg = Some(Reorg {
+            height: 12,
+            depth: 2,
+        });
+        let mut vec = vec![];
+        let len = update.consensus_encode(&mut vec).unwrap();
+        assert_eq!(vec[4], 1);
+        assert_eq!(vec, lwk_test_util::update_test_vector_v1_bytes());
+        assert_eq!(len, 2842 + 1 + 36 + 9);
+        let back = Update::consensus_decode(&vec[..]).unwrap();
+        assert_eq!(update, back);
+
+        // With scripts status the update is encoded with version 2
+        let mut update = update;
+        update.scripts_status = vec![(Script::new(), Some([7u8; 32]))];
+        let mut vec = vec![];
+        let len = update.consensus_encode(&mut vec).unwrap();
+        assert_eq!(vec[4], 2);
+        assert_eq!(vec, lwk_test_util::update_test_vector_v2_bytes());
+        assert_eq!(len, 2842 + 1 + 36 + 9 + 1 + 1 + 32);
+        let back = Update::consensus_decode(&vec[..]).unwrap();
+        assert_eq!(update, back);
+
+        // Scripts without history are encoded in the same version
+        update.scripts_status = vec![(Script::new(), None)];
+        let vec = update.serialize().unwrap();
+        assert_eq!(vec[4], 2);
+        let back = Update::deserialize(&vec).unwrap();
+        assert_eq!(back.scripts_status, vec![(Script::new(), None)]);
+    }
+
+    #[test]
+    fn test_update_versions() {
+        // Golden vectors of every supported version
+        let vectors = [
+            lwk_test_util::update_test_vector_bytes(),
+            lwk_test_util::update_test_vector_v1_bytes(),
+            lwk_test_util::update_test_vector_v2_bytes(),
+        ];
+        assert_eq!(vectors.len(), UPDATE_VERSION as usize + 1);
+        for (version, bytes) in vectors.iter().enumerate() {
+            assert_eq!(bytes[4], version as u8);
+            let update = Update::deserialize(bytes).unwrap();
+            assert_eq!(&update.serialize().unwrap(), bytes);
+            assert_eq!(update.block_hashes.is_empty(), version < 1);
+            assert_eq!(update.scripts_status.is_empty(), version < 2);
+        }
+
+        // Updates written by later versions are rejected with a clear error
+        let mut bytes = lwk_test_util::update_test_vector_v2_bytes();
+        bytes[4] = UPDATE_VERSION + 1;
+        assert!(Update::deserialize(&bytes).is_err());
+        let err = Update::deserialize_versioned(&bytes).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnsupportedUpdateVersion {
+                version: 3,
+                latest: 2
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Update version 3 is not supported, the latest supported is 2"
+        );
+
+        // Invalid magic bytes
+        bytes[0] = 0;
+        assert!(matches!(
+            Update::deserialize_versioned(&bytes),
+            Err(Error::ElementsEncode(_))
+        ));
+    }
+
+    #[test]
+    fn test_update_merge() {
+        let tip = lwk_test_util::liquid_block_1().header;
+        let txid = |n: u8| elements::Txid::from_byte_array([n; 32]);
+        let hash = |n: u8| BlockHash::from_byte_array([n; 32]);
+        let empty = Update {
+            new_txs: DownloadTxResult::default(),
+            txid_height_new: vec![],
+            txid_height_delete: vec![],
+            timestamps: vec![],
+            scripts: HashMap::new(),
+            tip,
+            block_hashes: vec![],
+            reorg: None,
+            scripts_status: vec![],
+        };
+        let mut first = empty.clone();
+        first.new_txs = download_tx_result_test_vector();
+        first.txid_height_new = vec![(txid(1), Some(10)), (txid(2), None), (txid(3), Some(12))];
+        first.txid_height_delete = vec![txid(4)];
+        first.timestamps = vec![(10, 100), (12, 120)];
+        first.block_hashes = vec![(10, hash(10)), (12, hash(12))];
+        first
+            .scripts
+            .insert(Script::default(), (Chain::External, 0u32.into()));
+        first.scripts_status = vec![(Script::default(), Some([1u8; 32]))];
+
+        let mut second = empty.clone();
+        second.new_txs = download_tx_result_test_vector();
+        second.txid_height_new = vec![(txid(2), Some(13))];
+        second.txid_height_delete = vec![txid(1), txid(4)];
+        second.timestamps = vec![(13, 130)];
+        second.block_hashes = vec![(13, hash(13))];
+        second.reorg = Some(Reorg {
+            height: 12,
+            depth: 1,
+        });
+        second.scripts_status = vec![(Script::default(), Some([2u8; 32]))];
+
+        let mut merged = first.clone();
+        merged.merge(second);
+        assert_eq!(merged.new_txs, download_tx_result_test_vector());
+        assert_eq!(
+            merged.txid_height_new,
+            vec![(txid(3), None), (txid(2), Some(13))]
+        );
+        assert_eq!(merged.txid_height_delete, vec![txid(4), txid(1)]);
+        assert_eq!(merged.timestamps, vec![(10, 100), (13, 130)]);
+        assert_eq!(merged.block_hashes, vec![(10, hash(10)), (13, hash(13))]);
+        assert_eq!(merged.scripts, first.scripts);
+        assert_eq!(
+            merged.scripts_status,
+            vec![(Script::default(), Some([2u8; 32]))]
+        );
+        assert_eq!(
+            merged.reorg,
+            Some(Reorg {
+                height: 12,
+                depth: 1
+            })
+        );
+
+        // Merging a tip only update keeps the content and moves the tip
+        let mut tip_only = empty.clone();
+        tip_only.tip = lwk_test_util::liquid_block_1().header;
+        tip_only.tip.height += 1;
+        let mut moved = first.clone();
+        moved.merge(tip_only.clone());
+        assert_eq!(moved.tip, tip_only.tip);
+        moved.tip = first.tip.clone();
+        assert_eq!(moved, first);
+
+        // Merged reorgs cover both
+        let mut older = empty.clone();
+        older.reorg = Some(Reorg {
+            height: 8,
+            depth: 2,
+        });
+        older.merge(merged);
+        assert_eq!(
+            older.reorg,
+            Some(Reorg {
+                height: 8,
+                depth: 5
+            })
+        );
     }
 
     #[test]
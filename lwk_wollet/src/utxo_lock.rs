@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::elements::OutPoint;
+use crate::update::{decrypt, encrypt};
+use crate::{Error, Wollet, WolletDescriptor};
+
+/// Default number of blocks the inputs of a transaction created with the [`crate::TxBuilder`]
+/// are locked for, about a week on Liquid.
+pub const DEFAULT_LOCK_BLOCKS: u32 = 10_080;
+
+/// A lock on a wallet UTXO, locked UTXOs are never selected by the [`crate::TxBuilder`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UtxoLock {
+    /// The UTXO is reserved by a transaction not yet broadcast, the lock is released when the
+    /// blockchain tip reaches the given height
+    UntilHeight(u32),
+
+    /// The UTXO is frozen by the user, the lock is released only explicitly
+    Frozen,
+}
+
+impl UtxoLock {
+    /// Whether the lock is still active with the blockchain tip at the given height
+    pub fn is_active(&self, tip_height: u32) -> bool {
+        match self {
+            UtxoLock::UntilHeight(height) => tip_height < *height,
+            UtxoLock::Frozen => true,
+        }
+    }
+}
+
+/// The locks on the UTXOs of a wallet
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UtxoLocks(pub(crate) BTreeMap<OutPoint, UtxoLock>);
+
+impl UtxoLocks {
+    /// The lock on the given UTXO, if active
+    pub fn get(&self, outpoint: &OutPoint, tip_height: u32) -> Option<UtxoLock> {
+        self.0
+            .get(outpoint)
+            .filter(|lock| lock.is_active(tip_height))
+            .cloned()
+    }
+
+    /// Iterate over all the locks, including the expired ones
+    pub fn iter(&self) -> impl Iterator<Item = (&OutPoint, &UtxoLock)> {
+        self.0.iter()
+    }
+
+    pub fn serialize_encrypted(&self, desc: &WolletDescriptor) -> Result<Vec<u8>, Error> {
+        let locks: Vec<_> = self.0.iter().collect();
+        encrypt(serde_json::to_vec(&locks)?, desc)
+    }
+
+    pub fn deserialize_decrypted(bytes: &[u8], desc: &WolletDescriptor) -> Result<Self, Error> {
+        let locks: Vec<(OutPoint, UtxoLock)> = serde_json::from_slice(&decrypt(bytes, desc)?)?;
+        Ok(Self(locks.into_iter().collect()))
+    }
+}
+
+impl Wollet {
+    /// Get the active lock on the given UTXO, if any
+    pub fn utxo_lock(&self, outpoint: &OutPoint) -> Result<Option<UtxoLock>, Error> {
+        let tip_height = self.tip().height();
+        Ok(self.locks()?.get(outpoint, tip_height))
+    }
+
+    /// Get the wallet UTXOs with an active lock
+    pub fn locked_utxos(&self) -> Result<Vec<(OutPoint, UtxoLock)>, Error> {
+        let tip_height = self.tip().height();
+        Ok(self
+            .locks()?
+            .iter()
+            .filter(|(_, lock)| lock.is_active(tip_height))
+            .map(|(outpoint, lock)| (*outpoint, *lock))
+            .collect())
+    }
+
+    /// Lock the given wallet UTXOs for the next `blocks` blocks
+    ///
+    /// Frozen UTXOs stay frozen, already locked UTXOs are locked until the later expiry.
+    pub fn lock_utxos(&self, outpoints: &[OutPoint], blocks: u32) -> Result<(), Error> {
+        let until = self.tip().height().saturating_add(blocks);
+        self.update_locks(outpoints, |current| match current {
+            Some(UtxoLock::Frozen) => Some(UtxoLock::Frozen),
+            Some(UtxoLock::UntilHeight(h)) => Some(UtxoLock::UntilHeight(h.max(until))),
+            None => Some(UtxoLock::UntilHeight(until)),
+        })
+    }
+
+    /// Freeze the given wallet UTXOs, they will not be spent until unlocked
+    pub fn freeze_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error> {
+        self.update_locks(outpoints, |_| Some(UtxoLock::Frozen))
+    }
+
+    /// Release the locks on the given wallet UTXOs, including frozen ones
+    pub fn unlock_utxos(&self, outpoints: &[OutPoint]) -> Result<(), Error> {
+        self.update_locks(outpoints, |_| None)
+    }
+
+    /// Apply `f` to the active locks of the given outpoints, which must be wallet UTXOs,
+    /// then persist the locks.
+    ///
+    /// Expired locks and locks of spent UTXOs are removed.
+    fn update_locks(
+        &self,
+        outpoints: &[OutPoint],
+        f: impl Fn(Option<UtxoLock>) -> Option<UtxoLock>,
+    ) -> Result<(), Error> {
+        let tip_height = self.tip().height();
+        let utxos = self.utxos()?;
+        for outpoint in outpoints {
+            if !utxos.iter().any(|u| &u.outpoint == outpoint) {
+                return Err(Error::MissingWalletUtxo(*outpoint));
+            }
+        }
+
+        let mut locks = self.locks()?;
+        for outpoint in outpoints {
+            match f(locks.get(outpoint, tip_height)) {
+                Some(lock) => locks.0.insert(*outpoint, lock),
+                None => locks.0.remove(outpoint),
+            };
+        }
+        locks.0.retain(|outpoint, lock| {
+            lock.is_active(tip_height) && utxos.iter().any(|u| &u.outpoint == outpoint)
+        });
+        self.persister.set_locks(&locks)?;
+        Ok(())
+    }
+
+    /// Replace all the locks, then persist them
+    pub(crate) fn replace_locks(&self, new_locks: UtxoLocks) -> Result<(), Error> {
+        let mut locks = self.locks()?;
+        self.persister.set_locks(&new_locks)?;
+        *locks = new_locks;
+        Ok(())
+    }
+
+    fn locks(&self) -> Result<std::sync::MutexGuard<'_, UtxoLocks>, Error> {
+        self.locks
+            .lock()
+            .map_err(|e| Error::Generic(format!("utxo locks poisoned: {e}")))
+    }
+}
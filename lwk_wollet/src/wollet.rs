@@ -6,12 +6,14 @@ use crate::elements::secp256k1_zkp::ZERO_TWEAK;
 use crate::elements::{AssetId, BlockHash, OutPoint, Script, Transaction, Txid};
 use crate::error::Error;
 use crate::hashes::Hash;
-use crate::model::{AddressResult, IssuanceDetails, WalletTx, WalletTxOut};
+use crate::model::{
+    AddressResult, Balance, IssuanceDetails, WalletTx, WalletTxOut, WalletTxStatus,
+};
 use crate::persister::PersistError;
-use crate::store::{Height, Store, Timestamp};
+use crate::store::{is_pruned_tx, Height, Store, Timestamp, DEFAULT_GAP_LIMIT};
 use crate::tx_builder::{extract_issuances, WolletTxBuilder};
 use crate::util::EC;
-use crate::{FsPersister, NoPersist, Persister, Update, WolletDescriptor};
+use crate::{FsPersister, NoPersist, Persister, Reorg, Update, UtxoLocks, WolletDescriptor};
 use elements::bitcoin::bip32::ChildNumber;
 use elements_miniscript::psbt::PsbtExt;
 use elements_miniscript::{psbt, ForEachKey};
@@ -24,14 +26,28 @@ use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::Hasher;
 use std::path::Path;
-use std::sync::{atomic, Arc};
+use std::sync::{atomic, Arc, Mutex};
+
+/// Number of blocks after which a coinbase output can be spent
+const COINBASE_MATURITY: u32 = 100;
 
 /// A watch-only wallet defined by a CT descriptor.
 pub struct Wollet {
     pub(crate) config: Config,
     pub(crate) store: Store,
     pub(crate) persister: Arc<dyn Persister + Send + Sync>,
+    pub(crate) locks: Mutex<UtxoLocks>,
+    pub(crate) events: Vec<WolletEvent>,
     descriptor: WolletDescriptor,
+    gap_limit: u32,
+    pub(crate) pruned_cache: bool,
+}
+
+/// An event happened while applying updates to the wallet, see [`Wollet::take_events()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WolletEvent {
+    /// A chain reorganization rolled back the confirmations of the wallet from the given height
+    Reorg(Reorg),
 }
 
 impl std::fmt::Debug for Wollet {
@@ -58,11 +74,17 @@ impl Wollet {
         let config = Config::new(network)?;
 
         let store = Store::default();
+        let locks = Mutex::new(persister.get_locks()?);
+        let gap_limit = persister.get_gap_limit()?.unwrap_or(DEFAULT_GAP_LIMIT);
         let mut wollet = Wollet {
             store,
             config,
             descriptor,
             persister,
+            locks,
+            events: vec![],
+            gap_limit,
+            pruned_cache: false,
         };
 
         for i in 0.. {
@@ -71,6 +93,8 @@ impl Wollet {
                 None => break,
             }
         }
+        // Events of persisted updates have already been reported
+        wollet.events.clear();
 
         Ok(wollet)
     }
@@ -88,6 +112,21 @@ impl Wollet {
         )
     }
 
+    /// Create a new wallet persisting in a SQLite database in `datadir`
+    ///
+    /// The data previously persisted in `datadir` by [`Wollet::with_fs_persist()`] is migrated
+    /// to the database the first time.
+    #[cfg(feature = "sqlite")]
+    pub fn with_sqlite_persist<P: AsRef<Path>>(
+        network: ElementsNetwork,
+        descriptor: WolletDescriptor,
+        datadir: P,
+    ) -> Result<Self, Error> {
+        let persister = crate::SqlitePersister::new(&datadir, network, &descriptor)?;
+        persister.migrate_from_fs(&datadir)?;
+        Self::new(network, persister, descriptor)
+    }
+
     /// Create a new wallet which not persist anything
     pub fn without_persist(
         network: ElementsNetwork,
@@ -96,6 +135,59 @@ impl Wollet {
         Self::new(network, Arc::new(NoPersist {}), descriptor)
     }
 
+    /// The number of consecutive unused scripts after which a scan stops, for both the external
+    /// and the internal chain
+    pub fn gap_limit(&self) -> u32 {
+        self.gap_limit
+    }
+
+    /// Set and persist the gap limit, by default [`DEFAULT_GAP_LIMIT`]
+    ///
+    /// Wallets whose addresses have been given out with large gaps, for instance by a merchant
+    /// backend, need a higher gap limit to find all their funds. To search once with a higher
+    /// gap limit use [`crate::BlockchainBackend::full_scan_with_gap_limit()`].
+    pub fn set_gap_limit(&mut self, gap_limit: u32) -> Result<(), Error> {
+        if gap_limit == 0 {
+            return Err(Error::InvalidGapLimit);
+        }
+        self.persister.set_gap_limit(gap_limit)?;
+        self.gap_limit = gap_limit;
+        Ok(())
+    }
+
+    /// Whether the proofs of the cached transactions are pruned, see [`Wollet::set_pruned_cache()`]
+    pub fn pruned_cache(&self) -> bool {
+        self.pruned_cache
+    }
+
+    /// Keep in the cache only the proofs of the transactions needed by the wallet, false by default
+    ///
+    /// Rangeproofs and surjection proofs are most of the size of a confidential transaction, once
+    /// the wallet outputs are unblinded only their rangeproofs are needed to spend them. When
+    /// enabled, the cached transactions are pruned and so are the ones of the following updates,
+    /// also when persisted. Balances, fees, [`crate::WalletTx`] inputs and outputs and created
+    /// transactions are not affected, but [`crate::WalletTx::tx`] lacks the pruned proofs: use
+    /// [`crate::BlockchainBackend::full_transaction()`] when the full transaction is needed.
+    ///
+    /// The setting is not persisted, updates persisted before enabling it are not pruned.
+    pub fn set_pruned_cache(&mut self, pruned: bool) {
+        if pruned {
+            self.store.prune();
+        }
+        self.pruned_cache = pruned;
+    }
+
+    /// Whether the cached transaction `txid` lacks some proofs, for instance because pruned, see
+    /// [`Wollet::set_pruned_cache()`]
+    pub fn is_transaction_pruned(&self, txid: &Txid) -> bool {
+        self.store
+            .cache
+            .all_txs
+            .get(txid)
+            .map(is_pruned_tx)
+            .unwrap_or(false)
+    }
+
     /// Get the network policy asset
     pub fn policy_asset(&self) -> AssetId {
         self.config.policy_asset()
@@ -121,6 +213,12 @@ impl Wollet {
         self.descriptor.clone()
     }
 
+    /// Return and clear the events happened since the last call, such as reorgs detected by the
+    /// applied updates
+    pub fn take_events(&mut self) -> Vec<WolletEvent> {
+        std::mem::take(&mut self.events)
+    }
+
     /// Get the blockchain tip
     pub fn tip(&self) -> Tip {
         let (height, hash) = self.store.cache.tip;
@@ -176,13 +274,21 @@ impl Wollet {
     }
 
     pub fn txos_inner(&self, unspent: bool) -> Result<Vec<WalletTxOut>, Error> {
-        let mut txos = vec![];
         let spent = if unspent {
             self.store.spent()?
         } else {
             HashSet::new()
         };
-        for (tx_id, height) in self.store.cache.heights.iter() {
+        self.txos_of(self.store.cache.heights.iter(), &spent)
+    }
+
+    fn txos_of<'a>(
+        &self,
+        txids: impl Iterator<Item = (&'a Txid, &'a Option<Height>)>,
+        spent: &HashSet<OutPoint>,
+    ) -> Result<Vec<WalletTxOut>, Error> {
+        let mut txos = vec![];
+        for (tx_id, height) in txids {
             let tx = self
                 .store
                 .cache
@@ -238,6 +344,67 @@ impl Wollet {
             .collect())
     }
 
+    /// The wallet outputs of the wallet transactions, evicted ones included
+    fn txos_with_evicted(&self) -> Result<HashMap<OutPoint, WalletTxOut>, Error> {
+        let mut txos = self.txos()?;
+        let evicted = self.store.cache.evicted.keys().map(|txid| (txid, &None));
+        txos.extend(
+            self.txos_of(evicted, &HashSet::new())?
+                .into_iter()
+                .map(|txo| (txo.outpoint, txo)),
+        );
+        Ok(txos)
+    }
+
+    /// The status of the wallet transactions, evicted ones included
+    fn txs_status(&self) -> HashMap<Txid, WalletTxStatus> {
+        let cache = &self.store.cache;
+        let mut spenders: HashMap<OutPoint, Vec<Txid>> = HashMap::new();
+        for txid in cache.heights.keys() {
+            if let Some(tx) = cache.all_txs.get(txid) {
+                for input in tx.input.iter() {
+                    spenders
+                        .entry(input.previous_output)
+                        .or_default()
+                        .push(*txid);
+                }
+            }
+        }
+        let is_confirmed = |txid: &Txid| matches!(cache.heights.get(txid), Some(Some(_)));
+
+        let mut status = HashMap::new();
+        for (txid, height) in cache.heights.iter() {
+            let s = if height.is_some() {
+                WalletTxStatus::Confirmed
+            } else {
+                let conflicting = cache
+                    .all_txs
+                    .get(txid)
+                    .into_iter()
+                    .flat_map(|tx| tx.input.iter())
+                    .filter_map(|i| spenders.get(&i.previous_output))
+                    .flatten()
+                    .filter(|other| *other != txid)
+                    // Prefer the confirmed conflicting transaction, if any
+                    .min_by_key(|other| (!is_confirmed(other), **other));
+                match conflicting {
+                    Some(other) => WalletTxStatus::Conflicted(*other),
+                    None => WalletTxStatus::Pending,
+                }
+            };
+            status.insert(*txid, s);
+        }
+        for (txid, by) in cache.evicted.iter() {
+            let s = if is_confirmed(by) {
+                WalletTxStatus::Conflicted(*by)
+            } else {
+                WalletTxStatus::Replaced(*by)
+            };
+            status.insert(*txid, s);
+        }
+        status
+    }
+
     pub(crate) fn balance_from_utxos(
         &self,
         utxos: &[WalletTxOut],
@@ -251,15 +418,91 @@ impl Wollet {
     }
 
     /// Get the wallet balance
+    ///
+    /// It includes unconfirmed and locked UTXOs, see [`Wollet::balance_details()`] to distinguish
+    /// them.
     pub fn balance(&self) -> Result<BTreeMap<AssetId, u64>, Error> {
         let utxos = self.utxos()?;
         self.balance_from_utxos(&utxos)
     }
 
+    /// Get the wallet balance split in confirmed, pending, immature and locked funds
+    pub fn balance_details(&self) -> Result<Balance, Error> {
+        let tip_height = self.tip().height();
+        let locked: HashSet<OutPoint> = self
+            .locked_utxos()?
+            .into_iter()
+            .map(|(outpoint, _)| outpoint)
+            .collect();
+        let trusted = self.trusted_pending_txids()?;
+
+        let mut balance = Balance::default();
+        for utxo in self.utxos()? {
+            let tx = self
+                .store
+                .cache
+                .all_txs
+                .get(&utxo.outpoint.txid)
+                .ok_or_else(|| Error::MissingTransaction)?;
+            let map = if locked.contains(&utxo.outpoint) {
+                &mut balance.locked
+            } else {
+                match utxo.height {
+                    Some(height)
+                        if tx.is_coinbase() && tip_height + 1 < height + COINBASE_MATURITY =>
+                    {
+                        &mut balance.immature
+                    }
+                    Some(_) => &mut balance.confirmed,
+                    None if trusted.contains(&utxo.outpoint.txid) => &mut balance.trusted_pending,
+                    None => &mut balance.untrusted_pending,
+                }
+            };
+            *map.entry(utxo.unblinded.asset).or_default() += utxo.unblinded.value;
+        }
+        balance.confirmed.entry(self.policy_asset()).or_insert(0);
+        Ok(balance)
+    }
+
+    /// The unconfirmed transactions spending only wallet UTXOs, whose outputs can't be double
+    /// spent by others
+    pub(crate) fn trusted_pending_txids(&self) -> Result<HashSet<Txid>, Error> {
+        let txos = self.txos()?;
+        let mut txids = HashSet::new();
+        for (txid, height) in self.store.cache.heights.iter() {
+            if height.is_some() {
+                continue;
+            }
+            let tx = self
+                .store
+                .cache
+                .all_txs
+                .get(txid)
+                .ok_or_else(|| Error::MissingTransaction)?;
+            if tx
+                .input
+                .iter()
+                .all(|i| txos.contains_key(&i.previous_output))
+            {
+                txids.insert(*txid);
+            }
+        }
+        Ok(txids)
+    }
+
     /// Get the wallet transactions with their heights (if confirmed)
+    ///
+    /// Unconfirmed transactions evicted by a replacing or conflicting wallet transaction are
+    /// included, see [`WalletTx::status`].
     pub fn transactions(&self) -> Result<Vec<WalletTx>, Error> {
         let mut txs = vec![];
-        let mut my_txids: Vec<(&Txid, &Option<u32>)> = self.store.cache.heights.iter().collect();
+        let mut my_txids: Vec<(&Txid, &Option<u32>)> = self
+            .store
+            .cache
+            .heights
+            .iter()
+            .chain(self.store.cache.evicted.keys().map(|txid| (txid, &None)))
+            .collect();
         my_txids.sort_by(|a, b| {
             let height_cmp =
                 b.1.unwrap_or(std::u32::MAX)
@@ -270,7 +513,8 @@ impl Wollet {
             }
         });
 
-        let txos = self.txos()?;
+        let txos = self.txos_with_evicted()?;
+        let status = self.txs_status();
         for (txid, height) in my_txids.iter() {
             let tx = self
                 .store
@@ -290,6 +534,10 @@ impl Wollet {
                 tx: tx.clone(),
                 txid: **txid,
                 height: **height,
+                status: status
+                    .get(*txid)
+                    .cloned()
+                    .unwrap_or(WalletTxStatus::Pending),
                 balance,
                 fee,
                 type_,
@@ -304,10 +552,18 @@ impl Wollet {
 
     /// Get a wallet transaction
     pub fn transaction(&self, txid: &Txid) -> Result<Option<WalletTx>, Error> {
-        let height = self.store.cache.heights.get(txid);
+        let height = match self.store.cache.evicted.contains_key(txid) {
+            true => Some(&None),
+            false => self.store.cache.heights.get(txid),
+        };
         let tx = self.store.cache.all_txs.get(txid);
         if let (Some(height), Some(tx)) = (height, tx) {
-            let txos = self.txos()?;
+            let txos = self.txos_with_evicted()?;
+            let status = self
+                .txs_status()
+                .get(txid)
+                .cloned()
+                .unwrap_or(WalletTxStatus::Pending);
 
             let balance = tx_balance(*txid, tx, &txos);
             let fee = tx_fee(tx);
@@ -321,6 +577,7 @@ impl Wollet {
                 tx: tx.clone(),
                 txid: *txid,
                 height: *height,
+                status,
                 balance,
                 fee,
                 type_,
@@ -337,6 +594,9 @@ impl Wollet {
     pub fn issuances(&self) -> Result<Vec<IssuanceDetails>, Error> {
         let mut r = vec![];
         for tx in self.transactions()? {
+            if self.store.cache.evicted.contains_key(&tx.txid) {
+                continue;
+            }
             r.extend(extract_issuances(&tx.tx));
         }
         Ok(r)
@@ -733,6 +993,67 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_apply_reorg_update() {
+        let bytes = lwk_test_util::update_test_vector_bytes();
+
+        let mut update = crate::Update::deserialize(&bytes[..]).unwrap();
+        update.txid_height_delete.clear();
+        update.tip.height = 12;
+        update.block_hashes = vec![(12, update.tip.block_hash())];
+        // Heights are kept only for the transactions with unblinded outputs
+        let txid = update.new_txs.unblinds[0].0.txid;
+        update.txid_height_new = vec![(txid, Some(12))];
+
+        let exp = "ct(slip77(9c8e4f05c7711a98c838be228bcb84924d4570ca53f35fa1c793e58841d47023),elwpkh([73c5da0a/84'/1'/0']tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/<0;1>/*))";
+        let mut wollet = new_wollet(exp);
+        wollet.apply_update(update.clone()).unwrap();
+        assert_eq!(wollet.store.cache.heights.get(&txid), Some(&Some(12)));
+        assert!(wollet.store.cache.timestamps.contains_key(&12));
+        assert!(wollet.take_events().is_empty());
+
+        // Block 12 is replaced, the transaction is not confirmed anymore
+        let reorg = crate::Reorg {
+            height: 12,
+            depth: 1,
+        };
+        let mut reorg_update = update;
+        reorg_update.new_txs = Default::default();
+        reorg_update.txid_height_new.clear();
+        reorg_update.timestamps.clear();
+        reorg_update.block_hashes.clear();
+        reorg_update.scripts.clear();
+        reorg_update.tip.height = 10; // lower than the previous tip, accepted because of the reorg
+        reorg_update.reorg = Some(reorg);
+        wollet.apply_update(reorg_update).unwrap();
+        assert_eq!(wollet.store.cache.heights.get(&txid), Some(&None));
+        assert!(!wollet.store.cache.timestamps.contains_key(&12));
+        assert!(!wollet.store.cache.block_hashes.contains_key(&12));
+        assert_eq!(wollet.take_events(), vec![WolletEvent::Reorg(reorg)]);
+        assert!(wollet.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_apply_scripts_status() {
+        let bytes = lwk_test_util::update_test_vector_bytes();
+
+        let mut update = crate::Update::deserialize(&bytes[..]).unwrap();
+        let script = Script::new();
+        update.scripts_status = vec![(script.clone(), Some([7u8; 32]))];
+        let exp = "ct(slip77(9c8e4f05c7711a98c838be228bcb84924d4570ca53f35fa1c793e58841d47023),elwpkh([73c5da0a/84'/1'/0']tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/<0;1>/*))";
+        let mut wollet = new_wollet(exp);
+        wollet.apply_update(update.clone()).unwrap();
+        assert_eq!(
+            wollet.store.cache.script_status.get(&script),
+            Some(&[7u8; 32])
+        );
+
+        // The history of the script is now empty, its status is removed
+        update.scripts_status = vec![(script.clone(), None)];
+        wollet.apply_update(update).unwrap();
+        assert_eq!(wollet.store.cache.script_status.get(&script), None);
+    }
+
     #[test]
     fn fixed_addresses_test() {
         let expected = [
@@ -803,4 +1124,33 @@ mod tests {
 
         assert_eq!(16997737043419915973, wollet.status());
     }
+
+    #[test]
+    fn test_pruned_cache() {
+        let bytes = lwk_test_util::update_test_vector_bytes();
+
+        let update = crate::Update::deserialize(&bytes[..]).unwrap();
+        let exp = "ct(slip77(9c8e4f05c7711a98c838be228bcb84924d4570ca53f35fa1c793e58841d47023),elwpkh([73c5da0a/84'/1'/0']tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/<0;1>/*))";
+        let mut wollet = new_wollet(exp);
+        wollet.set_pruned_cache(true);
+        wollet.apply_update(update.clone()).unwrap();
+
+        let mut full = new_wollet(exp);
+        full.apply_update(update).unwrap();
+
+        assert_eq!(wollet.status(), full.status());
+        assert_eq!(wollet.balance().unwrap(), full.balance().unwrap());
+        let outpoints = |w: &Wollet| -> Vec<OutPoint> {
+            w.utxos().unwrap().iter().map(|u| u.outpoint).collect()
+        };
+        assert_eq!(outpoints(&wollet), outpoints(&full));
+        for tx in full.transactions().unwrap() {
+            assert!(!full.is_transaction_pruned(&tx.txid));
+            assert!(wollet.is_transaction_pruned(&tx.txid));
+        }
+
+        // Pruning an existing cache gives the same result
+        full.set_pruned_cache(true);
+        assert_eq!(full.store.cache.all_txs, wollet.store.cache.all_txs);
+    }
 }
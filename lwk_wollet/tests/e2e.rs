@@ -6,7 +6,7 @@ use electrum_client::ScriptStatus;
 use elements::bitcoin::{bip32::DerivationPath, XKeyIdentifier};
 use elements::encode::deserialize;
 use elements::hex::FromHex;
-use elements::Transaction;
+use elements::{OutPoint, Transaction};
 use lwk_common::Signer;
 use lwk_containers::testcontainers::clients::Cli;
 use lwk_signer::*;
@@ -977,3 +977,1153 @@ fn drain() {
     assert!(wallet.balance(&asset) > 0);
     assert!(wallet.balance(&token) > 0);
 }
+
+#[test]
+fn coin_control() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let signers = [&AnySigner::Software(signer)];
+
+    let mut wallet = TestWollet::new(&server.electrs.electrum_url, &desc);
+    wallet.fund_btc(&server);
+    wallet.fund_btc(&server);
+    wallet.fund_btc(&server);
+    let utxos = wallet.wollet.utxos().unwrap();
+    assert_eq!(utxos.len(), 3);
+    let node_address = server.node_getnewaddress();
+    let inputs = |pset: &elements::pset::PartiallySignedTransaction| -> Vec<OutPoint> {
+        pset.inputs()
+            .iter()
+            .map(|i| OutPoint::new(i.previous_txid, i.previous_output_index))
+            .collect()
+    };
+
+    // Missing utxo
+    let err = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 1_000)
+        .unwrap()
+        .add_utxo(OutPoint::null())
+        .finish()
+        .unwrap_err();
+    assert!(matches!(err, Error::MissingWalletUtxo(_)));
+
+    // Added utxos are not enough
+    let err = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, utxos[0].unblinded.value)
+        .unwrap()
+        .add_utxos_only(&[utxos[0].outpoint])
+        .finish()
+        .unwrap_err();
+    assert!(matches!(err, Error::InsufficientFunds));
+
+    // Excluded utxos are not spent even when draining
+    let pset = wallet
+        .tx_builder()
+        .exclude_utxo(utxos[0].outpoint)
+        .drain_lbtc_wallet()
+        .drain_lbtc_to(node_address.clone())
+        .finish()
+        .unwrap();
+    assert_eq!(inputs(&pset), vec![utxos[1].outpoint, utxos[2].outpoint]);
+
+    // Spend exactly the given utxos, even if one would be enough
+    let mut pset = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 1_000)
+        .unwrap()
+        .add_utxos_only(&[utxos[0].outpoint, utxos[1].outpoint])
+        .finish()
+        .unwrap();
+    assert_eq!(inputs(&pset), vec![utxos[0].outpoint, utxos[1].outpoint]);
+    for signer in signers {
+        wallet.sign(signer, &mut pset);
+    }
+    wallet.send(&mut pset);
+    let utxos_after = wallet.wollet.utxos().unwrap();
+    assert!(utxos_after.iter().any(|u| u.outpoint == utxos[2].outpoint));
+    assert!(!utxos_after.iter().any(|u| u.outpoint == utxos[0].outpoint));
+}
+
+#[test]
+fn utxo_locks() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+
+    let mut wallet = TestWollet::new(&server.electrs.electrum_url, &desc);
+    wallet.fund_btc(&server);
+    wallet.fund_btc(&server);
+    let utxos = wallet.wollet.utxos().unwrap();
+    assert_eq!(utxos.len(), 2);
+    let node_address = server.node_getnewaddress();
+    let input = |pset: &elements::pset::PartiallySignedTransaction| -> OutPoint {
+        assert_eq!(pset.inputs().len(), 1);
+        let i = &pset.inputs()[0];
+        OutPoint::new(i.previous_txid, i.previous_output_index)
+    };
+
+    // Inputs are not locked by default
+    wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 1_000)
+        .unwrap()
+        .finish()
+        .unwrap();
+    assert!(wallet.wollet.locked_utxos().unwrap().is_empty());
+
+    // If requested inputs are locked once the PSET is created, a following PSET spends other UTXOs
+    let pset1 = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 1_000)
+        .unwrap()
+        .lock_inputs_for(DEFAULT_LOCK_BLOCKS)
+        .finish()
+        .unwrap();
+    let pset2 = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 1_000)
+        .unwrap()
+        .lock_inputs_for(DEFAULT_LOCK_BLOCKS)
+        .finish()
+        .unwrap();
+    assert_ne!(input(&pset1), input(&pset2));
+    assert_eq!(wallet.wollet.locked_utxos().unwrap().len(), 2);
+    let err = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 1_000)
+        .unwrap()
+        .finish()
+        .unwrap_err();
+    assert!(matches!(err, Error::InsufficientFunds));
+
+    // Frozen UTXOs can't be spent, not even explicitly
+    let outpoint = input(&pset1);
+    wallet.wollet.unlock_utxos(&[outpoint]).unwrap();
+    assert_eq!(wallet.wollet.utxo_lock(&outpoint).unwrap(), None);
+    wallet.wollet.freeze_utxos(&[outpoint]).unwrap();
+    assert_eq!(
+        wallet.wollet.utxo_lock(&outpoint).unwrap(),
+        Some(UtxoLock::Frozen)
+    );
+    let err = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 1_000)
+        .unwrap()
+        .add_utxo(outpoint)
+        .finish()
+        .unwrap_err();
+    assert!(matches!(err, Error::LockedUtxo(_)));
+
+    // Locks are persisted
+    let db_root_dir = wallet.db_root_dir();
+    let mut wallet = TestWollet::with_temp_dir(&server.electrs.electrum_url, &desc, db_root_dir);
+    assert_eq!(wallet.wollet.locked_utxos().unwrap().len(), 2);
+
+    // Locks expire
+    let outpoint = input(&pset2);
+    wallet.wollet.unlock_utxos(&[outpoint]).unwrap();
+    wallet.wollet.lock_utxos(&[outpoint], 1).unwrap();
+    assert!(wallet.wollet.utxo_lock(&outpoint).unwrap().is_some());
+    let height = wallet.tip().height();
+    server.generate(1);
+    wallet.wait_height(height + 1);
+    assert_eq!(wallet.wollet.utxo_lock(&outpoint).unwrap(), None);
+}
+
+#[test]
+fn bump_fee() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+
+    let mut wallet = TestWollet::new(&server.electrs.electrum_url, &desc);
+    wallet.fund_btc(&server);
+    let node_address = server.node_getnewaddress();
+
+    let mut pset = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .finish()
+        .unwrap();
+    assert!(pset
+        .inputs()
+        .iter()
+        .all(|i| i.sequence == Some(elements::Sequence::ENABLE_RBF_NO_LOCKTIME)));
+    let original = pset.clone();
+    wallet.sign(&signer, &mut pset);
+    let txid = wallet.send(&mut pset);
+    let fee = wallet.wollet.transaction(&txid).unwrap().unwrap().fee;
+
+    // The recipient output is confidential and can't be recovered without the original PSET
+    let err = wallet.wollet.bump_fee(&txid, 1_000.0).unwrap_err();
+    assert!(matches!(err, Error::BumpFeeMissingRecipient(_)));
+
+    // The new fee must be higher
+    let err = wallet
+        .wollet
+        .bump_fee_with(&txid, 100.0, Some(&original), &[])
+        .unwrap_err();
+    assert!(matches!(err, Error::BumpFeeTooLow { .. }));
+
+    // The increase must pay at least the minimum relay fee of the replacement
+    let err = wallet
+        .wollet
+        .bump_fee_with(&txid, 150.0, Some(&original), &[])
+        .unwrap_err();
+    assert!(matches!(err, Error::BumpFeeTooLow { .. }));
+
+    // The recipient can also be given explicitly
+    let recipient = Recipient::from_address(10_000, &node_address, wallet.policy_asset());
+    wallet
+        .wollet
+        .bump_fee_with(&txid, 1_000.0, None, &[recipient])
+        .unwrap();
+
+    let mut pset = wallet
+        .wollet
+        .bump_fee_with(&txid, 1_000.0, Some(&original), &[])
+        .unwrap();
+    wallet.sign(&signer, &mut pset);
+    let new_txid = wallet.send(&mut pset);
+    let new_tx = wallet.wollet.transaction(&new_txid).unwrap().unwrap();
+    assert!(new_tx.fee > fee);
+    assert_eq!(new_tx.status, WalletTxStatus::Pending);
+    let old_tx = wallet.wollet.transaction(&txid).unwrap().unwrap();
+    assert_eq!(old_tx.status, WalletTxStatus::Replaced(new_txid));
+    let input = &original.inputs()[0];
+    let outpoint = OutPoint::new(input.previous_txid, input.previous_output_index);
+    assert!(new_tx
+        .tx
+        .input
+        .iter()
+        .any(|i| i.previous_output == outpoint));
+
+    // Confirmed transactions can't be replaced
+    let height = wallet.tip().height();
+    server.generate(1);
+    wallet.wait_height(height + 1);
+    let err = wallet.wollet.bump_fee(&new_txid, 2_000.0).unwrap_err();
+    assert!(matches!(err, Error::TxAlreadyConfirmed(_)));
+
+    // Once the replacement is confirmed the original transaction is double spent
+    let old_tx = wallet.wollet.transaction(&txid).unwrap().unwrap();
+    assert_eq!(old_tx.status, WalletTxStatus::Conflicted(new_txid));
+    let txs = wallet.wollet.transactions().unwrap();
+    assert!(txs.iter().any(|tx| tx.txid == txid));
+}
+
+#[test]
+fn bump_fee_with_descendants() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+
+    let mut wallet = TestWollet::new(&server.electrs.electrum_url, &desc);
+    wallet.fund_btc(&server);
+    let height = wallet.tip().height();
+    server.generate(1);
+    wallet.wait_height(height + 1);
+    let node_address = server.node_getnewaddress();
+
+    let mut pset = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .finish()
+        .unwrap();
+    let original = pset.clone();
+    wallet.sign(&signer, &mut pset);
+    let txid = wallet.send(&mut pset);
+    let parent = wallet.wollet.transaction(&txid).unwrap().unwrap();
+
+    // A child spends the change of the transaction to replace
+    let mut pset = wallet.tx_builder().cpfp(txid, 1_000.0).finish().unwrap();
+    wallet.sign(&signer, &mut pset);
+    let child_txid = wallet.send(&mut pset);
+    let child = wallet.wollet.transaction(&child_txid).unwrap().unwrap();
+
+    // The replacement evicts the child too, it must pay its fee as well
+    let err = wallet
+        .wollet
+        .bump_fee_with(&txid, 1_000.0, Some(&original), &[])
+        .unwrap_err();
+    assert!(
+        matches!(err, Error::BumpFeeTooLow { original, .. } if original == parent.fee + child.fee)
+    );
+
+    let mut pset = wallet
+        .wollet
+        .bump_fee_with(&txid, 5_000.0, Some(&original), &[])
+        .unwrap();
+    // Neither the outputs of the replaced transaction nor the ones of its child are spent
+    assert!(pset
+        .inputs()
+        .iter()
+        .all(|i| i.previous_txid != txid && i.previous_txid != child_txid));
+    wallet.sign(&signer, &mut pset);
+    let new_txid = wallet.send(&mut pset);
+    let new_tx = wallet.wollet.transaction(&new_txid).unwrap().unwrap();
+    assert!(new_tx.fee > parent.fee + child.fee);
+    let child_status = wallet
+        .wollet
+        .transaction(&child_txid)
+        .unwrap()
+        .map(|tx| tx.status);
+    assert_ne!(child_status, Some(WalletTxStatus::Pending));
+}
+
+#[test]
+fn cpfp() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+
+    let mut wallet = TestWollet::new(&server.electrs.electrum_url, &desc);
+    wallet.fund_btc(&server);
+    let utxos = wallet.wollet.utxos().unwrap();
+    assert_eq!(utxos.len(), 1);
+    let parent_txid = utxos[0].outpoint.txid;
+    let parent = wallet.wollet.transaction(&parent_txid).unwrap().unwrap();
+    assert!(parent.height.is_none());
+
+    // Excluded parent outputs are not spent
+    let err = wallet
+        .tx_builder()
+        .cpfp(parent_txid, 1_000.0)
+        .exclude_utxo(utxos[0].outpoint)
+        .finish()
+        .unwrap_err();
+    assert!(matches!(err, Error::CpfpNoSpendableOutput(_)));
+
+    let fee_rate = 1_000.0;
+    let mut pset = wallet
+        .tx_builder()
+        .cpfp(parent_txid, fee_rate)
+        .finish()
+        .unwrap();
+    let input = &pset.inputs()[0];
+    assert_eq!(input.previous_txid, parent_txid);
+    wallet.sign(&signer, &mut pset);
+    let txid = wallet.send(&mut pset);
+    let child = wallet.wollet.transaction(&txid).unwrap().unwrap();
+
+    // The package pays at least the target fee rate
+    let package_fee = (parent.fee + child.fee) as f32;
+    let package_vsize = ((parent.tx.weight() + child.tx.weight()) / 4) as f32;
+    assert!(package_fee >= package_vsize * fee_rate / 1000.0);
+    assert!(child.fee > parent.fee);
+
+    // Confirmed transactions don't need a child
+    let height = wallet.tip().height();
+    server.generate(1);
+    wallet.wait_height(height + 1);
+    let err = wallet
+        .tx_builder()
+        .cpfp(txid, fee_rate)
+        .finish()
+        .unwrap_err();
+    assert!(matches!(err, Error::TxAlreadyConfirmed(_)));
+}
+
+#[test]
+fn fee_target() {
+    let server = setup(false);
+    let mut wallet = TestWollet::with_test_desc(&server.electrs.electrum_url);
+    wallet.fund_btc(&server);
+    let node_address = server.node_getnewaddress();
+
+    let electrum_url = ElectrumUrl::new(&server.electrs.electrum_url, false, false);
+    let client = ElectrumClient::new(&electrum_url).unwrap();
+    let fee_rate = client.estimate_fee(2).unwrap();
+    assert!(fee_rate >= FeeRate::MIN_RELAY);
+
+    let fee = |pset: &elements::pset::PartiallySignedTransaction| {
+        pset.outputs().last().unwrap().amount.unwrap()
+    };
+    let pset = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .fee_target(2, &client)
+        .unwrap()
+        .finish()
+        .unwrap();
+    let expected = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .fee_rate(Some(fee_rate.to_sat_kvb()))
+        .finish()
+        .unwrap();
+    assert_eq!(fee(&pset), fee(&expected));
+}
+
+#[test]
+fn fee_policy() {
+    let server = setup(false);
+    let mut wallet = TestWollet::with_test_desc(&server.electrs.electrum_url);
+    wallet.fund_btc(&server);
+    let node_address = server.node_getnewaddress();
+    assert_eq!(wallet.network().fee_policy(), FeePolicy::Vsize);
+
+    let build = |fee_policy| {
+        wallet
+            .tx_builder()
+            .add_lbtc_recipient(&node_address, 10_000)
+            .unwrap()
+            .fee_rate(Some(1_000.0))
+            .fee_policy(fee_policy)
+            .finish()
+            .unwrap()
+    };
+    let fee = |pset: &elements::pset::PartiallySignedTransaction| {
+        pset.outputs().last().unwrap().amount.unwrap()
+    };
+
+    // Confidential outputs are much cheaper with the discounted vsize
+    let pset = build(FeePolicy::Vsize);
+    let discounted_pset = build(FeePolicy::DiscountedVsize);
+    assert!(fee(&discounted_pset) < fee(&pset));
+
+    let tx = discounted_pset.extract_tx().unwrap();
+    let discounted_vsize = FeePolicy::DiscountedVsize.vsize(&tx);
+    assert!(discounted_vsize < FeePolicy::Vsize.vsize(&tx));
+    assert!(fee(&discounted_pset) >= discounted_vsize as u64);
+}
+
+#[test]
+fn balance_details() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let mut wallet = TestWollet::new(&server.electrs.electrum_url, &desc);
+    let policy_asset = wallet.policy_asset();
+    let satoshi = |map: &std::collections::BTreeMap<elements::AssetId, u64>| {
+        map.get(&policy_asset).cloned().unwrap_or(0)
+    };
+
+    // Funds received from others are untrusted until confirmed
+    wallet.fund_btc(&server);
+    let balance = wallet.wollet.balance_details().unwrap();
+    assert_eq!(satoshi(&balance.confirmed), 0);
+    assert_eq!(satoshi(&balance.untrusted_pending), 1_000_000);
+    assert_eq!(balance.total(), wallet.wollet.balance().unwrap());
+    assert_eq!(satoshi(&balance.spendable()), 0);
+
+    let height = wallet.tip().height();
+    server.generate(1);
+    wallet.wait_height(height + 1);
+    let balance = wallet.wollet.balance_details().unwrap();
+    assert_eq!(satoshi(&balance.confirmed), 1_000_000);
+    assert_eq!(satoshi(&balance.untrusted_pending), 0);
+
+    // Our own change is trusted
+    let node_address = server.node_getnewaddress();
+    let mut pset = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .finish()
+        .unwrap();
+    wallet.sign(&signer, &mut pset);
+    wallet.send(&mut pset);
+    let balance = wallet.wollet.balance_details().unwrap();
+    assert_eq!(satoshi(&balance.confirmed), 0);
+    assert_eq!(satoshi(&balance.untrusted_pending), 0);
+    let change = satoshi(&balance.trusted_pending);
+    assert!(change > 0);
+    assert_eq!(satoshi(&balance.spendable()), change);
+
+    // Locked UTXOs are counted apart
+    let utxos = wallet.wollet.utxos().unwrap();
+    wallet.wollet.freeze_utxos(&[utxos[0].outpoint]).unwrap();
+    let balance = wallet.wollet.balance_details().unwrap();
+    assert_eq!(satoshi(&balance.trusted_pending), 0);
+    assert_eq!(satoshi(&balance.locked), change);
+    assert_eq!(balance.total(), wallet.wollet.balance().unwrap());
+}
+
+#[test]
+fn spend_unconfirmed() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let mut wallet = TestWollet::new(&server.electrs.electrum_url, &desc);
+    let node_address = server.node_getnewaddress();
+    let insufficient = |err: Error| assert!(matches!(err, Error::InsufficientFunds));
+
+    // Funds received from others can only be spent with SpendUnconfirmed::All
+    wallet.fund_btc(&server);
+    for spend_unconfirmed in [SpendUnconfirmed::None, SpendUnconfirmed::OwnOnly] {
+        let builder = || wallet.tx_builder().spend_unconfirmed(spend_unconfirmed);
+        insufficient(
+            builder()
+                .add_lbtc_recipient(&node_address, 10_000)
+                .unwrap()
+                .finish()
+                .unwrap_err(),
+        );
+        insufficient(
+            builder()
+                .drain_lbtc_wallet()
+                .drain_lbtc_to(node_address.clone())
+                .finish()
+                .unwrap_err(),
+        );
+        insufficient(
+            builder()
+                .issue_asset(10, None, 1, None, None)
+                .unwrap()
+                .finish()
+                .unwrap_err(),
+        );
+    }
+    wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .finish()
+        .unwrap();
+
+    // Confirmed UTXOs need the minimum number of confirmations
+    let height = wallet.tip().height();
+    server.generate(1);
+    wallet.wait_height(height + 1);
+    let err = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .min_confirmations(2)
+        .finish()
+        .unwrap_err();
+    insufficient(err);
+    let mut pset = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .min_confirmations(1)
+        .spend_unconfirmed(SpendUnconfirmed::None)
+        .finish()
+        .unwrap();
+    wallet.sign(&signer, &mut pset);
+    wallet.send(&mut pset);
+
+    // Unconfirmed UTXOs are never selected if confirmations are required
+    let err = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .min_confirmations(1)
+        .finish()
+        .unwrap_err();
+    insufficient(err);
+
+    // Our own unconfirmed change can be spent with SpendUnconfirmed::OwnOnly
+    let err = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .spend_unconfirmed(SpendUnconfirmed::None)
+        .finish()
+        .unwrap_err();
+    insufficient(err);
+    wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .spend_unconfirmed(SpendUnconfirmed::OwnOnly)
+        .finish()
+        .unwrap();
+}
+
+#[test]
+fn apply_transaction() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let mut wallet = TestWollet::new(&server.electrs.electrum_url, &desc);
+    let node_address = server.node_getnewaddress();
+
+    wallet.fund_btc(&server);
+    let utxos = wallet.wollet.utxos().unwrap();
+    assert_eq!(utxos.len(), 1);
+
+    let mut pset = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .finish()
+        .unwrap();
+    wallet.sign(&signer, &mut pset);
+    let tx = wallet.wollet.finalize(&mut pset).unwrap();
+    let electrum_client = ElectrumClient::new(&wallet.electrum_url).unwrap();
+    let txid = electrum_client.broadcast(&tx).unwrap();
+
+    // Without scanning, the spent utxo is replaced by the change
+    wallet.wollet.apply_transaction(tx.clone()).unwrap();
+    let new_utxos = wallet.wollet.utxos().unwrap();
+    assert_eq!(new_utxos.len(), 1);
+    assert_eq!(new_utxos[0].outpoint.txid, txid);
+    let wallet_tx = wallet.wollet.transaction(&txid).unwrap().unwrap();
+    assert_eq!(wallet_tx.status, WalletTxStatus::Pending);
+    assert!(wallet_tx.height.is_none());
+
+    // Applying it twice is a no-op
+    let updates = wallet.wollet.updates().unwrap().len();
+    wallet.wollet.apply_transaction(tx).unwrap();
+    assert_eq!(wallet.wollet.updates().unwrap().len(), updates);
+
+    // The next transaction spends the change
+    let pset = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .finish()
+        .unwrap();
+    assert!(pset.inputs().iter().all(|i| i.previous_txid == txid));
+
+    // Transactions not involving the wallet are rejected
+    let other_signer = generate_signer();
+    let other_desc = format!("ct({},elwpkh({}/*))", view_key, other_signer.xpub());
+    let mut other_wallet = TestWollet::new(&server.electrs.electrum_url, &other_desc);
+    other_wallet.fund_btc(&server);
+    let other_tx = other_wallet.wollet.transactions().unwrap()[0].tx.clone();
+    let err = wallet.wollet.apply_transaction(other_tx).unwrap_err();
+    assert!(matches!(err, Error::TxNotRelevant(_)));
+
+    // The applied transaction is persisted
+    TestWollet::check_persistence(wallet);
+}
+
+#[test]
+fn apply_transaction_replacement() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let mut wallet = TestWollet::new(&server.electrs.electrum_url, &desc);
+    let node_address = server.node_getnewaddress();
+    wallet.fund_btc(&server);
+    let electrum_client = ElectrumClient::new(&wallet.electrum_url).unwrap();
+
+    let mut pset = wallet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 10_000)
+        .unwrap()
+        .finish()
+        .unwrap();
+    let original = pset.clone();
+    wallet.sign(&signer, &mut pset);
+    let tx = wallet.wollet.finalize(&mut pset).unwrap();
+    let txid = electrum_client.broadcast(&tx).unwrap();
+    wallet.wollet.apply_transaction(tx).unwrap();
+
+    // Without scanning, the replacement evicts the original transaction and its change
+    let mut pset = wallet
+        .wollet
+        .bump_fee_with(&txid, 1_000.0, Some(&original), &[])
+        .unwrap();
+    wallet.sign(&signer, &mut pset);
+    let tx = wallet.wollet.finalize(&mut pset).unwrap();
+    let new_txid = electrum_client.broadcast(&tx).unwrap();
+    wallet.wollet.apply_transaction(tx).unwrap();
+
+    let old_tx = wallet.wollet.transaction(&txid).unwrap().unwrap();
+    assert_eq!(old_tx.status, WalletTxStatus::Replaced(new_txid));
+    let utxos = wallet.wollet.utxos().unwrap();
+    assert_eq!(utxos.len(), 1);
+    assert_eq!(utxos[0].outpoint.txid, new_txid);
+    let balance = wallet.wollet.balance().unwrap();
+    assert_eq!(
+        balance.get(&wallet.policy_asset()),
+        Some(&utxos[0].unblinded.value)
+    );
+}
+
+#[test]
+fn incremental_sync() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let descriptor: WolletDescriptor = desc.parse().unwrap();
+    let network = network_regtest();
+    let electrum_url = ElectrumUrl::new(&server.electrs.electrum_url, false, false);
+    let mut client = ElectrumClient::new(&electrum_url)
+        .unwrap()
+        .with_incremental_sync();
+    let mut wollet = Wollet::new(network, NoPersist::new(), descriptor.clone()).unwrap();
+    full_scan_with_electrum_client(&mut wollet, &mut client).unwrap();
+    assert!(!client.has_notifications().unwrap());
+
+    // The server notifies the change of the status of the subscribed script
+    let address = wollet.address(None).unwrap().address().clone();
+    server.node_sendtoaddress(&address, 10_000, None);
+    let mut notified = false;
+    for _ in 0..50 {
+        if client.has_notifications().unwrap() {
+            notified = true;
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+    assert!(notified);
+
+    // Only the history of the changed script is downloaded
+    let update = client.full_scan(&wollet).unwrap().unwrap();
+    assert_eq!(update.scripts_status.len(), 1);
+    assert_eq!(update.scripts_status[0].0, address.script_pubkey());
+    assert_eq!(update.txid_height_new.len(), 1);
+    wollet.apply_update(update).unwrap();
+    assert_eq!(
+        *wollet
+            .balance()
+            .unwrap()
+            .get(&wollet.policy_asset())
+            .unwrap(),
+        10_000
+    );
+
+    // Nothing changed, the history of the unchanged scripts is taken from the store
+    let update = client.full_scan(&wollet).unwrap();
+    if let Some(update) = update {
+        assert!(update.only_tip());
+    }
+
+    // The confirmation changes the status
+    server.generate(1);
+    let mut update = None;
+    for _ in 0..50 {
+        update = client.full_scan(&wollet).unwrap();
+        if update.as_ref().map(|u| !u.only_tip()).unwrap_or(false) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+    let update = update.unwrap();
+    assert_eq!(update.scripts_status.len(), 1);
+    wollet.apply_update(update).unwrap();
+
+    // Same result of a wallet scanned without subscriptions
+    let mut client = ElectrumClient::new(&electrum_url).unwrap();
+    let mut other = Wollet::new(network, NoPersist::new(), descriptor).unwrap();
+    full_scan_with_electrum_client(&mut other, &mut client).unwrap();
+    assert_eq!(wollet.balance().unwrap(), other.balance().unwrap());
+    let heights = |w: &Wollet| -> Vec<_> {
+        w.transactions()
+            .unwrap()
+            .iter()
+            .map(|tx| (tx.txid, tx.height))
+            .collect()
+    };
+    assert_eq!(heights(&wollet), heights(&other));
+}
+
+#[cfg(feature = "electrum_async")]
+#[tokio::test]
+async fn async_electrum_client() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let descriptor: WolletDescriptor = desc.parse().unwrap();
+    let network = network_regtest();
+    let electrum_url = ElectrumUrl::new(&server.electrs.electrum_url, false, false);
+    let mut client = AsyncElectrumClient::new(&electrum_url).await.unwrap();
+    let mut wollet = Wollet::new(network, NoPersist::new(), descriptor.clone()).unwrap();
+    let update = client.full_scan(&wollet).await.unwrap().unwrap();
+    wollet.apply_update(update).unwrap();
+
+    let address = wollet.address(None).unwrap().address().clone();
+    let txid = server.node_sendtoaddress(&address, 10_000, None);
+    server.generate(1);
+    for _ in 0..50 {
+        if let Some(update) = client.full_scan(&wollet).await.unwrap() {
+            wollet.apply_update(update).unwrap();
+        }
+        if wollet
+            .transaction(&txid)
+            .unwrap()
+            .and_then(|tx| tx.height)
+            .is_some()
+        {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+    let tx = wollet.transaction(&txid).unwrap().unwrap();
+    assert!(tx.height.is_some());
+
+    // Same result of the sync scan
+    let mut sync_client = ElectrumClient::new(&electrum_url).unwrap();
+    let mut other = Wollet::new(network, NoPersist::new(), descriptor).unwrap();
+    full_scan_with_electrum_client(&mut other, &mut sync_client).unwrap();
+    assert_eq!(wollet.balance().unwrap(), other.balance().unwrap());
+    assert_eq!(wollet.tip().height(), other.tip().height());
+
+    let fee_rate = client.estimate_fee(2).await.unwrap();
+    assert!(fee_rate.to_sat_kvb() > 0.0);
+}
+
+#[cfg(feature = "elements_rpc")]
+#[test]
+fn elements_rpc_client() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let descriptor: WolletDescriptor = desc.parse().unwrap();
+    let network = network_regtest();
+    let auth = ElementsRpcAuth::CookieFile(server.node_cookie_file());
+    let mut client = ElementsRpcClient::new(&server.node_rpc_url(), auth).unwrap();
+    let mut wollet = Wollet::new(network, NoPersist::new(), descriptor.clone()).unwrap();
+
+    let update = client.full_scan(&wollet).unwrap().unwrap();
+    wollet.apply_update(update).unwrap();
+    assert_eq!(wollet.tip().height(), server.node_height() as u32);
+    assert!(wollet.transactions().unwrap().is_empty());
+
+    // An unconfirmed transaction
+    let address = wollet.address(None).unwrap().address().clone();
+    let txid = server.node_sendtoaddress(&address, 10_000, None);
+    let update = client.full_scan(&wollet).unwrap().unwrap();
+    wollet.apply_update(update).unwrap();
+    let tx = wollet.transaction(&txid).unwrap().unwrap();
+    assert!(tx.height.is_none());
+
+    // Confirmed, with a change address
+    let address = wollet.change(None).unwrap().address().clone();
+    let txid_change = server.node_sendtoaddress(&address, 20_000, None);
+    server.generate(1);
+    let update = client.full_scan(&wollet).unwrap().unwrap();
+    wollet.apply_update(update).unwrap();
+    for txid in [txid, txid_change] {
+        let tx = wollet.transaction(&txid).unwrap().unwrap();
+        assert_eq!(tx.height, Some(server.node_height() as u32));
+    }
+
+    // Spend and broadcast through the node
+    let node_address = server.node_getnewaddress();
+    let mut pset = wollet
+        .tx_builder()
+        .add_lbtc_recipient(&node_address, 5_000)
+        .unwrap()
+        .finish()
+        .unwrap();
+    signer.sign(&mut pset).unwrap();
+    let tx = wollet.finalize(&mut pset).unwrap();
+    let txid = client.broadcast(&tx).unwrap();
+    let update = client.full_scan(&wollet).unwrap().unwrap();
+    wollet.apply_update(update).unwrap();
+    assert!(wollet.transaction(&txid).unwrap().is_some());
+
+    // Same result of an electrum scan
+    let electrum_url = ElectrumUrl::new(&server.electrs.electrum_url, false, false);
+    let mut electrum_client = ElectrumClient::new(&electrum_url).unwrap();
+    let mut other = Wollet::new(network, NoPersist::new(), descriptor).unwrap();
+    for _ in 0..50 {
+        full_scan_with_electrum_client(&mut other, &mut electrum_client).unwrap();
+        if other.transaction(&txid).unwrap().is_some() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+    assert_eq!(wollet.balance().unwrap(), other.balance().unwrap());
+    assert_eq!(
+        wollet.transactions().unwrap().len(),
+        other.transactions().unwrap().len()
+    );
+}
+
+#[test]
+fn multi_backend() {
+    let server = setup(true);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let descriptor: WolletDescriptor = desc.parse().unwrap();
+    let network = network_regtest();
+    let electrum_url = ElectrumUrl::new(&server.electrs.electrum_url, false, false);
+    let esplora_url = format!("http://{}", server.electrs.esplora_url.as_ref().unwrap());
+
+    let mut wollet = Wollet::new(network, NoPersist::new(), descriptor.clone()).unwrap();
+    let address = wollet.address(None).unwrap().address().clone();
+    let txid = server.node_sendtoaddress(&address, 10_000, None);
+    server.generate(1);
+
+    // The first backend is down, the scan is done by the second one
+    let mut backend = MultiBackend::new()
+        .add(
+            "down",
+            EsploraClient::builder("http://127.0.0.1:1")
+                .max_retries(0)
+                .build()
+                .unwrap(),
+        )
+        .add("electrum", ElectrumClient::new(&electrum_url).unwrap())
+        .add("esplora", EsploraClient::new(&esplora_url));
+    for _ in 0..50 {
+        if let Some(update) = backend.full_scan(&wollet).unwrap() {
+            wollet.apply_update(update).unwrap();
+        }
+        if wollet
+            .transaction(&txid)
+            .unwrap()
+            .and_then(|tx| tx.height)
+            .is_some()
+        {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+    assert!(wollet.transaction(&txid).unwrap().is_some());
+    let health = backend.health();
+    assert!(health[0].consecutive_failures > 0);
+    assert!(health[0].last_error.is_some());
+    assert_eq!(health[1].consecutive_failures, 0);
+
+    // Honest backends agree
+    let mut backend = MultiBackend::new()
+        .add("electrum", ElectrumClient::new(&electrum_url).unwrap())
+        .add("esplora", EsploraClient::new(&esplora_url))
+        .with_cross_check();
+    let mut other = Wollet::new(network, NoPersist::new(), descriptor).unwrap();
+    let update = backend.full_scan(&other).unwrap().unwrap();
+    other.apply_update(update).unwrap();
+    assert_eq!(wollet.balance().unwrap(), other.balance().unwrap());
+    assert!(backend.health().iter().all(|h| !h.suspicious));
+    assert!(backend.health().iter().all(|h| h.tip_height.is_some()));
+}
+
+#[test]
+fn proxy_is_used() {
+    let server = setup(true);
+    let electrum_url = ElectrumUrl::new(&server.electrs.electrum_url, false, false);
+    let esplora_url = format!("http://{}", server.electrs.esplora_url.as_ref().unwrap());
+
+    // Connecting directly works, connecting through a proxy not listening fails
+    let options = ElectrumOptions {
+        socks5: Some("127.0.0.1:1".to_string()),
+        timeout: Some(5),
+    };
+    assert!(ElectrumClient::new_with_options(&electrum_url, &ElectrumOptions::default()).is_ok());
+    assert!(ElectrumClient::new_with_options(&electrum_url, &options).is_err());
+
+    let mut client = EsploraClient::builder(&esplora_url).build().unwrap();
+    assert!(client.tip().is_ok());
+    let mut client = EsploraClient::builder(&esplora_url)
+        .socks5("127.0.0.1:1")
+        .timeout(std::time::Duration::from_secs(5))
+        .max_retries(0)
+        .build()
+        .unwrap();
+    assert!(client.tip().is_err());
+}
+
+#[test]
+fn gap_limit() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/<0;1>/*))", view_key, signer.xpub());
+    let descriptor: WolletDescriptor = desc.parse().unwrap();
+    let network = network_regtest();
+    let electrum_url = ElectrumUrl::new(&server.electrs.electrum_url, false, false);
+    let mut client = ElectrumClient::new(&electrum_url).unwrap();
+    let tempdir = tempfile::tempdir().unwrap();
+    let mut wollet = Wollet::with_fs_persist(network, descriptor.clone(), &tempdir).unwrap();
+    assert_eq!(wollet.gap_limit(), DEFAULT_GAP_LIMIT);
+    assert!(matches!(
+        wollet.set_gap_limit(0),
+        Err(Error::InvalidGapLimit)
+    ));
+
+    // Funds beyond the default gap limit on both chains
+    let external = wollet.address(Some(30)).unwrap().address().clone();
+    let internal = wollet.change(Some(45)).unwrap().address().clone();
+    server.node_sendtoaddress(&external, 10_000, None);
+    server.node_sendtoaddress(&internal, 20_000, None);
+    server.generate(1);
+
+    let balance =
+        |w: &Wollet| -> u64 { *w.balance().unwrap().get(&w.policy_asset()).unwrap_or(&0) };
+    full_scan_with_electrum_client(&mut wollet, &mut client).unwrap();
+    assert_eq!(balance(&wollet), 0);
+
+    // A deep rescan finds them
+    let update = client
+        .full_scan_with_gap_limit(&wollet, 50)
+        .unwrap()
+        .unwrap();
+    wollet.apply_update(update).unwrap();
+    assert_eq!(balance(&wollet), 30_000);
+    assert_eq!(wollet.address(None).unwrap().index(), 31);
+    assert_eq!(wollet.change(None).unwrap().index(), 46);
+
+    // Following scans with the default gap limit keep them
+    if let Some(update) = client.full_scan(&wollet).unwrap() {
+        assert!(update.txid_height_delete.is_empty());
+        wollet.apply_update(update).unwrap();
+    }
+    assert_eq!(balance(&wollet), 30_000);
+
+    // The gap limit is persisted with the wallet
+    wollet.set_gap_limit(50).unwrap();
+    let mut other = Wollet::without_persist(network, descriptor.clone()).unwrap();
+    assert_eq!(other.gap_limit(), DEFAULT_GAP_LIMIT);
+    other.set_gap_limit(50).unwrap();
+    full_scan_with_electrum_client(&mut other, &mut client).unwrap();
+    assert_eq!(balance(&other), 30_000);
+    drop(wollet);
+    let wollet = Wollet::with_fs_persist(network, descriptor, &tempdir).unwrap();
+    assert_eq!(wollet.gap_limit(), 50);
+    assert_eq!(balance(&wollet), 30_000);
+}
+
+#[test]
+fn fs_persister_compaction() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let descriptor: WolletDescriptor = desc.parse().unwrap();
+    let network = network_regtest();
+    let electrum_url = ElectrumUrl::new(&server.electrs.electrum_url, false, false);
+    let mut client = ElectrumClient::new(&electrum_url).unwrap();
+    let tempdir = tempfile::tempdir().unwrap();
+    let persister = FsPersister::new(&tempdir, network, &descriptor).unwrap();
+    let mut wollet = Wollet::new(network, persister.clone(), descriptor.clone()).unwrap();
+
+    let balance =
+        |w: &Wollet| -> u64 { *w.balance().unwrap().get(&w.policy_asset()).unwrap_or(&0) };
+    for i in 1..=3 {
+        let address = wollet.address(None).unwrap().address().clone();
+        server.node_sendtoaddress(&address, 10_000, None);
+        server.generate(1);
+        for _ in 0..50 {
+            full_scan_with_electrum_client(&mut wollet, &mut client).unwrap();
+            if balance(&wollet) == i * 10_000 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(200));
+        }
+        assert_eq!(balance(&wollet), i * 10_000);
+    }
+    assert!(wollet.updates().unwrap().len() > 1);
+
+    // A wallet loaded from the compacted updates has the same content
+    persister.compact().unwrap();
+    assert_eq!(wollet.updates().unwrap().len(), 1);
+    let persister = FsPersister::new(&tempdir, network, &descriptor).unwrap();
+    let other = Wollet::new(network, persister, descriptor).unwrap();
+    assert_eq!(other.status(), wollet.status());
+    assert_eq!(other.balance().unwrap(), wollet.balance().unwrap());
+}
+
+#[test]
+fn pruned_cache() {
+    let server = setup(false);
+    let signer = generate_signer();
+    let view_key = generate_view_key();
+    let desc = format!("ct({},elwpkh({}/*))", view_key, signer.xpub());
+    let signers = [&AnySigner::Software(signer)];
+
+    let mut wallet = TestWollet::new(&server.electrs.electrum_url, &desc);
+    wallet.fund_btc(&server);
+    let txid = wallet.wollet.transactions().unwrap()[0].txid;
+    assert!(!wallet.wollet.is_transaction_pruned(&txid));
+    let status = wallet.wollet.status();
+    let balance = wallet.wollet.balance().unwrap();
+
+    // Pruning keeps the content of the wallet
+    wallet.wollet.set_pruned_cache(true);
+    assert!(wallet.wollet.pruned_cache());
+    assert!(wallet.wollet.is_transaction_pruned(&txid));
+    assert_eq!(wallet.wollet.status(), status);
+    assert_eq!(wallet.wollet.balance().unwrap(), balance);
+    let tx = wallet.wollet.transaction(&txid).unwrap().unwrap().tx;
+    let utxos = wallet.wollet.utxos().unwrap();
+    for (vout, output) in tx.output.iter().enumerate() {
+        assert!(output.witness.surjection_proof.is_none());
+        let outpoint = OutPoint::new(txid, vout as u32);
+        let is_utxo = utxos.iter().any(|u| u.outpoint == outpoint);
+        assert_eq!(output.witness.rangeproof.is_some(), is_utxo);
+    }
+
+    // Following updates are pruned and the wallet outputs can be spent
+    wallet.fund_btc(&server);
+    wallet.send_btc(&signers, None, None);
+    let txs = wallet.wollet.transactions().unwrap();
+    assert!(txs
+        .iter()
+        .all(|tx| wallet.wollet.is_transaction_pruned(&tx.txid)));
+
+    // The full transaction is downloaded again when needed
+    let client = ElectrumClient::new(&wallet.electrum_url).unwrap();
+    let full = client.full_transaction(&wallet.wollet, &txid).unwrap();
+    assert_eq!(full.txid(), txid);
+    assert!(full
+        .output
+        .iter()
+        .any(|o| o.witness.surjection_proof.is_some()));
+    assert_eq!(full.output.len(), tx.output.len());
+}
+
+#[test]
+fn reorg() {
+    let server = setup(false);
+    let mut wallet = TestWollet::with_test_desc(&server.electrs.electrum_url);
+    wallet.fund_btc(&server);
+    let txid = wallet.wollet.transactions().unwrap()[0].txid;
+    let height = wallet.tip().height();
+    server.generate(1);
+    wallet.wait_height(height + 1);
+    let tx = wallet.wollet.transaction(&txid).unwrap().unwrap();
+    assert_eq!(tx.height, Some(height + 1));
+    let old_hash = wallet.tip().hash();
+    assert!(wallet.wollet.take_events().is_empty());
+
+    // Replace the block confirming the transaction, which is confirmed again in the new chain
+    server.node_invalidateblock((height + 1) as u64);
+    server.generate(2);
+    wallet.wait_height(height + 2);
+    assert_eq!(
+        wallet.wollet.take_events(),
+        vec![WolletEvent::Reorg(Reorg {
+            height: height + 1,
+            depth: 1
+        })]
+    );
+    let tx = wallet.wollet.transaction(&txid).unwrap().unwrap();
+    assert_eq!(tx.height, Some(height + 1));
+    assert_eq!(tx.status, WalletTxStatus::Confirmed);
+
+    // The wallet is the same as a wallet synced from scratch on the new chain
+    let mut other = TestWollet::with_test_desc(&server.electrs.electrum_url);
+    other.sync();
+    assert_eq!(other.tip().hash(), wallet.tip().hash());
+    assert_ne!(old_hash, wallet.tip().hash());
+    assert_eq!(
+        other.wollet.balance().unwrap(),
+        wallet.wollet.balance().unwrap()
+    );
+    let heights = |w: &TestWollet| -> Vec<_> {
+        let txs = w.wollet.transactions().unwrap();
+        txs.iter()
+            .map(|tx| (tx.txid, tx.height, tx.timestamp))
+            .collect()
+    };
+    assert_eq!(heights(&other), heights(&wallet));
+}